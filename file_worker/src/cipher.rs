@@ -0,0 +1,583 @@
+use std::fmt;
+
+use aes::Aes256;
+use aes::cipher::KeyIvInit;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use ctr::Ctr128BE;
+use rand::Rng;
+use sha2::{Sha256, digest::Digest};
+
+pub type Aes256Ctr = Ctr128BE<Aes256>;
+
+pub const KEY_LENGTH: usize = 32;
+pub const NONCE_LENGTH: usize = 16;
+
+/// Начало заголовка блоба. Блобы старого формата (AES-256-CTR) заголовка не имеют,
+/// формат блоба хранится в БД и по наличию заголовка не определяется
+pub const BLOB_MAGIC: &[u8; 4] = b"FLXB";
+pub const BLOB_VERSION_SEGMENTED: u8 = 1;
+pub const BLOB_ALGORITHM_AES256_GCM: u8 = 1;
+/// Открытых байт в одном сегменте, кроме последнего
+pub const SEGMENT_SIZE: u32 = 64 * 1024;
+pub const TAG_LENGTH: usize = 16;
+const NONCE_PREFIX_LENGTH: usize = 7;
+/// magic, версия, алгоритм, размер сегмента (u32 BE), префикс nonce
+pub const HEADER_LENGTH: usize = BLOB_MAGIC.len() + 1 + 1 + 4 + NONCE_PREFIX_LENGTH;
+
+/// Префикс обернутого ключа: `kek:{id KEK}:{hex(nonce || ciphertext)}`
+const WRAPPED_KEY_PREFIX: &str = "kek";
+/// Длина nonce AES-GCM
+const GCM_NONCE_LENGTH: usize = 12;
+
+#[derive(Debug)]
+pub enum KeyError {
+    InvalidHex,
+    UnknownKek(String),
+    Corrupted,
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::InvalidHex => write!(f, "key is not a valid hex string"),
+            KeyError::UnknownKek(id) => write!(f, "key is wrapped with unknown KEK {}", id),
+            KeyError::Corrupted => write!(f, "wrapped key is corrupted"),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+#[derive(Debug)]
+pub enum CipherError {
+    MissingHeader,
+    VersionMismatch { expected: u8, found: u8 },
+    UnsupportedFormat { version: u8, algorithm: u8 },
+    InvalidSegment(u64),
+    Authentication(u64),
+}
+
+impl fmt::Display for CipherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CipherError::MissingHeader => write!(f, "blob header is missing"),
+            CipherError::VersionMismatch { expected, found } => write!(
+                f,
+                "blob format version {} does not match stored version {}",
+                found, expected
+            ),
+            CipherError::UnsupportedFormat { version, algorithm } => write!(
+                f,
+                "unsupported blob format version {} algorithm {}",
+                version, algorithm
+            ),
+            CipherError::InvalidSegment(index) => {
+                write!(f, "segment {} has unexpected length", index)
+            }
+            CipherError::Authentication(index) => {
+                write!(f, "segment {} failed authentication", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CipherError {}
+
+/// Nonce объекта: первые 16 байт SHA256 от ключа
+pub fn derive_nonce(key: &[u8; KEY_LENGTH]) -> [u8; NONCE_LENGTH] {
+    let mut hasher = Sha256::new();
+    Digest::update(&mut hasher, key);
+    let result = hasher.finalize();
+    let mut nonce = [0u8; NONCE_LENGTH];
+    nonce.copy_from_slice(&result[..NONCE_LENGTH]);
+    nonce
+}
+
+/// AES-256-CTR шифр блобов старого формата по ключу данных, развернутому из `decode_key`
+pub fn init_cipher(key: &[u8; KEY_LENGTH]) -> Aes256Ctr {
+    let nonce = derive_nonce(key);
+    Aes256Ctr::new(key.into(), &nonce.into())
+}
+
+/// Заголовок блоба. Содержимое после него - сегменты по `segment_size` открытых байт,
+/// каждый зашифрован AES-256-GCM отдельно и заканчивается тегом.
+/// Nonce сегмента - префикс из заголовка, номер сегмента и флаг последнего сегмента,
+/// поэтому перестановка, обрезка и дописывание сегментов не проходят проверку
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobHeader {
+    pub version: u8,
+    pub algorithm: u8,
+    pub segment_size: u32,
+    nonce_prefix: [u8; NONCE_PREFIX_LENGTH],
+}
+
+impl BlobHeader {
+    /// Заголовок нового блоба со случайным префиксом nonce
+    pub fn generate() -> Self {
+        Self {
+            version: BLOB_VERSION_SEGMENTED,
+            algorithm: BLOB_ALGORITHM_AES256_GCM,
+            segment_size: SEGMENT_SIZE,
+            nonce_prefix: rand::rng().random(),
+        }
+    }
+
+    /// Заголовок из начала блоба, записанного версией формата `expected`.
+    /// Отсутствующий или поврежденный заголовок и другая версия - ошибка
+    pub fn parse(bytes: &[u8], expected: u8) -> Result<Self, CipherError> {
+        if bytes.len() < HEADER_LENGTH || !bytes.starts_with(BLOB_MAGIC) {
+            return Err(CipherError::MissingHeader);
+        }
+        let version = bytes[4];
+        let algorithm = bytes[5];
+        let segment_size = u32::from_be_bytes(bytes[6..10].try_into().unwrap());
+        if version != expected {
+            return Err(CipherError::VersionMismatch {
+                expected,
+                found: version,
+            });
+        }
+        if version != BLOB_VERSION_SEGMENTED
+            || algorithm != BLOB_ALGORITHM_AES256_GCM
+            || segment_size == 0
+        {
+            return Err(CipherError::UnsupportedFormat { version, algorithm });
+        }
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LENGTH];
+        nonce_prefix.copy_from_slice(&bytes[10..HEADER_LENGTH]);
+        Ok(Self {
+            version,
+            algorithm,
+            segment_size,
+            nonce_prefix,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_LENGTH] {
+        let mut bytes = [0u8; HEADER_LENGTH];
+        bytes[..4].copy_from_slice(BLOB_MAGIC);
+        bytes[4] = self.version;
+        bytes[5] = self.algorithm;
+        bytes[6..10].copy_from_slice(&self.segment_size.to_be_bytes());
+        bytes[10..].copy_from_slice(&self.nonce_prefix);
+        bytes
+    }
+
+    /// Число сегментов; у пустого файла один пустой сегмент
+    pub fn segment_count(&self, plaintext_len: u64) -> u64 {
+        plaintext_len.div_ceil(self.segment_size as u64).max(1)
+    }
+
+    /// Сегмент, в который попадает байт `offset` открытого содержимого
+    pub fn segment_index(&self, offset: u64) -> u64 {
+        offset / self.segment_size as u64
+    }
+
+    /// Открытых байт в сегменте `index`
+    pub fn segment_plain_len(&self, index: u64, plaintext_len: u64) -> usize {
+        let start = index * self.segment_size as u64;
+        plaintext_len
+            .saturating_sub(start)
+            .min(self.segment_size as u64) as usize
+    }
+
+    /// Смещение начала сегмента `index` в зашифрованном блобе
+    pub fn segment_offset(&self, index: u64) -> u64 {
+        HEADER_LENGTH as u64 + index * (self.segment_size as u64 + TAG_LENGTH as u64)
+    }
+
+    /// Полный размер зашифрованного блоба
+    pub fn encrypted_len(&self, plaintext_len: u64) -> u64 {
+        let last = self.segment_count(plaintext_len) - 1;
+        self.segment_offset(last)
+            + self.segment_plain_len(last, plaintext_len) as u64
+            + TAG_LENGTH as u64
+    }
+
+    fn nonce(&self, index: u64, last: bool) -> [u8; GCM_NONCE_LENGTH] {
+        let mut nonce = [0u8; GCM_NONCE_LENGTH];
+        nonce[..NONCE_PREFIX_LENGTH].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_LENGTH..GCM_NONCE_LENGTH - 1]
+            .copy_from_slice(&(index as u32).to_be_bytes());
+        nonce[GCM_NONCE_LENGTH - 1] = last as u8;
+        nonce
+    }
+}
+
+/// Шифрование и проверка сегментов одного блоба. Заголовок входит в AAD каждого сегмента
+#[derive(Clone)]
+pub struct BlobCipher {
+    cipher: Aes256Gcm,
+    header: BlobHeader,
+    plaintext_len: u64,
+}
+
+impl BlobCipher {
+    pub fn new(key: &[u8; KEY_LENGTH], header: BlobHeader, plaintext_len: u64) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(key.into()),
+            header,
+            plaintext_len,
+        }
+    }
+
+    pub fn header(&self) -> &BlobHeader {
+        &self.header
+    }
+
+    pub fn segment_count(&self) -> u64 {
+        self.header.segment_count(self.plaintext_len)
+    }
+
+    /// Ожидаемая длина зашифрованного сегмента вместе с тегом
+    pub fn segment_encrypted_len(&self, index: u64) -> usize {
+        self.header.segment_plain_len(index, self.plaintext_len) + TAG_LENGTH
+    }
+
+    pub fn encrypt_segment(&self, index: u64, plaintext: &[u8]) -> Result<Vec<u8>, CipherError> {
+        if index >= self.segment_count()
+            || plaintext.len() != self.header.segment_plain_len(index, self.plaintext_len)
+        {
+            return Err(CipherError::InvalidSegment(index));
+        }
+        let nonce = self.nonce(index);
+        let header = self.header.to_bytes();
+        self.cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| CipherError::InvalidSegment(index))
+    }
+
+    pub fn decrypt_segment(&self, index: u64, ciphertext: &[u8]) -> Result<Vec<u8>, CipherError> {
+        if index >= self.segment_count() || ciphertext.len() != self.segment_encrypted_len(index) {
+            return Err(CipherError::InvalidSegment(index));
+        }
+        let nonce = self.nonce(index);
+        let header = self.header.to_bytes();
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| CipherError::Authentication(index))
+    }
+
+    fn nonce(&self, index: u64) -> [u8; GCM_NONCE_LENGTH] {
+        self.header.nonce(index, index + 1 == self.segment_count())
+    }
+}
+
+/// Новый случайный ключ данных для одного блоба
+pub fn generate_data_key() -> [u8; KEY_LENGTH] {
+    rand::rng().random()
+}
+
+fn decode_key_hex(hex_key: &str) -> Result<[u8; KEY_LENGTH], KeyError> {
+    let mut key = [0u8; KEY_LENGTH];
+    hex::decode_to_slice(hex_key.trim(), &mut key).map_err(|_| KeyError::InvalidHex)?;
+    Ok(key)
+}
+
+/// Ключ шифрования ключей (KEK). Идентификатор - начало SHA256 от ключа,
+/// по нему обернутый ключ находит свой KEK
+#[derive(Clone)]
+pub struct Kek {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+impl Kek {
+    pub fn from_hex(hex_key: &str) -> Result<Self, KeyError> {
+        let key = decode_key_hex(hex_key)?;
+        let id = hex::encode(&Sha256::digest(key)[..8]);
+        Ok(Self {
+            id,
+            cipher: Aes256Gcm::new(&key.into()),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn wrap(&self, data_key: &[u8; KEY_LENGTH]) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, data_key.as_slice())
+            .expect("AES-GCM encryption of a data key cannot fail");
+        let mut wrapped = nonce.to_vec();
+        wrapped.extend_from_slice(&ciphertext);
+        format!(
+            "{}:{}:{}",
+            WRAPPED_KEY_PREFIX,
+            self.id,
+            hex::encode(wrapped)
+        )
+    }
+
+    fn unwrap(&self, wrapped_hex: &str) -> Result<[u8; KEY_LENGTH], KeyError> {
+        let wrapped = hex::decode(wrapped_hex).map_err(|_| KeyError::InvalidHex)?;
+        if wrapped.len() <= GCM_NONCE_LENGTH {
+            return Err(KeyError::Corrupted);
+        }
+        let (nonce, ciphertext) = wrapped.split_at(GCM_NONCE_LENGTH);
+        let data_key = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| KeyError::Corrupted)?;
+        data_key.try_into().map_err(|_| KeyError::Corrupted)
+    }
+}
+
+/// Текущий KEK и, на время ротации, предыдущий.
+/// Новые ключи оборачиваются текущим, развернуть можно ключ под любым из двух
+#[derive(Clone)]
+pub struct Keyring {
+    current: Kek,
+    previous: Option<Kek>,
+}
+
+impl Keyring {
+    pub fn new(current: Kek, previous: Option<Kek>) -> Self {
+        Self { current, previous }
+    }
+
+    pub fn from_hex(current: &str, previous: Option<&str>) -> Result<Self, KeyError> {
+        let current = Kek::from_hex(current)?;
+        let previous = previous.map(Kek::from_hex).transpose()?;
+        Ok(Self::new(current, previous))
+    }
+
+    pub fn current_id(&self) -> &str {
+        self.current.id()
+    }
+
+    pub fn wrap(&self, data_key: &[u8; KEY_LENGTH]) -> String {
+        self.current.wrap(data_key)
+    }
+
+    /// Ключ данных из `decode_key`. Ключи, сохраненные до появления KEK
+    /// открытым hex, принимаются как есть до их переобертки
+    pub fn unwrap(&self, decode_key: &str) -> Result<[u8; KEY_LENGTH], KeyError> {
+        let mut parts = decode_key.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(WRAPPED_KEY_PREFIX), Some(id), Some(wrapped)) => {
+                let kek = [Some(&self.current), self.previous.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .find(|kek| kek.id() == id)
+                    .ok_or_else(|| KeyError::UnknownKek(id.to_string()))?;
+                kek.unwrap(wrapped)
+            }
+            _ => decode_key_hex(decode_key),
+        }
+    }
+
+    /// Ключ уже обернут текущим KEK
+    pub fn is_current(&self, decode_key: &str) -> bool {
+        decode_key.starts_with(&format!("{}:{}:", WRAPPED_KEY_PREFIX, self.current.id()))
+    }
+
+    /// Переобертка ключа текущим KEK
+    pub fn rewrap(&self, decode_key: &str) -> Result<String, KeyError> {
+        Ok(self.wrap(&self.unwrap(decode_key)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const S: u64 = SEGMENT_SIZE as u64;
+    const TAG: u64 = TAG_LENGTH as u64;
+    const HEADER: u64 = HEADER_LENGTH as u64;
+
+    fn encrypt_all(cipher: &BlobCipher, plaintext: &[u8]) -> Vec<Vec<u8>> {
+        (0..cipher.segment_count())
+            .map(|index| {
+                let start = (index * S) as usize;
+                let end = (start + S as usize).min(plaintext.len());
+                cipher
+                    .encrypt_segment(index, &plaintext[start..end])
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let header = BlobHeader::generate();
+        let bytes = header.to_bytes();
+        assert!(bytes.starts_with(BLOB_MAGIC));
+        assert_eq!(
+            BlobHeader::parse(&bytes, BLOB_VERSION_SEGMENTED).unwrap(),
+            header
+        );
+    }
+
+    #[test]
+    fn parse_requires_header() {
+        let mut damaged = BlobHeader::generate().to_bytes();
+        damaged[0] = b'X';
+        let short = &BlobHeader::generate().to_bytes()[..HEADER_LENGTH - 1];
+        for bytes in [&[][..], short, &damaged[..], &[0x5a; 64][..]] {
+            assert!(matches!(
+                BlobHeader::parse(bytes, BLOB_VERSION_SEGMENTED),
+                Err(CipherError::MissingHeader)
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_rejects_other_version() {
+        let mut bytes = BlobHeader::generate().to_bytes();
+        bytes[4] = 2;
+        assert!(matches!(
+            BlobHeader::parse(&bytes, BLOB_VERSION_SEGMENTED),
+            Err(CipherError::VersionMismatch {
+                expected: 1,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            BlobHeader::parse(&BlobHeader::generate().to_bytes(), 0),
+            Err(CipherError::VersionMismatch {
+                expected: 0,
+                found: 1
+            })
+        ));
+
+        let mut bytes = BlobHeader::generate().to_bytes();
+        bytes[5] = 9;
+        assert!(matches!(
+            BlobHeader::parse(&bytes, BLOB_VERSION_SEGMENTED),
+            Err(CipherError::UnsupportedFormat {
+                version: 1,
+                algorithm: 9
+            })
+        ));
+    }
+
+    #[test]
+    fn segment_layout() {
+        let header = BlobHeader::generate();
+        assert_eq!(header.segment_count(0), 1);
+        assert_eq!(header.segment_count(1), 1);
+        assert_eq!(header.segment_count(S), 1);
+        assert_eq!(header.segment_count(S + 1), 2);
+        assert_eq!(header.segment_count(3 * S), 3);
+
+        assert_eq!(header.segment_index(0), 0);
+        assert_eq!(header.segment_index(S - 1), 0);
+        assert_eq!(header.segment_index(S), 1);
+        assert_eq!(header.segment_index(2 * S + 5), 2);
+
+        assert_eq!(header.segment_plain_len(0, 0), 0);
+        assert_eq!(header.segment_plain_len(0, S + 10), S as usize);
+        assert_eq!(header.segment_plain_len(1, S + 10), 10);
+        assert_eq!(header.segment_plain_len(2, S + 10), 0);
+
+        assert_eq!(header.segment_offset(0), HEADER);
+        assert_eq!(header.segment_offset(1), HEADER + S + TAG);
+        assert_eq!(header.segment_offset(2), HEADER + 2 * (S + TAG));
+
+        assert_eq!(header.encrypted_len(0), HEADER + TAG);
+        assert_eq!(header.encrypted_len(S), HEADER + S + TAG);
+        assert_eq!(header.encrypted_len(S + 10), HEADER + S + TAG + 10 + TAG);
+    }
+
+    #[test]
+    fn segment_nonce() {
+        let header = BlobHeader::generate();
+        let nonce = header.nonce(3, false);
+        assert_eq!(nonce[..NONCE_PREFIX_LENGTH], header.nonce_prefix);
+        assert_eq!(nonce[NONCE_PREFIX_LENGTH..11], 3u32.to_be_bytes());
+        assert_eq!(nonce[11], 0);
+        assert_eq!(header.nonce(3, true)[11], 1);
+        assert_ne!(header.nonce(3, false), header.nonce(4, false));
+
+        let key = generate_data_key();
+        let cipher = BlobCipher::new(&key, header, 2 * S + 1);
+        assert_eq!(cipher.nonce(1), header.nonce(1, false));
+        assert_eq!(cipher.nonce(2), header.nonce(2, true));
+    }
+
+    #[test]
+    fn segments_round_trip() {
+        let key = generate_data_key();
+        let plaintext: Vec<u8> = (0..2 * S + 100).map(|i| i as u8).collect();
+        let cipher = BlobCipher::new(&key, BlobHeader::generate(), plaintext.len() as u64);
+        let segments = encrypt_all(&cipher, &plaintext);
+
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[2].len(), cipher.segment_encrypted_len(2));
+        assert_eq!(
+            HEADER + segments.iter().map(|s| s.len() as u64).sum::<u64>(),
+            cipher.header().encrypted_len(plaintext.len() as u64)
+        );
+        let decrypted: Vec<u8> = segments
+            .iter()
+            .enumerate()
+            .flat_map(|(index, segment)| cipher.decrypt_segment(index as u64, segment).unwrap())
+            .collect();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn empty_blob_has_one_segment() {
+        let key = generate_data_key();
+        let cipher = BlobCipher::new(&key, BlobHeader::generate(), 0);
+        let segment = cipher.encrypt_segment(0, &[]).unwrap();
+        assert_eq!(segment.len(), TAG_LENGTH);
+        assert!(cipher.decrypt_segment(0, &segment).unwrap().is_empty());
+    }
+
+    #[test]
+    fn tampered_segments_fail() {
+        let key = generate_data_key();
+        let plaintext = vec![7u8; 2 * S as usize];
+        let cipher = BlobCipher::new(&key, BlobHeader::generate(), plaintext.len() as u64);
+        let segments = encrypt_all(&cipher, &plaintext);
+
+        // Перестановка сегментов
+        assert!(matches!(
+            cipher.decrypt_segment(1, &segments[0]),
+            Err(CipherError::Authentication(1))
+        ));
+        // Обрезка: сегмент не был последним
+        let truncated = BlobCipher::new(&key, *cipher.header(), S);
+        assert!(matches!(
+            truncated.decrypt_segment(0, &segments[0]),
+            Err(CipherError::Authentication(0))
+        ));
+        // Измененный байт
+        let mut flipped = segments[1].clone();
+        flipped[0] ^= 1;
+        assert!(matches!(
+            cipher.decrypt_segment(1, &flipped),
+            Err(CipherError::Authentication(1))
+        ));
+        // Другой заголовок в AAD
+        let other = BlobCipher::new(&key, BlobHeader::generate(), plaintext.len() as u64);
+        assert!(other.decrypt_segment(0, &segments[0]).is_err());
+        // Неверная длина и сегмент за концом
+        assert!(matches!(
+            cipher.decrypt_segment(1, &segments[1][1..]),
+            Err(CipherError::InvalidSegment(1))
+        ));
+        assert!(matches!(
+            cipher.decrypt_segment(2, &segments[1]),
+            Err(CipherError::InvalidSegment(2))
+        ));
+    }
+}
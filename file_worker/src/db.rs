@@ -1,10 +1,18 @@
 use super::env::EnvironmentVariables;
 
-use sqlx::{Error, Pool, Postgres};
+use sqlx::{Error, Pool, Postgres, types::Uuid};
 use std::time::Duration;
 
 use sqlx::{self, postgres::PgPoolOptions};
 
+/// Состояние загрузки содержимого в S3, тип `uploadStatus` из миграций flaxum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "uploadStatus", rename_all = "lowercase")]
+pub enum UploadStatus {
+    Stored,
+    Failed,
+}
+
 #[derive(Debug, Clone)]
 pub struct Database {
     pool: Pool<Postgres>,
@@ -47,4 +55,92 @@ impl Database {
                 err
             })
     }
+
+    /// Статус блоба и всех ссылающихся на него объектов.
+    /// Строка блоба обновляется первой: дедупликация в flaxum блокирует ее на время
+    /// своей транзакции, поэтому новый объект либо скопирует уже новый статус, либо попадет в выборку
+    pub async fn set_blob_upload_status(
+        &self,
+        blob_id: &str,
+        status: UploadStatus,
+    ) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(r#"UPDATE "Blob" SET upload_status = $2 WHERE id = $1::uuid"#)
+            .bind(blob_id)
+            .bind(status)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r#"UPDATE "Object" SET upload_status = $2, upload_s3 = $3 WHERE blob_id = $1::uuid"#,
+        )
+        .bind(blob_id)
+        .bind(status)
+        .bind(status == UploadStatus::Stored)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    }
+
+    /// Статус файла робота
+    pub async fn set_robot_object_upload_status(
+        &self,
+        id: &str,
+        status: UploadStatus,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"UPDATE "RobotObject" SET upload_status = $2, upload_s3 = $3 WHERE id = $1::uuid"#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(status == UploadStatus::Stored)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Блоб уже загружен: повторно доставленное событие можно пропустить
+    pub async fn is_blob_stored(&self, blob_id: &str) -> Result<bool, Error> {
+        let stored: Option<bool> = sqlx::query_scalar(
+            r#"SELECT upload_status = 'stored' FROM "Blob" WHERE id = $1::uuid"#,
+        )
+        .bind(blob_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(stored.unwrap_or(false))
+    }
+
+    pub async fn is_robot_object_stored(&self, id: &str) -> Result<bool, Error> {
+        let stored: Option<bool> = sqlx::query_scalar(
+            r#"SELECT upload_status = 'stored' FROM "RobotObject" WHERE id = $1::uuid"#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(stored.unwrap_or(false))
+    }
+
+    /// Событие, исчерпавшее попытки или не разобранное, для просмотра и повтора из админки
+    pub async fn insert_dead_letter(
+        &self,
+        routing_key: &str,
+        object_id: Option<Uuid>,
+        payload: &str,
+        error: &str,
+        attempts: i32,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO "UploadDeadLetter" (routing_key, object_id, payload, error, attempts)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(routing_key)
+        .bind(object_id)
+        .bind(payload)
+        .bind(error)
+        .bind(attempts)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
 }
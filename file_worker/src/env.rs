@@ -2,10 +2,11 @@ use std::borrow::Cow;
 
 use anyhow::bail;
 
+use crate::storage::StorageConfig;
+
 #[derive(Clone, Debug)]
 pub struct EnvironmentVariables {
-    pub minio_url: Cow<'static, str>,
-    pub upload_main_bucket: Cow<'static, str>,
+    pub storage: StorageConfig,
 
     pub postgres_user: Cow<'static, str>,
     pub postgres_password: Cow<'static, str>,
@@ -18,6 +19,11 @@ pub struct EnvironmentVariables {
     pub rmq_port: u16,
     pub rmq_user: Cow<'static, str>,
     pub rmq_pass: Cow<'static, str>,
+
+    /// KEK в hex, которым оборачиваются ключи данных объектов
+    pub master_key: Cow<'static, str>,
+    /// Прежний KEK, нужен только на время ротации
+    pub master_key_previous: Option<Cow<'static, str>>,
 }
 
 impl EnvironmentVariables {
@@ -25,15 +31,8 @@ impl EnvironmentVariables {
         dotenv::dotenv().ok();
         // ---------------------- change to validator
         Ok(Self {
-            // S3
-            minio_url: match dotenv::var("MINIO_URL") {
-                Ok(url) => url.into(),
-                Err(err) => bail!("missing MINIO_URL: {err}"),
-            },
-            upload_main_bucket: match dotenv::var("UPLOAD_MAIN_BUCKET") {
-                Ok(bucket) => bucket.into(),
-                Err(err) => bail!("missing UPLOAD_MAIN_BUCKET: {err}"),
-            },
+            // Storage
+            storage: StorageConfig::from_env()?,
             // DB
             postgres_user: match dotenv::var("POSTGRES_USER") {
                 Ok(user) => user.into(),
@@ -68,6 +67,26 @@ impl EnvironmentVariables {
                 Ok(rmq_password) => rmq_password.into(),
                 Err(err) => bail!("missing RABBITMQ_DEFAULT_PASS: {err}"),
             },
+            // KEK
+            master_key: match read_secret("MASTER_KEY")? {
+                Some(key) => key.into(),
+                None => bail!("missing MASTER_KEY or MASTER_KEY_FILE"),
+            },
+            master_key_previous: read_secret("MASTER_KEY_PREVIOUS")?.map(Into::into),
         })
     }
 }
+
+/// Секрет из переменной `name` или из файла, путь к которому в `{name}_FILE`
+fn read_secret(name: &str) -> anyhow::Result<Option<String>> {
+    if let Ok(value) = dotenv::var(name) {
+        return Ok(Some(value));
+    }
+    match dotenv::var(format!("{name}_FILE")) {
+        Ok(path) => match std::fs::read_to_string(&path) {
+            Ok(value) => Ok(Some(value.trim().to_string())),
+            Err(err) => bail!("failed to read {name}_FILE {path}: {err}"),
+        },
+        Err(_) => Ok(None),
+    }
+}
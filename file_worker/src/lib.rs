@@ -1,5 +1,3 @@
-use aws_sdk_s3::Client as S3Client;
-
 use env::EnvironmentVariables;
 
 use rabbitmq::NotifierAmqp;
@@ -10,10 +8,11 @@ use tokio::sync::Notify;
 
 use db::DatabaseTrait;
 
+pub mod cipher;
 mod db;
 mod env;
 mod rabbitmq;
-mod s3;
+pub mod storage;
 
 static TMP_DIR: &str = "tmp";
 
@@ -33,7 +32,8 @@ fn parse_path(path: &str) -> String {
 struct Config {
     pub env: Arc<EnvironmentVariables>,
     pub db_conn: Arc<db::Database>,
-    pub s3_client: Arc<S3Client>,
+    pub storage: Arc<dyn storage::StorageBackend>,
+    pub keyring: Arc<cipher::Keyring>,
 }
 
 impl Config {
@@ -42,12 +42,15 @@ impl Config {
         let db_conn = db::Database::init(&env)
             .await
             .unwrap_or_else(|e| panic!("Database error {}", e));
-        let s3_client = s3::S3Client::init(&env).await;
+        let storage = env.storage.init().await?;
+        let keyring =
+            cipher::Keyring::from_hex(&env.master_key, env.master_key_previous.as_deref())?;
 
         Ok(Config {
             env: Arc::new(env),
             db_conn: Arc::new(db_conn),
-            s3_client: Arc::new(s3_client),
+            storage,
+            keyring: Arc::new(keyring),
         })
     }
 }
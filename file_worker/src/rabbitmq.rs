@@ -1,39 +1,57 @@
-use aes::cipher::KeyIvInit;
 use amqprs::{
-    BasicProperties, Deliver,
-    channel::{BasicAckArguments, BasicConsumeArguments, Channel},
+    BasicProperties, Deliver, FieldName, FieldTable, FieldValue,
+    channel::{
+        BasicAckArguments, BasicConsumeArguments, BasicNackArguments, BasicPublishArguments,
+        Channel, ExchangeDeclareArguments, ExchangeType, QueueBindArguments, QueueDeclareArguments,
+    },
     connection::{Connection, OpenConnectionArguments},
     consumer::AsyncConsumer,
 };
 use async_trait::async_trait;
-use aws_sdk_s3::{
-    primitives::ByteStream,
-    types::{CompletedMultipartUpload, CompletedPart},
-};
 use std::{
     fmt,
     fs::{self},
     sync::Arc,
+    time::Duration,
 };
+use tokio::io::AsyncReadExt;
 
-use tracing::{error, info};
+use sqlx::types::Uuid;
+use tracing::{error, info, warn};
 
 use crate::Config;
+use crate::cipher::{BlobCipher, BlobHeader, HEADER_LENGTH, SEGMENT_SIZE};
+use crate::db::UploadStatus;
+use crate::storage::StorageUpload;
 
 use super::env::EnvironmentVariables;
 use serde::{Deserialize, Serialize};
 
-use aes::Aes256;
-use ctr::Ctr128BE;
-use ctr::cipher::StreamCipher;
-use sha2::{Sha256, digest::Digest};
-
-type Aes256Ctr = Ctr128BE<Aes256>;
-
+/// Размер части multipart-загрузки и куска, который шифруется в памяти за раз.
+/// Кратен `SEGMENT_SIZE`, чтобы сегменты не разрывались между частями
 const CHUNK_SIZE: u64 = 1024 * 1024 * 8;
 
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 pub const QUEUE_EVENT_UPLOAD_USER: &str = "flaxum.upload.object.user";
 pub const ROUTING_KEY_EVENT_UPLOAD_USER: &str = "event.upload.user";
+pub const QUEUE_EVENT_UPLOAD_ROBOT: &str = "flaxum.upload.object.robot";
+pub const ROUTING_KEY_EVENT_UPLOAD_ROBOT: &str = "event.upload.robot";
+
+pub const EXCHANGE_UPLOAD_OBJECT: &str = "flaxum.upload.object";
+pub const EXCHANGE_UPLOAD_DEAD: &str = "flaxum.upload.object.dead";
+pub const QUEUE_EVENT_UPLOAD_DEAD: &str = "flaxum.upload.object.dead";
+
+/// Число уже сделанных повторов события
+const HEADER_RETRY_COUNT: &str = "x-retry-count";
+const HEADER_ERROR: &str = "x-error";
+/// Повторы после первой неудачи, n-й ждет `RETRY_BASE_DELAY_SECS * 4^(n-1)` секунд
+const MAX_RETRIES: u32 = 5;
+const RETRY_BASE_DELAY_SECS: u32 = 10;
+/// Пауза перед возвратом события в очередь, если переложить его в очередь повторов
+/// или dead-letter не удалось: у основных очередей нет DLX, без паузы событие сразу
+/// приходит снова
+const REROUTE_FAILURE_DELAY_SECS: u64 = 5;
 
 static TMP_DIR: &str = "tmp";
 
@@ -63,118 +81,283 @@ pub struct NotifierAmqp {
     pub conn_channel_list: Vec<ArcturusAmqpConnChannel>,
 }
 
+/// Чьи файлы загружает консьюмер: от этого зависят очередь и таблица со статусом
+#[derive(Debug, Clone, Copy)]
+enum UploadTarget {
+    User,
+    Robot,
+}
+
+impl UploadTarget {
+    fn queue(&self) -> &'static str {
+        match self {
+            UploadTarget::User => QUEUE_EVENT_UPLOAD_USER,
+            UploadTarget::Robot => QUEUE_EVENT_UPLOAD_ROBOT,
+        }
+    }
+
+    fn routing_key(&self) -> &'static str {
+        match self {
+            UploadTarget::User => ROUTING_KEY_EVENT_UPLOAD_USER,
+            UploadTarget::Robot => ROUTING_KEY_EVENT_UPLOAD_ROBOT,
+        }
+    }
+
+    /// Очередь ожидания перед `attempt`-м повтором: по истечении TTL событие
+    /// возвращается в `EXCHANGE_UPLOAD_OBJECT` с исходным ключом маршрутизации
+    fn retry_queue(&self, attempt: u32) -> String {
+        format!("{}.retry.{}", self.queue(), attempt)
+    }
+}
+
+fn retry_delay_ms(attempt: u32) -> u32 {
+    RETRY_BASE_DELAY_SECS * 4u32.pow(attempt - 1) * 1000
+}
+
+fn field_name(name: &str) -> FieldName {
+    FieldName::try_from(name).unwrap()
+}
+
+fn retry_count(properties: &BasicProperties) -> u32 {
+    let key = field_name(HEADER_RETRY_COUNT);
+    match properties.headers().and_then(|headers| headers.get(&key)) {
+        Some(FieldValue::l(count)) => *count as u32,
+        _ => 0,
+    }
+}
+
 struct FileUploaderConsumer {
     config: Arc<Config>,
+    target: UploadTarget,
 }
 
 impl FileUploaderConsumer {
-    pub fn new(config: Arc<Config>) -> Self {
-        FileUploaderConsumer { config }
+    fn new(config: Arc<Config>, target: UploadTarget) -> Self {
+        FileUploaderConsumer { config, target }
     }
 
-    pub async fn config(config: Arc<Config>, amqp: &NotifierAmqp) -> ArcturusAmqpConnChannel {
+    async fn config(
+        config: Arc<Config>,
+        amqp: &NotifierAmqp,
+        target: UploadTarget,
+    ) -> ArcturusAmqpConnChannel {
         let connection = amqp.connection().await;
         let channel = connection.open_channel(None).await.unwrap();
 
-        let args =
-            BasicConsumeArguments::new(QUEUE_EVENT_UPLOAD_USER, ROUTING_KEY_EVENT_UPLOAD_USER);
+        let args = BasicConsumeArguments::new(target.queue(), target.routing_key());
         channel
-            .basic_consume(FileUploaderConsumer::new(config), args)
+            .basic_consume(FileUploaderConsumer::new(config, target), args)
             .await
             .unwrap();
 
         ArcturusAmqpConnChannel(connection, channel)
     }
-    pub async fn send(&self, event: UploadUserEvent) -> Result<(), Box<dyn std::error::Error>> {
-        let upload_bucket = self.config.env.upload_main_bucket.clone();
-        let s3_path = format!("{}/{}", event.user_id, event.object_id);
-        let path_to_file = format!("{}{}{}.{}", TMP_DIR, PATH_SEPO, event.user_id, event.object_id);
-    
-        // Читаем и шифруем файл
-        let data = fs::read(&path_to_file)?;
-    
-        let mut key = [0u8; 32];
-        let nonce: [u8; 16] = {
-            hex::decode_to_slice(&event.key, &mut key)?;
-            let mut hasher = Sha256::new();
-            Digest::update(&mut hasher, key);
-            let result = hasher.finalize();
-            result[..16].try_into().unwrap()
-        };
-    
-        let mut cipher = Aes256Ctr::new(&key.into(), &nonce.into());
-        let mut enc_data = data.clone();
-        cipher.apply_keystream(&mut enc_data);
-    
-        let enc_path = format!("{}.enc", path_to_file);
-        fs::write(&enc_path, &enc_data)?;
-    
-        let size = enc_data.len() as u64;
-        let chunk_count = (size + CHUNK_SIZE - 1) / CHUNK_SIZE; 
-    
-        let multipart_upload_res = self
-            .config
-            .s3_client
-            .create_multipart_upload()
-            .bucket(upload_bucket.clone())
-            .key(&s3_path)
-            .send()
-            .await?;
-    
-        let upload_id = multipart_upload_res.upload_id().unwrap();
-        let mut upload_parts = Vec::new();
-    
-        for chunk_index in 0..chunk_count {
-            let start = chunk_index * CHUNK_SIZE;
-            let end = std::cmp::min(start + CHUNK_SIZE, size);
-            let chunk_size = end - start;
-    
-            let stream = ByteStream::read_from()
-                .path(&enc_path)
-                .offset(start)
-                .length(aws_sdk_s3::primitives::Length::Exact(chunk_size))
-                .build()
-                .await?;
-    
-            let upload_part_res = self
-                .config
-                .s3_client
-                .upload_part()
-                .bucket(upload_bucket.clone())
-                .key(&s3_path)
-                .upload_id(upload_id)
-                .part_number((chunk_index + 1) as i32)
-                .body(stream)
-                .send()
-                .await?;
-    
-            upload_parts.push(
-                CompletedPart::builder()
-                    .e_tag(upload_part_res.e_tag.unwrap_or_default())
-                    .part_number((chunk_index + 1) as i32)
-                    .build(),
+
+    fn parse_event(&self, content: &[u8]) -> serde_json::Result<UploadUserEvent> {
+        match self.target {
+            UploadTarget::User => serde_json::from_slice::<UploadUserEvent>(content),
+            UploadTarget::Robot => {
+                serde_json::from_slice::<UploadRobotEvent>(content).map(UploadUserEvent::from)
+            }
+        }
+    }
+
+    /// Запись результата загрузки: для пользователя `object_id` события - id блоба
+    async fn store_status(&self, object_id: &str, status: UploadStatus) -> Result<(), sqlx::Error> {
+        match self.target {
+            UploadTarget::User => {
+                self.config
+                    .db_conn
+                    .set_blob_upload_status(object_id, status)
+                    .await
+            }
+            UploadTarget::Robot => {
+                self.config
+                    .db_conn
+                    .set_robot_object_upload_status(object_id, status)
+                    .await
+            }
+        }
+    }
+
+    async fn is_stored(&self, object_id: &str) -> Result<bool, sqlx::Error> {
+        match self.target {
+            UploadTarget::User => self.config.db_conn.is_blob_stored(object_id).await,
+            UploadTarget::Robot => self.config.db_conn.is_robot_object_stored(object_id).await,
+        }
+    }
+
+    fn tmp_path(event: &UploadUserEvent) -> String {
+        format!(
+            "{}{}{}.{}",
+            TMP_DIR, PATH_SEPO, event.user_id, event.object_id
+        )
+    }
+
+    /// Загрузка с записью статуса. Повторно доставленное событие уже загруженного
+    /// файла не отправляется в S3 второй раз. Временный файл удаляется только после
+    /// записи статуса, чтобы повтор или replay из админки могли его прочитать
+    async fn process(&self, event: &UploadUserEvent) -> Result<(), String> {
+        let stored = self
+            .is_stored(&event.object_id)
+            .await
+            .map_err(|err| err.to_string())?;
+        if stored {
+            info!("Object {} is already stored, skipping.", event.object_id);
+        } else {
+            self.send(event).await.map_err(|err| err.to_string())?;
+            self.store_status(&event.object_id, UploadStatus::Stored)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        let path_to_file = Self::tmp_path(event);
+        if let Err(err) = fs::remove_file(&path_to_file)
+            && err.kind() != std::io::ErrorKind::NotFound
+        {
+            error!("Failed to remove {}: {}", path_to_file, err);
+        }
+        Ok(())
+    }
+
+    /// Отложенный повтор через очередь ожидания `attempt`
+    async fn retry(
+        &self,
+        channel: &Channel,
+        content: &[u8],
+        attempt: u32,
+    ) -> Result<(), amqprs::error::Error> {
+        let mut headers = FieldTable::new();
+        headers.insert(
+            field_name(HEADER_RETRY_COUNT),
+            FieldValue::l(attempt as i64),
+        );
+        let args = BasicPublishArguments::new("", &self.target.retry_queue(attempt));
+        channel
+            .basic_publish(
+                BasicProperties::default()
+                    .with_persistence(true)
+                    .with_headers(headers)
+                    .finish(),
+                content.to_vec(),
+                args,
+            )
+            .await
+    }
+
+    /// Событие без шансов на успех уходит в `EXCHANGE_UPLOAD_DEAD` и в таблицу
+    /// `UploadDeadLetter`, разобранное событие получает статус `failed`
+    async fn dead_letter(
+        &self,
+        channel: &Channel,
+        event: Option<&UploadUserEvent>,
+        content: &[u8],
+        reason: &str,
+        attempts: u32,
+    ) -> Result<(), amqprs::error::Error> {
+        if let Some(event) = event
+            && let Err(err) = self
+                .store_status(&event.object_id, UploadStatus::Failed)
+                .await
+        {
+            error!(
+                "Failed to store upload status of object {}: {}",
+                event.object_id, err
             );
         }
-    
-        // Завершаем загрузку
-        let completed_upload = CompletedMultipartUpload::builder()
-            .set_parts(Some(upload_parts))
-            .build();
-    
-        self.config
-            .s3_client
-            .complete_multipart_upload()
-            .bucket(upload_bucket)
-            .key(s3_path)
-            .upload_id(upload_id)
-            .multipart_upload(completed_upload)
-            .send()
-            .await?;
-    
-        // Удаляем временные файлы
-        fs::remove_file(&path_to_file)?;
-        fs::remove_file(&enc_path)?;
-    
+        let object_id = event.and_then(|event| Uuid::parse_str(&event.object_id).ok());
+        if let Err(err) = self
+            .config
+            .db_conn
+            .insert_dead_letter(
+                self.target.routing_key(),
+                object_id,
+                &String::from_utf8_lossy(content),
+                reason,
+                attempts as i32,
+            )
+            .await
+        {
+            error!("Failed to store dead-lettered upload: {}", err);
+        }
+
+        let mut headers = FieldTable::new();
+        headers.insert(
+            field_name(HEADER_RETRY_COUNT),
+            FieldValue::l(attempts as i64 - 1),
+        );
+        headers.insert(field_name(HEADER_ERROR), FieldValue::from(reason));
+        let args = BasicPublishArguments::new(EXCHANGE_UPLOAD_DEAD, self.target.routing_key());
+        channel
+            .basic_publish(
+                BasicProperties::default()
+                    .with_persistence(true)
+                    .with_headers(headers)
+                    .finish(),
+                content.to_vec(),
+                args,
+            )
+            .await
+    }
+
+    /// Потоковая загрузка: временный файл читается кусками по `CHUNK_SIZE`, каждый кусок
+    /// шифруется сегментами AES-256-GCM и уходит в хранилище отдельной частью, первая часть
+    /// начинается с заголовка блоба. В памяти одновременно находится не больше одного куска
+    pub async fn send(&self, event: &UploadUserEvent) -> Result<(), BoxError> {
+        let storage_key = format!("{}/{}", event.user_id, event.object_id);
+        let path_to_file = Self::tmp_path(event);
+
+        let mut file = tokio::fs::File::open(&path_to_file).await?;
+        let plaintext_len = file.metadata().await?.len();
+        let data_key = self.config.keyring.unwrap(&event.key)?;
+        let cipher = BlobCipher::new(&data_key, BlobHeader::generate(), plaintext_len);
+
+        let mut upload = self.config.storage.start_upload(&storage_key).await?;
+        if let Err(err) = Self::upload_parts(&mut file, &cipher, upload.as_mut()).await {
+            // Незавершенная загрузка иначе продолжит занимать место в хранилище
+            if let Err(abort_err) = upload.abort().await {
+                error!("Failed to abort upload of {}: {}", storage_key, abort_err);
+            }
+            return Err(err);
+        }
+        upload.complete().await?;
+
+        Ok(())
+    }
+
+    /// Части загрузки; пустой файл дает одну часть из заголовка и пустого сегмента
+    async fn upload_parts(
+        file: &mut tokio::fs::File,
+        cipher: &BlobCipher,
+        upload: &mut dyn StorageUpload,
+    ) -> Result<(), BoxError> {
+        let mut is_first = true;
+        let mut segment_index = 0;
+        loop {
+            let mut chunk = Vec::with_capacity(CHUNK_SIZE as usize);
+            file.take(CHUNK_SIZE).read_to_end(&mut chunk).await?;
+            if chunk.is_empty() && !is_first {
+                break;
+            }
+            let is_last = (chunk.len() as u64) < CHUNK_SIZE;
+
+            let mut part = Vec::with_capacity(chunk.len() + HEADER_LENGTH);
+            if is_first {
+                part.extend_from_slice(&cipher.header().to_bytes());
+            }
+            if chunk.is_empty() {
+                part.extend(cipher.encrypt_segment(segment_index, &[])?);
+            }
+            for segment in chunk.chunks(SEGMENT_SIZE as usize) {
+                part.extend(cipher.encrypt_segment(segment_index, segment)?);
+                segment_index += 1;
+            }
+
+            upload.write_part(part.into()).await?;
+            if is_last {
+                break;
+            }
+            is_first = false;
+        }
         Ok(())
     }
 }
@@ -195,15 +378,53 @@ impl AsyncConsumer for FileUploaderConsumer {
             content.len()
         );
 
-        if let Ok(event) = serde_json::from_str::<UploadUserEvent>(
-            String::from_utf8_lossy(&content).to_string().as_str(),
-        ) {
-            let _ = self.send(event).await;
-            info!("ack to delivery {} on channel {}", deliver, channel);
-            let args = BasicAckArguments::new(deliver.delivery_tag(), false);
-            channel.basic_ack(args).await.unwrap();
-        } else {
-            error!("Error when deserialize a UploadEvent.");
+        let retries = retry_count(&basic_properties);
+        let handled = match self.parse_event(&content) {
+            Ok(event) => match self.process(&event).await {
+                Ok(()) => Ok(()),
+                Err(err) if retries < MAX_RETRIES => {
+                    warn!(
+                        "Failed to upload object {} (attempt {}): {}",
+                        event.object_id,
+                        retries + 1,
+                        err
+                    );
+                    self.retry(channel, &content, retries + 1).await
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to upload object {} after {} attempts: {}",
+                        event.object_id,
+                        retries + 1,
+                        err
+                    );
+                    self.dead_letter(channel, Some(&event), &content, &err, retries + 1)
+                        .await
+                }
+            },
+            Err(err) => {
+                error!("Error when deserialize a UploadEvent: {}", err);
+                self.dead_letter(channel, None, &content, &err.to_string(), retries + 1)
+                    .await
+            }
+        };
+
+        match handled {
+            Ok(()) => {
+                info!("ack to delivery {} on channel {}", deliver, channel);
+                let args = BasicAckArguments::new(deliver.delivery_tag(), false);
+                if let Err(err) = channel.basic_ack(args).await {
+                    error!("Failed to ack delivery {}: {}", deliver, err);
+                }
+            }
+            Err(err) => {
+                error!("Failed to reroute delivery {}: {}", deliver, err);
+                tokio::time::sleep(Duration::from_secs(REROUTE_FAILURE_DELAY_SECS)).await;
+                let args = BasicNackArguments::new(deliver.delivery_tag(), false, true);
+                if let Err(err) = channel.basic_nack(args).await {
+                    error!("Failed to nack delivery {}: {}", deliver, err);
+                }
+            }
         }
     }
 }
@@ -221,11 +442,75 @@ impl NotifierAmqp {
 
     pub async fn init(&mut self, config: Arc<Config>) {
         info!("Starting upload worker-consumer.");
-        let mail_conn_channel = FileUploaderConsumer::config(config, self).await;
-        self.conn_channel_list.push(mail_conn_channel);
+        self.declare_retry_topology().await;
+        for target in [UploadTarget::User, UploadTarget::Robot] {
+            let conn_channel = FileUploaderConsumer::config(config.clone(), self, target).await;
+            self.conn_channel_list.push(conn_channel);
+        }
         info!("Finished upload worker-consumer.");
     }
 
+    /// Очереди ожидания повторов и dead-letter очередь. Основные очереди объявляет flaxum
+    async fn declare_retry_topology(&self) {
+        let connection = self.connection().await;
+        let channel = connection.open_channel(None).await.unwrap();
+
+        channel
+            .exchange_declare(
+                ExchangeDeclareArguments::new(
+                    EXCHANGE_UPLOAD_DEAD,
+                    ExchangeType::Fanout.to_string().as_str(),
+                )
+                .durable(true)
+                .finish(),
+            )
+            .await
+            .unwrap();
+        channel
+            .queue_declare(QueueDeclareArguments::durable_client_named(
+                QUEUE_EVENT_UPLOAD_DEAD,
+            ))
+            .await
+            .unwrap();
+        channel
+            .queue_bind(QueueBindArguments::new(
+                QUEUE_EVENT_UPLOAD_DEAD,
+                EXCHANGE_UPLOAD_DEAD,
+                "",
+            ))
+            .await
+            .unwrap();
+
+        for target in [UploadTarget::User, UploadTarget::Robot] {
+            for attempt in 1..=MAX_RETRIES {
+                let mut arguments = FieldTable::new();
+                arguments.insert(
+                    field_name("x-message-ttl"),
+                    FieldValue::I(retry_delay_ms(attempt) as i32),
+                );
+                arguments.insert(
+                    field_name("x-dead-letter-exchange"),
+                    FieldValue::from(EXCHANGE_UPLOAD_OBJECT),
+                );
+                arguments.insert(
+                    field_name("x-dead-letter-routing-key"),
+                    FieldValue::from(target.routing_key()),
+                );
+                channel
+                    .queue_declare(
+                        QueueDeclareArguments::durable_client_named(&target.retry_queue(attempt))
+                            .arguments(arguments)
+                            .finish(),
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+
+        channel.close().await.unwrap();
+        connection.close().await.unwrap();
+    }
+
     pub async fn connection(&self) -> Connection {
         Connection::open(&OpenConnectionArguments::new(
             self.amqp_host.to_owned().as_str(),
@@ -251,3 +536,13 @@ pub struct UploadRobotEvent {
     pub object_id: String,
     pub key: String,
 }
+
+impl From<UploadRobotEvent> for UploadUserEvent {
+    fn from(event: UploadRobotEvent) -> Self {
+        UploadUserEvent {
+            user_id: event.user_id,
+            object_id: event.object_id,
+            key: event.key,
+        }
+    }
+}
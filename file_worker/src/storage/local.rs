@@ -0,0 +1,269 @@
+use std::{
+    io,
+    ops::RangeInclusive,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use rand::{Rng, distr::Alphanumeric};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+
+use super::{
+    StorageBackend, StorageError, StorageStream, StorageUpload, StoredObject, StoredUpload,
+};
+
+/// Незавершенная загрузка пишется в `.part-{id}-{имя объекта}` рядом с объектом,
+/// такие файлы не видны в `list`
+const PART_PREFIX: &str = ".part-";
+const PART_ID_LENGTH: usize = 16;
+
+/// Хранилище в каталоге локальной файловой системы, ключ — относительный путь
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub async fn init(root: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&root).await?;
+        Ok(Self { root })
+    }
+
+    /// Путь объекта; ключи с `..`, абсолютные и пустые отклоняются
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let relative = Path::new(key);
+        let valid = !key.is_empty()
+            && relative.components().all(|component| match component {
+                Component::Normal(name) => !name.to_string_lossy().starts_with(PART_PREFIX),
+                _ => false,
+            });
+        if !valid {
+            return Err(StorageError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// Временный файл рядом с объектом, переименовывается в него после записи
+    async fn create_part(&self, key: &str) -> Result<(PathBuf, PathBuf, fs::File), StorageError> {
+        let path = self.path(key)?;
+        let dir = path.parent().unwrap_or(&self.root).to_path_buf();
+        fs::create_dir_all(&dir).await?;
+        let upload_id: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(PART_ID_LENGTH)
+            .map(char::from)
+            .collect();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let part_path = dir.join(format!("{PART_PREFIX}{upload_id}-{file_name}"));
+        let file = fs::File::create(&part_path).await?;
+        Ok((path, part_path, file))
+    }
+
+    /// Все файлы дерева: ключ и метаданные; у незавершенных загрузок ключ - путь `.part-` файла
+    async fn walk(&self) -> Result<Vec<(String, std::fs::Metadata)>, StorageError> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    dirs.push(entry.path());
+                    continue;
+                }
+                let path = entry.path();
+                let Ok(relative) = path.strip_prefix(&self.root) else {
+                    continue;
+                };
+                let key = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((key, metadata));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Ключ объекта и id загрузки по пути `.part-` файла
+    fn parse_part(part_key: &str) -> Option<(String, String)> {
+        let (dir, part_name) = match part_key.rsplit_once('/') {
+            Some((dir, part_name)) => (Some(dir), part_name),
+            None => (None, part_key),
+        };
+        let rest = part_name.strip_prefix(PART_PREFIX)?;
+        let file_name = rest.get(PART_ID_LENGTH..)?.strip_prefix('-')?;
+        let key = match dir {
+            Some(dir) => format!("{dir}/{file_name}"),
+            None => file_name.to_string(),
+        };
+        Some((key, part_name.to_string()))
+    }
+
+    fn map_not_found(key: &str, err: io::Error) -> StorageError {
+        match err.kind() {
+            io::ErrorKind::NotFound => StorageError::NotFound(key.to_string()),
+            _ => StorageError::Io(err),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError> {
+        let mut upload = self.start_upload(key).await?;
+        match upload.write_part(data).await {
+            Ok(()) => upload.complete().await,
+            Err(err) => {
+                upload.abort().await.ok();
+                Err(err)
+            }
+        }
+    }
+
+    async fn start_upload(&self, key: &str) -> Result<Box<dyn StorageUpload>, StorageError> {
+        let (path, part_path, file) = self.create_part(key).await?;
+        Ok(Box::new(LocalUpload {
+            path,
+            part_path,
+            file,
+        }))
+    }
+
+    async fn get(
+        &self,
+        key: &str,
+        range: Option<RangeInclusive<u64>>,
+    ) -> Result<StorageStream, StorageError> {
+        let mut file = fs::File::open(self.path(key)?)
+            .await
+            .map_err(|err| Self::map_not_found(key, err))?;
+        match range {
+            Some(range) => {
+                file.seek(io::SeekFrom::Start(*range.start())).await?;
+                let length = range.end().saturating_sub(*range.start()) + 1;
+                Ok(ReaderStream::new(file.take(length)).boxed())
+            }
+            None => Ok(ReaderStream::new(file).boxed()),
+        }
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let from_path = self.path(from)?;
+        let (path, part_path, file) = self.create_part(to).await?;
+        drop(file);
+        if let Err(err) = fs::copy(&from_path, &part_path).await {
+            fs::remove_file(&part_path).await.ok();
+            return Err(Self::map_not_found(from, err));
+        }
+        fs::rename(&part_path, &path).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, StorageError> {
+        let objects = self
+            .walk()
+            .await?
+            .into_iter()
+            .filter(|(key, _)| {
+                let file_name = key.rsplit('/').next().unwrap_or_default();
+                key.starts_with(prefix) && !file_name.starts_with(PART_PREFIX)
+            })
+            .map(|(key, metadata)| StoredObject {
+                key,
+                size: metadata.len(),
+                last_modified: metadata.modified().ok(),
+            })
+            .collect();
+        Ok(objects)
+    }
+
+    async fn list_uploads(&self) -> Result<Vec<StoredUpload>, StorageError> {
+        let uploads = self
+            .walk()
+            .await?
+            .into_iter()
+            .filter_map(|(part_key, metadata)| {
+                let (key, upload_id) = Self::parse_part(&part_key)?;
+                Some(StoredUpload {
+                    key,
+                    upload_id,
+                    initiated: metadata.modified().ok(),
+                })
+            })
+            .collect();
+        Ok(uploads)
+    }
+
+    async fn abort_upload(&self, upload: &StoredUpload) -> Result<(), StorageError> {
+        let path = self.path(&upload.key)?;
+        let expected = path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match Self::parse_part(&upload.upload_id) {
+            Some((file_name, _)) if file_name == expected => {}
+            _ => return Err(StorageError::InvalidKey(upload.upload_id.clone())),
+        }
+        let part_path = path.parent().unwrap_or(&self.root).join(&upload.upload_id);
+        match fs::remove_file(part_path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn presign_get(&self, _key: &str, _expires_in: Duration) -> Result<String, StorageError> {
+        Err(StorageError::Unsupported("presign"))
+    }
+}
+
+/// Запись во временный файл с переименованием при `complete`
+struct LocalUpload {
+    path: PathBuf,
+    part_path: PathBuf,
+    file: fs::File,
+}
+
+#[async_trait]
+impl StorageUpload for LocalUpload {
+    async fn write_part(&mut self, data: Bytes) -> Result<(), StorageError> {
+        self.file.write_all(&data).await?;
+        Ok(())
+    }
+
+    async fn complete(mut self: Box<Self>) -> Result<(), StorageError> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+        fs::rename(&self.part_path, &self.path).await?;
+        Ok(())
+    }
+
+    async fn abort(self: Box<Self>) -> Result<(), StorageError> {
+        drop(self.file);
+        match fs::remove_file(&self.part_path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fmt, io, ops::RangeInclusive, path::PathBuf, sync::Arc, time::Duration, time::SystemTime,
+};
+
+use anyhow::bail;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+
+mod local;
+mod s3;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+/// Содержимое объекта хранилища, читаемое по мере передачи
+pub type StorageStream = BoxStream<'static, io::Result<Bytes>>;
+
+#[derive(Debug)]
+pub enum StorageError {
+    NotFound(String),
+    InvalidKey(String),
+    Unsupported(&'static str),
+    Io(io::Error),
+    Backend(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::NotFound(key) => write!(f, "object {} not found", key),
+            StorageError::InvalidKey(key) => write!(f, "invalid object key {}", key),
+            StorageError::Unsupported(operation) => {
+                write!(f, "{} is not supported by this storage", operation)
+            }
+            StorageError::Io(err) => write!(f, "storage io error: {}", err),
+            StorageError::Backend(err) => write!(f, "storage error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(err: io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+/// Объект хранилища в результатах `list`
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<SystemTime>,
+}
+
+/// Незавершенная загрузка, начатая `start_upload`
+#[derive(Debug, Clone)]
+pub struct StoredUpload {
+    pub key: String,
+    pub upload_id: String,
+    pub initiated: Option<SystemTime>,
+}
+
+/// Хранилище зашифрованного содержимого. Ключи вида `{owner_id}/{blob_id}`
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError>;
+    /// Запись объекта по частям; объект появляется только после `complete`
+    async fn start_upload(&self, key: &str) -> Result<Box<dyn StorageUpload>, StorageError>;
+    /// Тело объекта целиком или диапазон байт `range` (включительно)
+    async fn get(
+        &self,
+        key: &str,
+        range: Option<RangeInclusive<u64>>,
+    ) -> Result<StorageStream, StorageError>;
+    /// Копия объекта внутри хранилища, без передачи тела через приложение
+    async fn copy(&self, from: &str, to: &str) -> Result<(), StorageError>;
+    /// Удаление отсутствующего объекта не считается ошибкой
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, StorageError>;
+    /// Загрузки, которые не были ни завершены, ни отменены
+    async fn list_uploads(&self) -> Result<Vec<StoredUpload>, StorageError>;
+    async fn abort_upload(&self, upload: &StoredUpload) -> Result<(), StorageError>;
+    /// Ссылка на скачивание без обращения к API
+    async fn presign_get(&self, key: &str, expires_in: Duration) -> Result<String, StorageError>;
+}
+
+#[async_trait]
+pub trait StorageUpload: Send {
+    async fn write_part(&mut self, data: Bytes) -> Result<(), StorageError>;
+    async fn complete(self: Box<Self>) -> Result<(), StorageError>;
+    async fn abort(self: Box<Self>) -> Result<(), StorageError>;
+}
+
+/// Выбор хранилища: `STORAGE_BACKEND=s3` (по умолчанию, `MINIO_URL` и `UPLOAD_MAIN_BUCKET`)
+/// или `STORAGE_BACKEND=local` с каталогом `STORAGE_LOCAL_PATH`
+#[derive(Clone, Debug)]
+pub enum StorageConfig {
+    S3 { endpoint: String, bucket: String },
+    Local { root: PathBuf },
+}
+
+const DEFAULT_STORAGE_LOCAL_PATH: &str = "storage";
+
+impl StorageConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let backend = dotenv::var("STORAGE_BACKEND").unwrap_or_else(|_| "s3".to_string());
+        match backend.as_str() {
+            "s3" => Ok(StorageConfig::S3 {
+                endpoint: match dotenv::var("MINIO_URL") {
+                    Ok(url) => url,
+                    Err(err) => bail!("missing MINIO_URL: {err}"),
+                },
+                bucket: match dotenv::var("UPLOAD_MAIN_BUCKET") {
+                    Ok(bucket) => bucket,
+                    Err(err) => bail!("missing UPLOAD_MAIN_BUCKET: {err}"),
+                },
+            }),
+            "local" => Ok(StorageConfig::Local {
+                root: dotenv::var("STORAGE_LOCAL_PATH")
+                    .unwrap_or_else(|_| DEFAULT_STORAGE_LOCAL_PATH.to_string())
+                    .into(),
+            }),
+            other => bail!("unknown STORAGE_BACKEND {other}, expected s3 or local"),
+        }
+    }
+
+    pub async fn init(&self) -> anyhow::Result<Arc<dyn StorageBackend>> {
+        match self {
+            StorageConfig::S3 { endpoint, bucket } => {
+                Ok(Arc::new(S3Storage::init(endpoint, bucket).await))
+            }
+            StorageConfig::Local { root } => Ok(Arc::new(LocalStorage::init(root.clone()).await?)),
+        }
+    }
+}
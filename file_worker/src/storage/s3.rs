@@ -0,0 +1,305 @@
+use std::{ops::RangeInclusive, time::Duration};
+
+use async_trait::async_trait;
+use aws_config::meta::region::RegionProviderChain;
+use aws_sdk_s3::{
+    Client,
+    config::Region,
+    error::{DisplayErrorContext, ProvideErrorMetadata},
+    presigning::PresigningConfig,
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use tokio_util::io::ReaderStream;
+
+use super::{
+    StorageBackend, StorageError, StorageStream, StorageUpload, StoredObject, StoredUpload,
+};
+
+const AWS_REGION: &str = "eu-central-1";
+
+fn backend_error<E: std::error::Error>(err: E) -> StorageError {
+    StorageError::Backend(DisplayErrorContext(err).to_string())
+}
+
+/// Хранилище в бакете S3 (MinIO)
+pub struct S3Storage {
+    client: Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    pub async fn init(endpoint: &str, bucket: &str) -> Self {
+        let region_provider = RegionProviderChain::first_try(Region::new(AWS_REGION));
+        let shared_config = aws_config::from_env()
+            .endpoint_url(endpoint)
+            .region(region_provider)
+            .load()
+            .await;
+
+        let storage = Self {
+            client: Client::new(&shared_config),
+            bucket: bucket.to_string(),
+        };
+        storage.init_bucket().await;
+        storage
+    }
+
+    async fn init_bucket(&self) {
+        let constraint = aws_sdk_s3::types::BucketLocationConstraint::from(AWS_REGION);
+        let cfg = aws_sdk_s3::types::CreateBucketConfiguration::builder()
+            .location_constraint(constraint)
+            .build();
+        let created_bucket = self
+            .client
+            .create_bucket()
+            .create_bucket_configuration(cfg)
+            .bucket(&self.bucket)
+            .send()
+            .await;
+        match created_bucket {
+            Ok(bucket) => tracing::info!("Created bucket {:?}", bucket.location),
+            Err(err) => match err.code() {
+                Some("BucketAlreadyOwnedByYou") | Some("BucketAlreadyExists") => {}
+                _ => tracing::warn!("Err create bucket, {}", DisplayErrorContext(err)),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn start_upload(&self, key: &str) -> Result<Box<dyn StorageUpload>, StorageError> {
+        let res = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        let upload_id = res
+            .upload_id()
+            .ok_or_else(|| StorageError::Backend("S3 did not return upload id".to_string()))?
+            .to_string();
+        Ok(Box::new(S3Upload {
+            client: self.client.clone(),
+            bucket: self.bucket.clone(),
+            key: key.to_string(),
+            upload_id,
+            parts: Vec::new(),
+        }))
+    }
+
+    async fn get(
+        &self,
+        key: &str,
+        range: Option<RangeInclusive<u64>>,
+    ) -> Result<StorageStream, StorageError> {
+        let res = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_range(range.map(|range| format!("bytes={}-{}", range.start(), range.end())))
+            .send()
+            .await
+            .map_err(|err| match err.as_service_error() {
+                Some(service_err) if service_err.is_no_such_key() => {
+                    StorageError::NotFound(key.to_string())
+                }
+                _ => backend_error(err),
+            })?;
+        Ok(ReaderStream::new(res.body.into_async_read()).boxed())
+    }
+
+    /// Один запрос `CopyObject`, S3 копирует объекты до 5 ГБ
+    async fn copy(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, from))
+            .key(to)
+            .send()
+            .await
+            .map_err(|err| match err.code() {
+                Some("NoSuchKey") => StorageError::NotFound(from.to_string()),
+                _ => backend_error(err),
+            })?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, StorageError> {
+        let mut objects = Vec::new();
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(backend_error)?;
+            for object in page.contents() {
+                let Some(key) = object.key() else {
+                    continue;
+                };
+                objects.push(StoredObject {
+                    key: key.to_string(),
+                    size: object.size().unwrap_or(0) as u64,
+                    last_modified: object
+                        .last_modified()
+                        .and_then(|time| std::time::SystemTime::try_from(*time).ok()),
+                });
+            }
+        }
+        Ok(objects)
+    }
+
+    async fn list_uploads(&self) -> Result<Vec<StoredUpload>, StorageError> {
+        let mut uploads = Vec::new();
+        let mut key_marker = None;
+        let mut upload_id_marker = None;
+        loop {
+            let page = self
+                .client
+                .list_multipart_uploads()
+                .bucket(&self.bucket)
+                .set_key_marker(key_marker.take())
+                .set_upload_id_marker(upload_id_marker.take())
+                .send()
+                .await
+                .map_err(backend_error)?;
+            for upload in page.uploads() {
+                let (Some(key), Some(upload_id)) = (upload.key(), upload.upload_id()) else {
+                    continue;
+                };
+                uploads.push(StoredUpload {
+                    key: key.to_string(),
+                    upload_id: upload_id.to_string(),
+                    initiated: upload
+                        .initiated()
+                        .and_then(|time| std::time::SystemTime::try_from(*time).ok()),
+                });
+            }
+            if !page.is_truncated().unwrap_or(false) {
+                break;
+            }
+            key_marker = page.next_key_marker().map(str::to_string);
+            upload_id_marker = page.next_upload_id_marker().map(str::to_string);
+        }
+        Ok(uploads)
+    }
+
+    async fn abort_upload(&self, upload: &StoredUpload) -> Result<(), StorageError> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&upload.key)
+            .upload_id(&upload.upload_id)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn presign_get(&self, key: &str, expires_in: Duration) -> Result<String, StorageError> {
+        let config = PresigningConfig::expires_in(expires_in).map_err(backend_error)?;
+        let request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(config)
+            .await
+            .map_err(backend_error)?;
+        Ok(request.uri().to_string())
+    }
+}
+
+/// Multipart-загрузка S3
+struct S3Upload {
+    client: Client,
+    bucket: String,
+    key: String,
+    upload_id: String,
+    parts: Vec<CompletedPart>,
+}
+
+#[async_trait]
+impl StorageUpload for S3Upload {
+    async fn write_part(&mut self, data: Bytes) -> Result<(), StorageError> {
+        let part_number = self.parts.len() as i32 + 1;
+        let res = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        self.parts.push(
+            CompletedPart::builder()
+                .e_tag(res.e_tag.unwrap_or_default())
+                .part_number(part_number)
+                .build(),
+        );
+        Ok(())
+    }
+
+    async fn complete(self: Box<Self>) -> Result<(), StorageError> {
+        let completed_upload = CompletedMultipartUpload::builder()
+            .set_parts(Some(self.parts))
+            .build();
+        self.client
+            .complete_multipart_upload()
+            .bucket(self.bucket)
+            .key(self.key)
+            .upload_id(self.upload_id)
+            .multipart_upload(completed_upload)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn abort(self: Box<Self>) -> Result<(), StorageError> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(self.bucket)
+            .key(self.key)
+            .upload_id(self.upload_id)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+CREATE TABLE "UploadSession" (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES "User"(id),
+    parent_id UUID REFERENCES "Object"(id) ON DELETE SET NULL,
+    name VARCHAR(255) NOT NULL,
+    mimetype VARCHAR(100),
+    size BIGINT NOT NULL,
+    upload_offset BIGINT NOT NULL DEFAULT 0,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    updated_at timestamp without time zone,
+    expires_at timestamp without time zone NOT NULL
+);
+CREATE INDEX idx_upload_session_user ON "UploadSession"(user_id);
+CREATE INDEX idx_upload_session_expires ON "UploadSession"(expires_at);
@@ -0,0 +1,15 @@
+CREATE TABLE "ShareLink" (
+    id UUID PRIMARY KEY,
+    object_id UUID NOT NULL REFERENCES "Object"(id) ON DELETE CASCADE,
+    owner_id UUID NOT NULL REFERENCES "User"(id),
+    token VARCHAR(64) UNIQUE NOT NULL,
+    password_hash VARCHAR(255),
+    has_password BOOLEAN GENERATED ALWAYS AS (password_hash IS NOT NULL) STORED,
+    expires_at timestamp without time zone,
+    max_downloads INTEGER,
+    download_count INTEGER NOT NULL DEFAULT 0,
+    read_only BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at timestamp without time zone NOT NULL DEFAULT now()
+);
+CREATE INDEX idx_share_link_owner ON "ShareLink"(owner_id);
+CREATE INDEX idx_share_link_object ON "ShareLink"(object_id);
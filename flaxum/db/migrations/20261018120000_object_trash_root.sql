@@ -0,0 +1,4 @@
+-- Объект, удаление которого поместило строку в корзину: сам объект или удаленная папка-предок
+ALTER TABLE "Object" ADD COLUMN trash_root_id UUID;
+UPDATE "Object" SET trash_root_id = id WHERE in_trash IS TRUE;
+CREATE INDEX idx_object_trash_root ON "Object"(trash_root_id) WHERE trash_root_id IS NOT NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE "Object" ADD COLUMN trashed_at timestamp without time zone;
+UPDATE "Object" SET trashed_at = COALESCE(updated_at, created_at) WHERE in_trash IS TRUE;
+CREATE INDEX idx_object_trashed_at ON "Object"(trashed_at) WHERE trash_root_id = id;
@@ -0,0 +1,7 @@
+-- NULL - без ограничения
+ALTER TABLE "User" ADD COLUMN storage_quota BIGINT;
+
+UPDATE "User" SET storage_size = COALESCE((
+    SELECT SUM(size) FROM "Object"
+    WHERE owner_id = "User".id AND eliminated IS NOT TRUE AND type = 'file'
+), 0);
@@ -0,0 +1,22 @@
+-- Зашифрованное содержимое в S3 под ключом {owner_id}/{id}, общее для одинаковых файлов владельца
+CREATE TABLE "Blob" (
+    id UUID PRIMARY KEY,
+    owner_id UUID NOT NULL REFERENCES "User"(id),
+    hash_sha256 CHAR(64),
+    size BIGINT NOT NULL DEFAULT 0,
+    decode_key VARCHAR(255),
+    ref_count INTEGER NOT NULL DEFAULT 1,
+    created_at timestamp without time zone NOT NULL DEFAULT now()
+);
+CREATE INDEX idx_blob_owner_hash ON "Blob"(owner_id, hash_sha256) WHERE ref_count > 0;
+
+ALTER TABLE "Object" ADD COLUMN blob_id UUID REFERENCES "Blob"(id);
+
+INSERT INTO "Blob" (id, owner_id, hash_sha256, size, decode_key, ref_count, created_at)
+SELECT id, owner_id, hash_sha256, COALESCE(size, 0), decode_key,
+    CASE WHEN eliminated IS TRUE THEN 0 ELSE 1 END, created_at
+FROM "Object"
+WHERE type = 'file';
+
+UPDATE "Object" SET blob_id = id WHERE type = 'file';
+CREATE INDEX idx_object_blob ON "Object"(blob_id) WHERE blob_id IS NOT NULL;
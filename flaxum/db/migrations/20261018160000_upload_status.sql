@@ -0,0 +1,11 @@
+-- Состояние загрузки содержимого в S3, выставляется file_worker
+CREATE TYPE uploadStatus AS ENUM ('pending', 'stored', 'failed');
+
+ALTER TABLE "Blob" ADD COLUMN upload_status uploadStatus NOT NULL DEFAULT 'pending';
+ALTER TABLE "Object" ADD COLUMN upload_status uploadStatus;
+ALTER TABLE "RobotObject" ADD COLUMN upload_status uploadStatus NOT NULL DEFAULT 'pending';
+
+-- Воркер раньше не обновлял upload_s3, уже принятые файлы считаются загруженными
+UPDATE "Blob" SET upload_status = 'stored';
+UPDATE "Object" SET upload_status = 'stored', upload_s3 = TRUE WHERE type = 'file';
+UPDATE "RobotObject" SET upload_status = 'stored', upload_s3 = TRUE;
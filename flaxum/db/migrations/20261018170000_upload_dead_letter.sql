@@ -0,0 +1,12 @@
+-- События загрузки, которые file_worker не смог обработать за все попытки
+CREATE TABLE "UploadDeadLetter" (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    routing_key VARCHAR(255) NOT NULL,
+    object_id UUID,
+    payload TEXT NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    replayed_at timestamp without time zone
+);
+CREATE INDEX idx_upload_dead_letter_pending ON "UploadDeadLetter"(created_at) WHERE replayed_at IS NULL;
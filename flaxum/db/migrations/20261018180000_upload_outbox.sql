@@ -0,0 +1,11 @@
+-- События загрузки, записанные в одной транзакции с объектом; публикует их relay
+CREATE TABLE "UploadOutbox" (
+    id UUID PRIMARY KEY,
+    routing_key VARCHAR(255) NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    sent_at timestamp without time zone
+);
+CREATE INDEX idx_upload_outbox_pending ON "UploadOutbox"(created_at) WHERE sent_at IS NULL;
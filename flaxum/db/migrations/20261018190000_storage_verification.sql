@@ -0,0 +1,17 @@
+-- Результаты проверки содержимого в S3: расшифровка и сверка hash_sha256
+CREATE TYPE scrubTarget AS ENUM ('blob', 'robot_object');
+CREATE TYPE verificationStatus AS ENUM ('ok', 'mismatch', 'missing', 'error');
+
+-- Последняя проверка каждого блоба и файла робота
+CREATE TABLE "StorageVerification" (
+    target_type scrubTarget NOT NULL,
+    target_id UUID NOT NULL,
+    storage_key VARCHAR(255) NOT NULL,
+    status verificationStatus NOT NULL,
+    expected_sha256 VARCHAR(64),
+    actual_sha256 VARCHAR(64),
+    error TEXT,
+    verified_at timestamp without time zone NOT NULL DEFAULT now(),
+    PRIMARY KEY (target_type, target_id)
+);
+CREATE INDEX idx_storage_verification_problems ON "StorageVerification"(verified_at) WHERE status != 'ok';
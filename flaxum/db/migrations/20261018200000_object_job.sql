@@ -0,0 +1,18 @@
+-- Долгие операции над деревом объектов, прогресс отдается клиенту по id
+CREATE TYPE objectJobKind AS ENUM ('copy');
+CREATE TYPE objectJobStatus AS ENUM ('running', 'done', 'failed');
+
+CREATE TABLE "ObjectJob" (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES "User"(id),
+    kind objectJobKind NOT NULL,
+    status objectJobStatus NOT NULL DEFAULT 'running',
+    total INTEGER NOT NULL DEFAULT 0,
+    processed INTEGER NOT NULL DEFAULT 0,
+    skipped INTEGER NOT NULL DEFAULT 0,
+    object_id UUID REFERENCES "Object"(id),
+    error TEXT,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    updated_at timestamp without time zone
+);
+CREATE INDEX idx_object_job_running ON "ObjectJob"(status) WHERE status = 'running';
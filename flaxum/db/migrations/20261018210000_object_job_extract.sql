@@ -0,0 +1,2 @@
+-- Распаковка загруженного архива в дерево папок
+ALTER TYPE objectJobKind ADD VALUE 'extract';
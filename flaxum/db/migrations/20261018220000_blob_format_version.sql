@@ -0,0 +1,10 @@
+-- Формат содержимого в хранилище: legacy - AES-256-CTR без заголовка,
+-- segmented - заголовок FLXB и сегменты AES-256-GCM. Формат при чтении берется отсюда,
+-- а не угадывается по началу блоба
+CREATE TYPE blobFormatVersion AS ENUM ('legacy', 'segmented');
+
+-- Все, что уже лежит в хранилище, записано старым форматом; новые загрузки пишутся сегментами
+ALTER TABLE "Blob" ADD COLUMN format_version blobFormatVersion NOT NULL DEFAULT 'legacy';
+ALTER TABLE "Blob" ALTER COLUMN format_version SET DEFAULT 'segmented';
+ALTER TABLE "RobotObject" ADD COLUMN format_version blobFormatVersion NOT NULL DEFAULT 'legacy';
+ALTER TABLE "RobotObject" ALTER COLUMN format_version SET DEFAULT 'segmented';
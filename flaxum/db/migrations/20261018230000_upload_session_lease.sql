@@ -0,0 +1,3 @@
+-- Запрос, который пишет кусок в файл сессии, и до какого времени за ним это право
+ALTER TABLE "UploadSession" ADD COLUMN writer_id UUID;
+ALTER TABLE "UploadSession" ADD COLUMN writing_until timestamp without time zone;
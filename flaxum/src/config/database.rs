@@ -49,6 +49,12 @@ impl DatabaseTrait for Database {
 }
 
 impl Database {
+    /// Обертка над готовым пулом, для тестов с `#[sqlx::test]`
+    #[cfg(test)]
+    pub(crate) fn from_pool(pool: Pool<Postgres>) -> Self {
+        Database { pool }
+    }
+
     /// Return database pool
     async fn connect(url: &str, pool_size: u32) -> Result<Pool<Postgres>, Error> {
         PgPoolOptions::new()
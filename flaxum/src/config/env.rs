@@ -2,6 +2,10 @@ use std::borrow::Cow;
 
 use anyhow::bail;
 
+use file_worker::storage::StorageConfig;
+
+use super::{DEFAULT_SCRUB_RATE_BYTES_PER_SEC, DEFAULT_TRASH_RETENTION_DAYS};
+
 #[derive(Clone, Debug)]
 pub struct EnvironmentVariables {
     pub api_address: Cow<'static, str>,
@@ -10,10 +14,14 @@ pub struct EnvironmentVariables {
     pub flaxum_super_user_email: Cow<'static, str>,
     pub flaxum_super_user_password: Cow<'static, str>,
 
-    pub minio_url: Cow<'static, str>,
-    pub upload_main_bucket: Cow<'static, str>,
+    pub storage: StorageConfig,
     pub download_tmp_bucket: Cow<'static, str>,
 
+    pub trash_retention_days: i64,
+    pub scrub_rate_bytes_per_sec: u64,
+    /// Удалять ли найденное фоновым сборщиком мусора хранилища, иначе только отчет в лог
+    pub storage_gc_cleanup: bool,
+
     pub postgres_user: Cow<'static, str>,
     pub postgres_password: Cow<'static, str>,
 
@@ -25,6 +33,11 @@ pub struct EnvironmentVariables {
     pub rmq_port: u16,
     pub rmq_user: Cow<'static, str>,
     pub rmq_pass: Cow<'static, str>,
+
+    /// KEK в hex, которым оборачиваются ключи данных объектов
+    pub master_key: Cow<'static, str>,
+    /// Прежний KEK, нужен только на время ротации
+    pub master_key_previous: Option<Cow<'static, str>>,
 }
 
 impl EnvironmentVariables {
@@ -51,19 +64,24 @@ impl EnvironmentVariables {
                 Ok(pass) => pass.into(),
                 Err(err) => bail!("missing FLAXUM_SUPER_USER_PASSWORD: {err}"),
             },
-            // S3
-            minio_url: match dotenv::var("MINIO_URL") {
-                Ok(url) => url.into(),
-                Err(err) => bail!("missing MINIO_URL: {err}"),
-            },
-            upload_main_bucket: match dotenv::var("UPLOAD_MAIN_BUCKET") {
-                Ok(bucket) => bucket.into(),
-                Err(err) => bail!("missing UPLOAD_MAIN_BUCKET: {err}"),
-            },
+            // Storage
+            storage: StorageConfig::from_env()?,
             download_tmp_bucket: match dotenv::var("DOWNLOAD_TEMP_BUCKET") {
                 Ok(bucket) => bucket.into(),
                 Err(err) => bail!("missing DOWNLOAD_TEMP_BUCKET: {err}"),
             },
+            trash_retention_days: match dotenv::var("TRASH_RETENTION_DAYS") {
+                Ok(days) => days.parse()?,
+                Err(_) => DEFAULT_TRASH_RETENTION_DAYS,
+            },
+            scrub_rate_bytes_per_sec: match dotenv::var("SCRUB_RATE_BYTES_PER_SEC") {
+                Ok(rate) => rate.parse()?,
+                Err(_) => DEFAULT_SCRUB_RATE_BYTES_PER_SEC,
+            },
+            storage_gc_cleanup: match dotenv::var("STORAGE_GC_CLEANUP") {
+                Ok(cleanup) => cleanup.parse()?,
+                Err(_) => false,
+            },
             // DB
             postgres_user: match dotenv::var("POSTGRES_USER") {
                 Ok(user) => user.into(),
@@ -98,6 +116,26 @@ impl EnvironmentVariables {
                 Ok(rmq_password) => rmq_password.into(),
                 Err(err) => bail!("missing RABBITMQ_DEFAULT_PASS: {err}"),
             },
+            // KEK
+            master_key: match read_secret("MASTER_KEY")? {
+                Some(key) => key.into(),
+                None => bail!("missing MASTER_KEY or MASTER_KEY_FILE"),
+            },
+            master_key_previous: read_secret("MASTER_KEY_PREVIOUS")?.map(Into::into),
         })
     }
 }
+
+/// Секрет из переменной `name` или из файла, путь к которому в `{name}_FILE`
+fn read_secret(name: &str) -> anyhow::Result<Option<String>> {
+    if let Ok(value) = dotenv::var(name) {
+        return Ok(Some(value));
+    }
+    match dotenv::var(format!("{name}_FILE")) {
+        Ok(path) => match std::fs::read_to_string(&path) {
+            Ok(value) => Ok(Some(value.trim().to_string())),
+            Err(err) => bail!("failed to read {name}_FILE {path}: {err}"),
+        },
+        Err(_) => Ok(None),
+    }
+}
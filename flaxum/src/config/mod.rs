@@ -2,25 +2,45 @@ pub mod database;
 pub mod env;
 pub mod parameter;
 pub mod rabbitmq;
-pub mod s3;
 
 use std::sync::Arc;
 
 use crate::config::env::EnvironmentVariables;
 use anyhow;
-use aws_sdk_s3::Client;
 use database::{Database, DatabaseTrait};
+use file_worker::cipher::Keyring;
+use file_worker::storage::StorageBackend;
 use rabbitmq::NotifierAmqp;
-use s3::S3Client;
 
 pub const SIZE_1GB: usize = 1024 * 1024 * 1024;
+/// Ограничение глубины обхода дерева объектов в рекурсивных запросах
+pub const MAX_TREE_DEPTH: i32 = 256;
+/// Время жизни сессии возобновляемой загрузки с момента последнего куска
+pub const UPLOAD_SESSION_TTL_HOURS: i64 = 24;
+/// На сколько запрос получает право записи в файл сессии; продлевается, пока идут данные
+pub const UPLOAD_CHUNK_LEASE_SECONDS: i64 = 60;
+/// Срок хранения объектов в корзине, если не задан `TRASH_RETENTION_DAYS`
+pub const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+/// Скорость чтения скрабера, если не задан `SCRUB_RATE_BYTES_PER_SEC`
+pub const DEFAULT_SCRUB_RATE_BYTES_PER_SEC: u64 = 8 * 1024 * 1024;
+/// Через сколько дней проверенное содержимое проверяется снова
+pub const SCRUB_REVERIFY_DAYS: i64 = 30;
+/// Каталог временных файлов загрузки `{owner_id}.{id}`, общий с file_worker
+pub const TMP_DIR: &str = "tmp";
+/// Сколько записей может быть в распаковываемом архиве
+pub const ARCHIVE_MAX_ENTRIES: usize = 100_000;
+/// Суммарный размер файлов распаковываемого архива
+pub const ARCHIVE_MAX_UNPACKED_SIZE: u64 = 16 * SIZE_1GB as u64;
+/// Сборщик мусора не трогает временные файлы, объекты и загрузки моложе этого срока
+pub const STORAGE_GC_GRACE_HOURS: i64 = 24;
 
 #[derive(Clone)]
 pub struct AppConfig {
     pub env: Arc<EnvironmentVariables>,
     pub db_conn: Arc<Database>,
-    pub s3_client: Arc<Client>,
+    pub storage: Arc<dyn StorageBackend>,
     pub rmq_conn: Arc<amqprs::connection::Connection>,
+    pub keyring: Arc<Keyring>,
 }
 
 impl AppConfig {
@@ -31,7 +51,9 @@ impl AppConfig {
             .await
             .unwrap_or_else(|e| panic!("Database error {}", e));
 
-        let s3_client = S3Client::init(&env).await;
+        let storage = env.storage.init().await?;
+
+        let keyring = Keyring::from_hex(&env.master_key, env.master_key_previous.as_deref())?;
 
         let mut ampq = NotifierAmqp::new(&env);
         let rmq_conn = ampq.init().await;
@@ -40,8 +62,9 @@ impl AppConfig {
         Ok(Self {
             env: Arc::new(env),
             db_conn: Arc::new(db_conn),
-            s3_client: Arc::new(s3_client),
+            storage,
             rmq_conn: Arc::new(rmq_conn),
+            keyring: Arc::new(keyring),
         })
     }
 }
@@ -1,17 +1,20 @@
 use std::fmt;
 
 use amqprs::{
-    callbacks::{DefaultChannelCallback, DefaultConnectionCallback},
+    callbacks::{ChannelCallback, DefaultChannelCallback, DefaultConnectionCallback},
     channel::{
-        BasicPublishArguments, Channel, ExchangeDeclareArguments, ExchangeType, QueueBindArguments,
-        QueueDeclareArguments,
+        BasicPublishArguments, Channel, ConfirmSelectArguments, ExchangeDeclareArguments,
+        ExchangeType, QueueBindArguments, QueueDeclareArguments,
     },
     connection::{Connection, OpenConnectionArguments},
-    BasicProperties,
+    error::Error as AmqpError,
+    Ack, BasicProperties, Cancel, CloseChannel, Nack, Return,
 };
-use rand::Rng;
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use super::env::EnvironmentVariables;
 use serde::{Deserialize, Serialize};
@@ -24,6 +27,9 @@ pub const QUEUE_EVENT_UPLOAD_ROBOT: &str = "flaxum.upload.object.robot";
 pub const ROUTING_KEY_EVENT_UPLOAD_USER: &str = "event.upload.user";
 pub const ROUTING_KEY_EVENT_UPLOAD_ROBOT: &str = "event.upload.robot";
 
+/// Сколько ждать подтверждения брокера на одну публикацию
+const PUBLISH_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct ArcturusAmqpConnChannel(pub Connection, pub Channel);
 
 impl fmt::Debug for ArcturusAmqpConnChannel {
@@ -128,43 +134,136 @@ impl NotifierAmqp {
     }
 }
 
-pub async fn send_upload_user_event(event: UploadUserEvent, conn: &Connection) {
-    let channel = conn.open_channel(None).await.unwrap();
-    match serde_json::to_string(&event) {
-        Ok(content) => {
-            let args =
-                BasicPublishArguments::new(EXCHANGE_UPLOAD_OBJECT, ROUTING_KEY_EVENT_UPLOAD_USER);
-            if let Err(err) = channel
-                .basic_publish(
-                    BasicProperties::default().with_persistence(true).finish(),
-                    content.as_bytes().to_vec(),
-                    args,
-                )
+/// Подтверждение публикации от брокера в режиме publisher confirms
+#[derive(Debug)]
+enum PublishConfirm {
+    Ack { delivery_tag: u64, multiple: bool },
+    Nack { delivery_tag: u64, multiple: bool },
+}
+
+impl PublishConfirm {
+    /// Подтверждение относится к публикации `delivery_tag`
+    fn covers(&self, delivery_tag: u64) -> bool {
+        let (tag, multiple) = match self {
+            PublishConfirm::Ack {
+                delivery_tag,
+                multiple,
+            }
+            | PublishConfirm::Nack {
+                delivery_tag,
+                multiple,
+            } => (*delivery_tag, *multiple),
+        };
+        tag == delivery_tag || (multiple && tag > delivery_tag)
+    }
+}
+
+struct ConfirmCallback {
+    confirms: mpsc::UnboundedSender<PublishConfirm>,
+}
+
+#[async_trait]
+impl ChannelCallback for ConfirmCallback {
+    async fn close(&mut self, channel: &Channel, close: CloseChannel) -> Result<(), AmqpError> {
+        error!("Publisher channel {} closed: {}", channel, close);
+        Ok(())
+    }
+    async fn cancel(&mut self, _channel: &Channel, _cancel: Cancel) -> Result<(), AmqpError> {
+        Ok(())
+    }
+    async fn flow(&mut self, _channel: &Channel, active: bool) -> Result<bool, AmqpError> {
+        Ok(active)
+    }
+    async fn publish_ack(&mut self, _channel: &Channel, ack: Ack) {
+        let _ = self.confirms.send(PublishConfirm::Ack {
+            delivery_tag: ack.delivery_tag(),
+            multiple: ack.mutiple(),
+        });
+    }
+    async fn publish_nack(&mut self, _channel: &Channel, nack: Nack) {
+        let _ = self.confirms.send(PublishConfirm::Nack {
+            delivery_tag: nack.delivery_tag(),
+            multiple: nack.multiple(),
+        });
+    }
+    async fn publish_return(
+        &mut self,
+        channel: &Channel,
+        ret: Return,
+        _basic_properties: BasicProperties,
+        _content: Vec<u8>,
+    ) {
+        warn!("Message returned on channel {}: {}", channel, ret);
+    }
+}
+
+/// Канал в режиме publisher confirms: публикация успешна только после `basic.ack` брокера
+pub struct ConfirmedPublisher {
+    channel: Channel,
+    confirms: mpsc::UnboundedReceiver<PublishConfirm>,
+    delivery_tag: u64,
+}
+
+impl ConfirmedPublisher {
+    pub async fn open(conn: &Connection) -> anyhow::Result<Self> {
+        let (sender, confirms) = mpsc::unbounded_channel();
+        let channel = conn.open_channel(None).await?;
+        channel
+            .register_callback(ConfirmCallback { confirms: sender })
+            .await?;
+        channel
+            .confirm_select(ConfirmSelectArguments::default())
+            .await?;
+        Ok(Self {
+            channel,
+            confirms,
+            delivery_tag: 0,
+        })
+    }
+
+    /// Публикация в `EXCHANGE_UPLOAD_OBJECT` с ожиданием подтверждения
+    pub async fn publish(&mut self, routing_key: &str, content: Vec<u8>) -> anyhow::Result<()> {
+        let args = BasicPublishArguments::new(EXCHANGE_UPLOAD_OBJECT, routing_key);
+        self.channel
+            .basic_publish(
+                BasicProperties::default().with_persistence(true).finish(),
+                content,
+                args,
+            )
+            .await?;
+        self.delivery_tag += 1;
+
+        let deadline = tokio::time::Instant::now() + PUBLISH_CONFIRM_TIMEOUT;
+        loop {
+            let confirm = tokio::time::timeout_at(deadline, self.confirms.recv())
                 .await
-            {
-                error!("Error when publish an evento into queue: {}", err);
+                .map_err(|_| anyhow::anyhow!("publish confirm timed out"))?
+                .ok_or_else(|| anyhow::anyhow!("publisher channel closed"))?;
+            if !confirm.covers(self.delivery_tag) {
+                continue;
             }
+            return match confirm {
+                PublishConfirm::Ack { .. } => Ok(()),
+                PublishConfirm::Nack { .. } => Err(anyhow::anyhow!("broker rejected message")),
+            };
         }
-        Err(err) => {
-            error!("Error when transform event into json: {}", err);
+    }
+
+    pub async fn close(self) {
+        if let Err(err) = self.channel.close().await {
+            warn!("Failed to close publisher channel: {}", err);
         }
     }
 }
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UploadUserEvent {
     pub user_id: String,
     pub object_id: String,
+    /// Ключ данных, обернутый KEK
     pub key: String,
 }
 
-impl UploadUserEvent {
-    pub fn generate_key(key_length_bytes: usize) -> String {
-        let mut rng = rand::rng();
-        let key: Vec<u8> = (0..key_length_bytes).map(|_| rng.random()).collect();
-        hex::encode(key)
-    }
-}
-
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UploadRobotEvent {
     pub user_id: String,
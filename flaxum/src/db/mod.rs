@@ -1,7 +1,9 @@
 use crate::entity::pagination::Pagination;
 
+use sqlx::Error;
 use sqlx::Postgres;
 use sqlx::QueryBuilder;
+use sqlx::Transaction;
 
 /// Append pagination to QueryBuilder
 #[inline]
@@ -17,3 +19,32 @@ pub fn pagination_query_builder<'a>(
 
     query
 }
+
+/// Точка отката внутри транзакции, чтобы ошибка одной операции не прерывала всю транзакцию
+pub async fn savepoint(tx: &mut Transaction<'static, Postgres>, name: &str) -> Result<(), Error> {
+    sqlx::query(&format!("SAVEPOINT {name}"))
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Отмена изменений после `savepoint`, транзакция остается рабочей
+pub async fn rollback_to_savepoint(
+    tx: &mut Transaction<'static, Postgres>,
+    name: &str,
+) -> Result<(), Error> {
+    sqlx::query(&format!("ROLLBACK TO SAVEPOINT {name}"))
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+pub async fn release_savepoint(
+    tx: &mut Transaction<'static, Postgres>,
+    name: &str,
+) -> Result<(), Error> {
+    sqlx::query(&format!("RELEASE SAVEPOINT {name}"))
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
@@ -3,4 +3,8 @@ pub mod token;
 pub mod user;
 pub mod uxo;
 pub mod robot;
-
+pub mod upload_session;
+pub mod share_link;
+pub mod upload_dead_letter;
+pub mod storage_verification;
+pub mod storage_gc;
@@ -1,6 +1,7 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use validator::Validate;
 
+use crate::dto::uxo::GiveAccessDto;
 use crate::scalar::Id;
 
 #[derive(Serialize, Deserialize, Default, Validate)]
@@ -29,6 +30,78 @@ pub struct DownloadFileDto {
     pub file_id: Id,
 }
 
+/// `?objectIds=..&objectIds=..`
+#[derive(Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadZipDto {
+    #[validate(length(min = 1, max = 1000))]
+    pub object_ids: Vec<Id>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetObjectInfoDto {
+    pub object_id: Id,
+}
+
+/// Переименование и перенос; не переданные поля не меняются
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateObjectDto {
+    pub object_id: Id,
+    #[validate(length(min = 1, max = 128))]
+    pub name: Option<String>,
+    /// `null` - перенос в корень
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub parent_id: Option<Option<Id>>,
+}
+
+/// Отличает переданный `null` от отсутствующего поля
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Копирование объекта в папку `parent_id`, `None` - в корень пользователя
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyObjectDto {
+    pub object_id: Id,
+    pub parent_id: Option<Id>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkObjectAction {
+    Trash,
+    Restore,
+    Eliminate,
+    Move,
+    Share,
+}
+
+/// Одно действие над списком объектов. `parent_id` - папка назначения для `move`
+/// (`null` - корень), `access` - выдаваемые права для `share`
+#[derive(Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkObjectDto {
+    #[validate(length(min = 1, max = 1000))]
+    pub object_ids: Vec<Id>,
+    pub action: BulkObjectAction,
+    pub parent_id: Option<Id>,
+    #[validate(nested)]
+    pub access: Option<GiveAccessDto>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetObjectJobDto {
+    pub job_id: Id,
+}
+
 #[derive(Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct DeleteObjectDto {
@@ -36,3 +109,10 @@ pub struct DeleteObjectDto {
     pub delete_mark: bool,
     pub hard_delete: bool,
 }
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeTrashDto {
+    #[serde(default)]
+    pub dry_run: bool,
+}
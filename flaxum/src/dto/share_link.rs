@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::scalar::Id;
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateShareLinkDto {
+    pub expires_at: Option<NaiveDateTime>,
+    #[validate(length(min = 4, max = 128))]
+    pub password: Option<String>,
+    #[validate(range(min = 1))]
+    pub max_downloads: Option<i32>,
+    #[serde(default = "default_read_only")]
+    pub read_only: bool,
+}
+
+fn default_read_only() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct GetShareLinkListDto {
+    pub object_id: Option<Id>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicDownloadDto {
+    pub file_id: Option<Id>,
+}
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUploadDeadLetterListDto {
+    #[serde(default)]
+    pub include_replayed: bool,
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::scalar::Id;
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUploadSessionDto {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[validate(range(min = 0))]
+    pub size: i64,
+    #[validate(length(min = 1, max = 100))]
+    pub mimetype: Option<String>,
+    pub parent_id: Option<Id>,
+}
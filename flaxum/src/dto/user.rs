@@ -64,3 +64,12 @@ pub struct AdminChangePasswordDto {
     #[validate(length(min = 3, max = 31))]
     pub new_password: String,
 }
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminSetStorageQuotaDto {
+    pub id: Id,
+    /// Квота в байтах, `null` - без ограничения
+    #[validate(range(min = 0))]
+    pub storage_quota: Option<i64>,
+}
@@ -4,7 +4,7 @@ use validator::Validate;
 
 use crate::scalar::Id;
 
-#[derive(Validate, Serialize, Deserialize)]
+#[derive(Validate, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct GiveAccessDto {
     pub can_read: bool,
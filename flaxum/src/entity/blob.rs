@@ -0,0 +1,47 @@
+use crate::{entity::object::UploadStatus, scalar::Id};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Формат зашифрованного содержимого в хранилище
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, Deserialize, Serialize)]
+#[sqlx(type_name = "blobFormatVersion", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum BlobFormatVersion {
+    /// AES-256-CTR без заголовка
+    Legacy,
+    /// Заголовок `FLXB` и сегменты AES-256-GCM
+    Segmented,
+}
+
+/// Зашифрованное содержимое файла в S3. Объекты владельца с одинаковым
+/// `hash_sha256` ссылаются на один блоб, `ref_count` - число таких объектов
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Blob {
+    pub id: Id,
+    pub owner_id: Id,
+    pub hash_sha256: Option<String>,
+    pub size: i64,
+    #[serde(skip)]
+    pub decode_key: Option<String>,
+    pub ref_count: i32,
+    pub created_at: NaiveDateTime,
+    pub upload_status: UploadStatus,
+    pub format_version: BlobFormatVersion,
+}
+
+impl Blob {
+    pub fn storage_key(&self) -> String {
+        format!("{}/{}", self.owner_id, self.id)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BlobCreateModel {
+    pub id: Id,
+    pub owner_id: Id,
+    pub hash_sha256: Option<String>,
+    pub size: i64,
+    pub decode_key: Option<String>,
+    pub format_version: BlobFormatVersion,
+}
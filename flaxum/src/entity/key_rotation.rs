@@ -0,0 +1,18 @@
+use crate::scalar::Id;
+use serde::{Deserialize, Serialize};
+
+/// Строка с обернутым ключом данных: `value` - сам `decode_key`
+/// или тело события, в поле `key` которого лежит ключ
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WrappedKeyRow {
+    pub id: Id,
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRotationReport {
+    pub kek_id: String,
+    pub rewrapped: i64,
+    pub failed: i64,
+}
@@ -1,5 +1,15 @@
+pub mod blob;
 pub mod object;
 pub mod pagination;
 pub mod user;
 pub mod robot;
 pub mod robot_object;
+pub mod upload_session;
+pub mod share_link;
+pub mod trash;
+pub mod upload_dead_letter;
+pub mod upload_outbox;
+pub mod key_rotation;
+pub mod storage_verification;
+pub mod storage_gc;
+pub mod object_job;
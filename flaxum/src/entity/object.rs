@@ -1,5 +1,6 @@
+use crate::entity::user::User;
 use crate::scalar::Id;
-use chrono::{DateTime, Local, NaiveDateTime};
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgRow, FromRow, Row};
 use uuid::Uuid;
@@ -12,6 +13,17 @@ pub enum ObjectType {
     File,
 }
 
+/// Состояние загрузки содержимого файла в S3
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, Default)]
+#[sqlx(type_name = "uploadStatus", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    #[default]
+    Pending,
+    Stored,
+    Failed,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ObjectsPaginated {
     items: Vec<Object>,
@@ -48,8 +60,12 @@ pub struct Object {
     pub in_trash: bool,
     pub eliminated: bool,
     pub upload_s3: Option<bool>,
+    /// Ключ данных, обернутый KEK; наружу не отдается
+    #[serde(skip_serializing)]
     pub decode_key: Option<String>,
     pub hash_sha256: Option<String>,
+    pub blob_id: Option<Id>,
+    pub upload_status: Option<UploadStatus>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -70,6 +86,8 @@ impl Object {
         upload_s3: Option<bool>,
         decode_key: Option<String>,
         hash_sha256: Option<String>,
+        blob_id: Option<Id>,
+        upload_status: Option<UploadStatus>,
     ) -> Object {
         Object {
             id,
@@ -87,8 +105,16 @@ impl Object {
             upload_s3,
             decode_key,
             hash_sha256,
+            blob_id,
+            upload_status,
         }
     }
+
+    /// Ключ зашифрованного содержимого в основном бакете.
+    /// Одинаковые файлы владельца ссылаются на общий `Blob`
+    pub fn storage_key(&self) -> String {
+        format!("{}/{}", self.owner_id, self.blob_id.unwrap_or(self.id))
+    }
 }
 
 impl From<PgRow> for Object {
@@ -109,11 +135,160 @@ impl From<PgRow> for Object {
             value.get("upload_s3"),
             value.get("decode_key"),
             value.get("hash_sha256"),
-
+            value.get("blob_id"),
+            value.get("upload_status"),
         )
     }
 }
 
+/// Папка на пути от корня к объекту
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectPathItem {
+    pub id: Id,
+    pub name: String,
+    /// Право чтения из собственной записи `UserXObject` пользователя на эту папку
+    #[serde(skip)]
+    pub can_read: Option<bool>,
+}
+
+/// Объект поддерева вместе с правом чтения из записи `UserXObject` пользователя на него
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SubtreeItem {
+    #[sqlx(flatten)]
+    pub object: Object,
+    pub can_read: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectOwner {
+    pub id: Id,
+    pub name_1: String,
+    pub name_2: Option<String>,
+    pub name_3: Option<String>,
+    pub email: String,
+}
+
+impl From<User> for ObjectOwner {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            name_1: user.name_1,
+            name_2: user.name_2,
+            name_3: user.name_3,
+            email: user.email,
+        }
+    }
+}
+
+/// Объект с путем от корня, владельцем и итоговыми правами запросившего пользователя
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectInfo {
+    #[serde(flatten)]
+    pub object: Object,
+    pub path: Vec<ObjectPathItem>,
+    pub owner: ObjectOwner,
+    pub access: UxOAccess,
+}
+
+/// Результат действия над одним объектом из списка: объект после изменения
+/// или ошибка с HTTP-статусом, который вернул бы запрос по одному объекту
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkObjectResult {
+    pub object_id: Id,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<Object>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkObjectReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub items: Vec<BulkObjectResult>,
+}
+
+/// Результат загрузки одного файла: созданный объект или ошибка
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadFileResult {
+    pub path: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<Object>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub items: Vec<UploadFileResult>,
+}
+
+/// Объект без служебных полей для выдачи по публичной ссылке
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicObject {
+    pub id: Id,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub size: Option<i64>,
+    #[serde(rename = "type")]
+    pub type_: ObjectType,
+    pub mimetype: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub upload_status: Option<UploadStatus>,
+}
+
+impl From<Object> for PublicObject {
+    fn from(obj: Object) -> Self {
+        Self {
+            id: obj.id,
+            parent_id: obj.parent_id,
+            name: obj.name,
+            size: obj.size,
+            type_: obj.type_,
+            mimetype: obj.mimetype,
+            created_at: obj.created_at,
+            updated_at: obj.updated_at,
+            upload_status: obj.upload_status,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PublicObjectsPaginated {
+    items: Vec<PublicObject>,
+    limit: i64,
+    offset: i64,
+    total: i64,
+}
+
+impl From<ObjectsPaginated> for PublicObjectsPaginated {
+    fn from(value: ObjectsPaginated) -> Self {
+        Self {
+            items: value.items.into_iter().map(PublicObject::from).collect(),
+            limit: value.limit,
+            offset: value.offset,
+            total: value.total,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ObjectCreateModel {
     pub id: Id,
@@ -127,9 +302,12 @@ pub struct ObjectCreateModel {
     pub upload_s3: Option<bool>,
     pub decode_key: Option<String>,
     pub hash_sha256: Option<String>,
+    pub blob_id: Option<Id>,
+    pub upload_status: Option<UploadStatus>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UxOAccess {
     pub can_read: bool,
     pub can_edit: bool,
@@ -144,6 +322,39 @@ impl UxOAccess {
             can_delete: true,
         }
     }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::Read => self.can_read,
+            Permission::Edit => self.can_edit,
+            Permission::Delete => self.can_delete,
+        }
+    }
+
+    /// Все права `other` есть и у `self`
+    pub fn covers(&self, other: &UxOAccess) -> bool {
+        (self.can_read || !other.can_read)
+            && (self.can_edit || !other.can_edit)
+            && (self.can_delete || !other.can_delete)
+    }
+}
+
+impl From<&UserXObject> for UxOAccess {
+    fn from(uxo: &UserXObject) -> Self {
+        Self {
+            can_read: uxo.can_read,
+            can_edit: uxo.can_edit,
+            can_delete: uxo.can_delete,
+        }
+    }
+}
+
+/// Действие над объектом, для которого проверяется доступ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Read,
+    Edit,
+    Delete,
 }
 
 #[derive(Debug, sqlx::Type, sqlx::FromRow, Serialize, Deserialize, Clone)]
@@ -265,16 +476,3 @@ impl<'r> FromRow<'r, PgRow> for PublicUserXObject {
 pub struct GetUxoListOut {
     pub items: Vec<PublicUserXObject>,
 }
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct DownloadFileUrl {
-    url: String,
-    valid_until: DateTime<Local>,
-}
-
-impl DownloadFileUrl {
-    pub fn new(url: String, valid_until: DateTime<Local>) -> Self {
-        DownloadFileUrl { url, valid_until }
-    }
-}
@@ -0,0 +1,38 @@
+use crate::scalar::Id;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, Deserialize, Serialize)]
+#[sqlx(type_name = "objectJobKind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectJobKind {
+    Copy,
+    Extract,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, Deserialize, Serialize)]
+#[sqlx(type_name = "objectJobStatus", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectJobStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+/// Фоновая операция над деревом объектов. `processed` и `skipped` считаются
+/// из `total` объектов, `object_id` - созданный корень результата
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectJob {
+    pub id: Id,
+    pub user_id: Id,
+    pub kind: ObjectJobKind,
+    pub status: ObjectJobStatus,
+    pub total: i32,
+    pub processed: i32,
+    pub skipped: i32,
+    pub object_id: Option<Id>,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
@@ -1,4 +1,4 @@
-use crate::scalar::Id;
+use crate::{entity::object::UploadStatus, scalar::Id};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgRow, Row};
@@ -13,8 +13,10 @@ pub struct RobotObject {
     pub size: Option<i64>,
     pub created_at: NaiveDateTime,
     pub upload_s3: Option<bool>,
+    #[serde(skip_serializing)]
     pub decode_key: String,
     pub hash_sha256: Option<String>,
+    pub upload_status: UploadStatus,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -28,6 +30,7 @@ impl RobotObject {
         upload_s3: Option<bool>,
         decode_key: String,
         hash_sha256: Option<String>,
+        upload_status: UploadStatus,
     ) -> RobotObject {
         RobotObject {
             id,
@@ -38,6 +41,7 @@ impl RobotObject {
             upload_s3,
             decode_key,
             hash_sha256,
+            upload_status,
         }
     }
 }
@@ -53,6 +57,7 @@ impl From<PgRow> for RobotObject {
             value.get("upload_s3"),
             value.get("decode_key"),
             value.get("hash_sha256"),
+            value.get("upload_status"),
         )
     }
 }
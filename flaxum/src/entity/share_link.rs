@@ -0,0 +1,59 @@
+use crate::entity::object::PublicObject;
+use crate::scalar::Id;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Публичная ссылка на объект, доступная без авторизации по `token`
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    pub id: Id,
+    pub object_id: Id,
+    pub owner_id: Id,
+    pub token: String,
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    pub has_password: bool,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_downloads: Option<i32>,
+    pub download_count: i32,
+    pub read_only: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl ShareLink {
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShareLinkCreateModel {
+    pub id: Id,
+    pub object_id: Id,
+    pub owner_id: Id,
+    pub token: String,
+    pub password_hash: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_downloads: Option<i32>,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ShareLinksPaginated {
+    pub items: Vec<ShareLink>,
+    pub limit: i64,
+    pub offset: i64,
+    pub total: i64,
+}
+
+/// Описание ссылки для анонимного получателя
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicShareLink {
+    pub read_only: bool,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_downloads: Option<i32>,
+    pub download_count: i32,
+    pub object: PublicObject,
+}
@@ -0,0 +1,61 @@
+use crate::entity::storage_verification::ScrubTarget;
+use crate::scalar::Id;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Файл в `tmp/`, который не нужен ни сессии загрузки, ни file_worker
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleTempFile {
+    pub name: String,
+    pub size: i64,
+    pub modified_at: Option<NaiveDateTime>,
+}
+
+/// Объект хранилища без строки `Blob` или `RobotObject`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanStorageObject {
+    pub key: String,
+    pub size: i64,
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+/// Содержимое, загруженное по данным БД
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredContent {
+    pub target_type: ScrubTarget,
+    pub id: Id,
+    pub owner_id: Id,
+}
+
+impl StoredContent {
+    pub fn storage_key(&self) -> String {
+        format!("{}/{}", self.owner_id, self.id)
+    }
+}
+
+/// Незавершенная загрузка в хранилище
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleUpload {
+    pub key: String,
+    pub upload_id: String,
+    pub initiated_at: Option<NaiveDateTime>,
+}
+
+/// Результат сверки хранилища. Без `cleanup` ничего не удаляется;
+/// `failed` - сколько найденного не удалось убрать
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageGcReport {
+    pub cleanup: bool,
+    pub stale_before: NaiveDateTime,
+    pub temp_files: Vec<StaleTempFile>,
+    pub orphan_objects: Vec<OrphanStorageObject>,
+    pub missing_content: Vec<StoredContent>,
+    pub stale_uploads: Vec<StaleUpload>,
+    pub reclaimed_size: i64,
+    pub failed: i64,
+}
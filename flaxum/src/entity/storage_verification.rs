@@ -0,0 +1,101 @@
+use crate::entity::blob::BlobFormatVersion;
+use crate::scalar::Id;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Что проверяет скрабер: блоб пользовательских файлов или файл робота
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, Deserialize, Serialize)]
+#[sqlx(type_name = "scrubTarget", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ScrubTarget {
+    Blob,
+    RobotObject,
+}
+
+/// `mismatch` - содержимое не прошло проверку тегов или хэш не совпал,
+/// `missing` - в S3 нет объекта, `error` - проверку не удалось выполнить
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type, Deserialize, Serialize)]
+#[sqlx(type_name = "verificationStatus", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationStatus {
+    Ok,
+    Mismatch,
+    Missing,
+    Error,
+}
+
+/// Содержимое, которое пора проверить
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ScrubCandidate {
+    pub target_type: ScrubTarget,
+    pub id: Id,
+    pub owner_id: Id,
+    pub hash_sha256: Option<String>,
+    pub size: i64,
+    pub decode_key: Option<String>,
+    pub format_version: BlobFormatVersion,
+}
+
+impl ScrubCandidate {
+    pub fn storage_key(&self) -> String {
+        format!("{}/{}", self.owner_id, self.id)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageVerification {
+    pub target_type: ScrubTarget,
+    pub target_id: Id,
+    pub storage_key: String,
+    pub status: VerificationStatus,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: Option<String>,
+    pub error: Option<String>,
+    pub verified_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageVerificationCreateModel {
+    pub target_type: ScrubTarget,
+    pub target_id: Id,
+    pub storage_key: String,
+    pub status: VerificationStatus,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Число проверок по статусам: итог прохода скрабера или сводка по таблице
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrubReport {
+    pub checked: i64,
+    pub ok: i64,
+    pub mismatch: i64,
+    pub missing: i64,
+    pub error: i64,
+}
+
+impl ScrubReport {
+    pub fn add(&mut self, status: VerificationStatus) {
+        self.checked += 1;
+        match status {
+            VerificationStatus::Ok => self.ok += 1,
+            VerificationStatus::Mismatch => self.mismatch += 1,
+            VerificationStatus::Missing => self.missing += 1,
+            VerificationStatus::Error => self.error += 1,
+        }
+    }
+}
+
+/// Отчет для администратора: число проверок по статусам и сами проверки
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageVerificationReport {
+    pub summary: ScrubReport,
+    pub items: Vec<StorageVerification>,
+    pub limit: i64,
+    pub offset: i64,
+    pub total: i64,
+}
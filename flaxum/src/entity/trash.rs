@@ -0,0 +1,28 @@
+use crate::entity::object::ObjectType;
+use crate::scalar::Id;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Объект корзины с истекшим сроком хранения вместе с размером поддерева
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiredTrashItem {
+    pub id: Id,
+    pub owner_id: Id,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: ObjectType,
+    pub trashed_at: NaiveDateTime,
+    pub object_count: i64,
+    pub total_size: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashPurgeReport {
+    pub dry_run: bool,
+    pub expired_before: NaiveDateTime,
+    pub object_count: i64,
+    pub total_size: i64,
+    pub items: Vec<ExpiredTrashItem>,
+}
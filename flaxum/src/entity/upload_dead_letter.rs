@@ -0,0 +1,26 @@
+use crate::scalar::Id;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Событие загрузки, которое file_worker не смог обработать за все попытки.
+/// `payload` - тело сообщения как есть, `object_id` пуст, если его не удалось разобрать
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadDeadLetter {
+    pub id: Id,
+    pub routing_key: String,
+    pub object_id: Option<Id>,
+    pub payload: String,
+    pub error: String,
+    pub attempts: i32,
+    pub created_at: NaiveDateTime,
+    pub replayed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UploadDeadLettersPaginated {
+    pub items: Vec<UploadDeadLetter>,
+    pub limit: i64,
+    pub offset: i64,
+    pub total: i64,
+}
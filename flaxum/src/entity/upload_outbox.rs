@@ -0,0 +1,33 @@
+use crate::scalar::Id;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Событие загрузки, ожидающее публикации в `EXCHANGE_UPLOAD_OBJECT`
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadOutboxMessage {
+    pub id: Id,
+    pub routing_key: String,
+    pub payload: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadOutboxCreateModel {
+    pub id: Id,
+    pub routing_key: String,
+    pub payload: String,
+}
+
+impl UploadOutboxCreateModel {
+    pub fn new<T: Serialize>(routing_key: &str, event: &T) -> serde_json::Result<Self> {
+        Ok(Self {
+            id: Id::new_v4(),
+            routing_key: routing_key.to_string(),
+            payload: serde_json::to_string(event)?,
+        })
+    }
+}
@@ -0,0 +1,45 @@
+use crate::scalar::Id;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Сессия возобновляемой загрузки: файл дописывается кусками в `tmp/{user_id}.{id}`
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSession {
+    pub id: Id,
+    pub user_id: Id,
+    pub parent_id: Option<Id>,
+    pub name: String,
+    pub mimetype: Option<String>,
+    pub size: i64,
+    pub upload_offset: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    /// Запрос, который сейчас пишет кусок, и срок его права на запись
+    #[serde(skip)]
+    pub writer_id: Option<Id>,
+    #[serde(skip)]
+    pub writing_until: Option<NaiveDateTime>,
+}
+
+impl UploadSession {
+    pub fn tmp_path(&self) -> String {
+        format!("tmp/{}.{}", self.user_id, self.id)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.upload_offset == self.size
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadSessionCreateModel {
+    pub id: Id,
+    pub user_id: Id,
+    pub parent_id: Option<Id>,
+    pub name: String,
+    pub mimetype: Option<String>,
+    pub size: i64,
+    pub expires_at: NaiveDateTime,
+}
@@ -34,6 +34,7 @@ pub struct User {
     pub is_blocked: bool,
     pub blocked_at: Option<NaiveDateTime>,
     pub storage_size: i64,
+    pub storage_quota: Option<i64>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -53,6 +54,7 @@ impl User {
         is_blocked: bool,
         blocked_at: Option<NaiveDateTime>,
         storage_size: i64,
+        storage_quota: Option<i64>,
     ) -> User {
         User {
             id,
@@ -69,6 +71,7 @@ impl User {
             is_blocked,
             blocked_at,
             storage_size,
+            storage_quota,
         }
     }
 }
@@ -90,6 +93,7 @@ impl From<PgRow> for User {
             value.get("is_blocked"),
             value.get("blocked_at"),
             value.get("storage_size"),
+            value.get("storage_quota"),
         )
     }
 }
@@ -113,6 +117,7 @@ impl User {
             is_blocked: false,
             blocked_at: None,
             storage_size: 0,
+            storage_quota: None,
         }
     }
 }
@@ -127,6 +132,7 @@ pub struct PublicUser {
     pub email: String,
     pub role_type: UserRole,
     pub storage_size: i64,
+    pub storage_quota: Option<i64>,
 }
 
 impl From<User> for PublicUser {
@@ -139,6 +145,7 @@ impl From<User> for PublicUser {
             email: user.email,
             role_type: user.role_type,
             storage_size: user.storage_size,
+            storage_quota: user.storage_quota,
         }
     }
 }
@@ -160,6 +167,7 @@ pub struct AdminUser {
     is_blocked: bool,
     blocked_at: Option<NaiveDateTime>,
     storage_size: i64,
+    storage_quota: Option<i64>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -178,6 +186,7 @@ impl AdminUser {
         is_blocked: bool,
         blocked_at: Option<NaiveDateTime>,
         storage_size: i64,
+        storage_quota: Option<i64>,
     ) -> AdminUser {
         AdminUser {
             id,
@@ -193,6 +202,7 @@ impl AdminUser {
             is_blocked,
             blocked_at,
             storage_size,
+            storage_quota,
         }
     }
 }
@@ -213,6 +223,7 @@ impl From<PgRow> for AdminUser {
             value.get("is_blocked"),
             value.get("blocked_at"),
             value.get("storage_size"),
+            value.get("storage_quota"),
         )
     }
 }
@@ -233,10 +244,27 @@ impl From<User> for AdminUser {
             is_blocked: user.is_blocked,
             blocked_at: user.blocked_at,
             storage_size: user.storage_size,
+            storage_quota: user.storage_quota,
         }
     }
 }
 
+/// Занятое место: живые файлы и файлы в корзине.
+/// Квота и `storage_size` считаются в логических байтах - каждый файл своим размером,
+/// даже если его содержимое хранится один раз. `physical_size` - реально занятое
+/// в S3 место после дедупликации. Файлы роботов сюда не входят
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    pub storage_size: i64,
+    pub storage_quota: Option<i64>,
+    pub live_size: i64,
+    pub live_files: i64,
+    pub trash_size: i64,
+    pub trash_files: i64,
+    pub physical_size: i64,
+}
+
 /// Ифнормация о пользователях для админа
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AdminUsersPaginated {
@@ -2,13 +2,15 @@ use std::io;
 
 use crate::error::{
     backend_error::BackendError, db_error::DbError, id_error::IdError, io_error::WriteReadError,
-    s3_error::ApiS3Error, token_error::TokenError, user_error::UserError,
+    object_error::ObjectError, request_error::RequestError, share_error::ShareError,
+    storage_error::ApiStorageError, token_error::TokenError, upload_error::UploadError,
+    user_error::UserError,
 };
-use aws_sdk_s3;
 use axum::{
     extract::multipart::MultipartError,
     response::{IntoResponse, Response},
 };
+use file_worker::storage::StorageError;
 use sqlx;
 use thiserror::Error;
 use uuid;
@@ -28,9 +30,17 @@ pub enum ApiError {
     #[error(transparent)]
     WriteReadError(#[from] WriteReadError),
     #[error(transparent)]
-    ApiS3Error(#[from] ApiS3Error),
+    ApiStorageError(#[from] ApiStorageError),
     #[error(transparent)]
     BackendError(#[from] BackendError),
+    #[error(transparent)]
+    ObjectError(#[from] ObjectError),
+    #[error(transparent)]
+    UploadError(#[from] UploadError),
+    #[error(transparent)]
+    ShareError(#[from] ShareError),
+    #[error(transparent)]
+    RequestError(#[from] RequestError),
 }
 
 impl IntoResponse for ApiError {
@@ -42,8 +52,12 @@ impl IntoResponse for ApiError {
             ApiError::MultipartError(error) => error.into_response(),
             ApiError::IdError(error) => error.into_response(),
             ApiError::WriteReadError(error) => error.into_response(),
-            ApiError::ApiS3Error(error) => error.into_response(),
+            ApiError::ApiStorageError(error) => error.into_response(),
             ApiError::BackendError(error) => error.into_response(),
+            ApiError::ObjectError(error) => error.into_response(),
+            ApiError::UploadError(error) => error.into_response(),
+            ApiError::ShareError(error) => error.into_response(),
+            ApiError::RequestError(error) => error.into_response(),
         }
     }
 }
@@ -66,8 +80,8 @@ impl From<io::Error> for ApiError {
     }
 }
 
-impl From<aws_sdk_s3::Error> for ApiError {
-    fn from(error: aws_sdk_s3::Error) -> Self {
-        ApiError::ApiS3Error(ApiS3Error::from(error))
+impl From<StorageError> for ApiError {
+    fn from(error: StorageError) -> Self {
+        ApiError::ApiStorageError(ApiStorageError::from(error))
     }
 }
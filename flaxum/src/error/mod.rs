@@ -3,7 +3,10 @@ pub(crate) mod backend_error;
 pub(crate) mod db_error;
 pub(crate) mod id_error;
 pub(crate) mod io_error;
+pub(crate) mod object_error;
 pub(crate) mod request_error;
-pub(crate) mod s3_error;
+pub(crate) mod share_error;
+pub(crate) mod storage_error;
 pub(crate) mod token_error;
+pub(crate) mod upload_error;
 pub(crate) mod user_error;
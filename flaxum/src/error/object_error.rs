@@ -0,0 +1,71 @@
+use crate::response::api_response::ApiErrorResponse;
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ObjectError {
+    #[error("Object not found")]
+    ObjectNotFound,
+    #[error("Object is not a file")]
+    NotAFile,
+    #[error("Object decode key is invalid")]
+    InvalidDecodeKey,
+    #[error("Access to object denied")]
+    AccessDenied,
+    #[error("Access of the object owner cannot be changed")]
+    OwnerAccess,
+    #[error("Parent object is not a folder")]
+    ParentNotAFolder,
+    #[error("File is still being uploaded to storage, retry later")]
+    UploadPending,
+    #[error("File upload to storage failed")]
+    UploadFailed,
+    #[error("Stored file is corrupted")]
+    CorruptedBlob,
+    #[error("Object with this name already exists in the folder")]
+    NameConflict,
+    #[error("Folder cannot be moved into itself or its subfolder")]
+    MoveIntoItself,
+    #[error("Folder cannot be copied into itself or its subfolder")]
+    CopyIntoItself,
+    #[error("Job not found")]
+    JobNotFound,
+    #[error("Action requires {0}")]
+    MissingActionParameter(&'static str),
+}
+
+/// Через сколько секунд клиенту стоит повторить скачивание незагруженного файла
+const UPLOAD_PENDING_RETRY_AFTER: u32 = 5;
+
+impl IntoResponse for ObjectError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            ObjectError::ObjectNotFound => StatusCode::NOT_FOUND,
+            ObjectError::NotAFile => StatusCode::BAD_REQUEST,
+            ObjectError::InvalidDecodeKey => StatusCode::INTERNAL_SERVER_ERROR,
+            ObjectError::AccessDenied => StatusCode::FORBIDDEN,
+            ObjectError::OwnerAccess => StatusCode::BAD_REQUEST,
+            ObjectError::ParentNotAFolder => StatusCode::BAD_REQUEST,
+            ObjectError::UploadPending => StatusCode::CONFLICT,
+            ObjectError::UploadFailed => StatusCode::CONFLICT,
+            ObjectError::CorruptedBlob => StatusCode::INTERNAL_SERVER_ERROR,
+            ObjectError::NameConflict => StatusCode::CONFLICT,
+            ObjectError::MoveIntoItself => StatusCode::BAD_REQUEST,
+            ObjectError::CopyIntoItself => StatusCode::BAD_REQUEST,
+            ObjectError::JobNotFound => StatusCode::NOT_FOUND,
+            ObjectError::MissingActionParameter(_) => StatusCode::BAD_REQUEST,
+        };
+
+        let mut response = ApiErrorResponse::send(status_code.as_u16(), Some(self.to_string()));
+        if let ObjectError::UploadPending = self {
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(UPLOAD_PENDING_RETRY_AFTER),
+            );
+        }
+        response
+    }
+}
@@ -0,0 +1,40 @@
+use crate::response::api_response::ApiErrorResponse;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ShareError {
+    #[error("Share link not found")]
+    LinkNotFound,
+    #[error("Share link has expired")]
+    LinkExpired,
+    #[error("Share link download limit reached")]
+    DownloadLimitReached,
+    #[error("Share link is password protected")]
+    PasswordRequired,
+    #[error("Invalid share link password")]
+    InvalidPassword,
+    #[error("Share link is read-only")]
+    ReadOnly,
+    #[error("Share link expiry must be in the future")]
+    InvalidExpiry,
+}
+
+impl IntoResponse for ShareError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            ShareError::LinkNotFound => StatusCode::NOT_FOUND,
+            ShareError::LinkExpired => StatusCode::GONE,
+            ShareError::DownloadLimitReached => StatusCode::GONE,
+            ShareError::PasswordRequired => StatusCode::UNAUTHORIZED,
+            ShareError::InvalidPassword => StatusCode::FORBIDDEN,
+            ShareError::ReadOnly => StatusCode::FORBIDDEN,
+            ShareError::InvalidExpiry => StatusCode::BAD_REQUEST,
+        };
+
+        ApiErrorResponse::send(status_code.as_u16(), Some(self.to_string()))
+    }
+}
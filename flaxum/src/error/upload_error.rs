@@ -0,0 +1,55 @@
+use crate::response::api_response::ApiErrorResponse;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum UploadError {
+    #[error("Upload session not found")]
+    SessionNotFound,
+    #[error("Upload offset mismatch, current offset is {0}")]
+    OffsetMismatch(i64),
+    #[error("Another request is writing to this upload session")]
+    SessionBusy,
+    #[error("Missing or invalid Upload-Offset header")]
+    InvalidOffset,
+    #[error("Chunk exceeds declared upload size")]
+    SizeExceeded,
+    #[error("Upload is incomplete: {0} of {1} bytes received")]
+    Incomplete(i64, i64),
+    #[error("Dead-lettered upload not found")]
+    DeadLetterNotFound,
+    #[error("Dead-lettered upload was already replayed")]
+    DeadLetterReplayed,
+    #[error("Request has no file field")]
+    NoFileField,
+    #[error("File path must be relative and must not contain `..`")]
+    InvalidPath,
+    #[error("Archive is damaged or not supported, expected .zip, .tar or .tar.gz")]
+    InvalidArchive,
+    #[error("Archive has too many entries or is too large when unpacked")]
+    ArchiveTooLarge,
+}
+
+impl IntoResponse for UploadError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            UploadError::SessionNotFound => StatusCode::NOT_FOUND,
+            UploadError::OffsetMismatch(_) => StatusCode::CONFLICT,
+            UploadError::SessionBusy => StatusCode::CONFLICT,
+            UploadError::InvalidOffset => StatusCode::BAD_REQUEST,
+            UploadError::SizeExceeded => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::Incomplete(_, _) => StatusCode::CONFLICT,
+            UploadError::DeadLetterNotFound => StatusCode::NOT_FOUND,
+            UploadError::DeadLetterReplayed => StatusCode::CONFLICT,
+            UploadError::NoFileField => StatusCode::BAD_REQUEST,
+            UploadError::InvalidPath => StatusCode::BAD_REQUEST,
+            UploadError::InvalidArchive => StatusCode::BAD_REQUEST,
+            UploadError::ArchiveTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        };
+
+        ApiErrorResponse::send(status_code.as_u16(), Some(self.to_string()))
+    }
+}
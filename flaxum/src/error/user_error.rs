@@ -19,6 +19,8 @@ pub enum UserError {
     UserAlreadyExists,
     #[error("Invalid password")]
     InvalidPassword,
+    #[error("Storage quota exceeded")]
+    StorageQuotaExceeded,
 }
 
 impl IntoResponse for UserError {
@@ -30,6 +32,7 @@ impl IntoResponse for UserError {
             UserError::UserDeleted => StatusCode::UNAUTHORIZED,
             UserError::UserAlreadyExists => StatusCode::BAD_REQUEST,
             UserError::InvalidPassword => StatusCode::BAD_REQUEST,
+            UserError::StorageQuotaExceeded => StatusCode::PAYLOAD_TOO_LARGE,
         };
 
         ApiErrorResponse::send(status_code.as_u16(), Some(self.to_string()))
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::service::scrub_service::{ScrubService, SCRUB_BATCH_SIZE};
+
+const SCRUB_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Периодическая проверка содержимого в хранилище пачками по `SCRUB_BATCH_SIZE`
+pub async fn run(config: Arc<AppConfig>) {
+    let service = ScrubService::new(
+        &config.db_conn,
+        &config.storage,
+        &config.keyring,
+        config.env.scrub_rate_bytes_per_sec,
+    );
+    let mut interval = tokio::time::interval(SCRUB_INTERVAL);
+    loop {
+        interval.tick().await;
+        match service.run_batch(SCRUB_BATCH_SIZE).await {
+            Ok(report) if report.checked == 0 => {}
+            Ok(report) => tracing::info!(
+                "Integrity scrub checked {}: {} ok, {} mismatch, {} missing, {} error",
+                report.checked,
+                report.ok,
+                report.mismatch,
+                report.missing,
+                report.error
+            ),
+            Err(err) => tracing::error!("Integrity scrub failed: {}", err),
+        }
+    }
+}
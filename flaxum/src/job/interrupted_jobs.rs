@@ -0,0 +1,14 @@
+use std::sync::Arc;
+
+use crate::config::AppConfig;
+use crate::repository::object_job_repository::{ObjectJobRepository, ObjectJobRepositoryTrait};
+
+/// Операции над объектами, оставшиеся незавершенными после прошлого запуска, помечаются упавшими
+pub async fn run(config: Arc<AppConfig>) {
+    let job_repo = ObjectJobRepository::new(&config.db_conn);
+    match job_repo.fail_interrupted().await {
+        Ok(0) => {}
+        Ok(count) => tracing::warn!("Marked {} interrupted object jobs as failed", count),
+        Err(err) => tracing::error!("Failed to mark interrupted object jobs: {}", err),
+    }
+}
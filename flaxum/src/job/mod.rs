@@ -0,0 +1,20 @@
+mod integrity_scrub;
+mod interrupted_jobs;
+mod storage_gc;
+mod trash_purge;
+mod upload_outbox_relay;
+mod upload_session_gc;
+
+use std::sync::Arc;
+
+use crate::config::AppConfig;
+
+/// Запуск фоновых задач API
+pub fn spawn_jobs(config: Arc<AppConfig>) {
+    tokio::spawn(interrupted_jobs::run(config.clone()));
+    tokio::spawn(upload_session_gc::run(config.clone()));
+    tokio::spawn(trash_purge::run(config.clone()));
+    tokio::spawn(integrity_scrub::run(config.clone()));
+    tokio::spawn(storage_gc::run(config.clone()));
+    tokio::spawn(upload_outbox_relay::run(config));
+}
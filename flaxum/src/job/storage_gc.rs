@@ -0,0 +1,28 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::service::storage_gc_service::StorageGcService;
+
+const STORAGE_GC_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Периодическая сверка хранилища; очистка только при `STORAGE_GC_CLEANUP=true`
+pub async fn run(config: Arc<AppConfig>) {
+    let service = StorageGcService::new(&config.db_conn, &config.storage);
+    let mut interval = tokio::time::interval(STORAGE_GC_INTERVAL);
+    loop {
+        interval.tick().await;
+        match service.run(config.env.storage_gc_cleanup).await {
+            Ok(report) => tracing::info!(
+                "Storage gc found {} temp files, {} orphan objects, {} missing, {} stale uploads; reclaimed {} bytes, {} failed",
+                report.temp_files.len(),
+                report.orphan_objects.len(),
+                report.missing_content.len(),
+                report.stale_uploads.len(),
+                report.reclaimed_size,
+                report.failed
+            ),
+            Err(err) => tracing::error!("Storage gc failed: {}", err),
+        }
+    }
+}
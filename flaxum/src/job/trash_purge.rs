@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::service::trash_service::TrashService;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Периодическое удаление объектов, пролежавших в корзине дольше `TRASH_RETENTION_DAYS`
+pub async fn run(config: Arc<AppConfig>) {
+    let service = TrashService::new(
+        &config.db_conn,
+        &config.storage,
+        config.env.trash_retention_days,
+    );
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match service.purge_expired(false).await {
+            Ok(report) if report.items.is_empty() => {}
+            Ok(report) => tracing::info!(
+                "Trash purge removed {} objects, {} bytes",
+                report.object_count,
+                report.total_size
+            ),
+            Err(err) => tracing::error!("Trash purge failed: {}", err),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::MissedTickBehavior;
+
+use crate::config::AppConfig;
+use crate::service::upload_outbox_service::UploadOutboxService;
+
+const RELAY_INTERVAL: Duration = Duration::from_secs(1);
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Публикация событий загрузки из outbox и очистка уже отправленных
+pub async fn run(config: Arc<AppConfig>) {
+    let service = UploadOutboxService::new(&config.db_conn, &config.rmq_conn);
+    let mut relay = tokio::time::interval(RELAY_INTERVAL);
+    relay.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut cleanup = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = relay.tick() => match service.relay_pending().await {
+                Ok(0) => {}
+                Ok(sent) => tracing::debug!("Upload outbox relay published {} events", sent),
+                Err(err) => tracing::error!("Upload outbox relay failed: {}", err),
+            },
+            _ = cleanup.tick() => match service.delete_sent().await {
+                Ok(0) => {}
+                Ok(deleted) => tracing::info!("Upload outbox cleanup removed {} events", deleted),
+                Err(err) => tracing::error!("Upload outbox cleanup failed: {}", err),
+            },
+        }
+    }
+}
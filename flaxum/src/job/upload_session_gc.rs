@@ -0,0 +1,21 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::service::upload_session_service::UploadSessionService;
+
+const GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Периодическая очистка просроченных сессий возобновляемой загрузки
+pub async fn run(config: Arc<AppConfig>) {
+    let service = UploadSessionService::new(&config.db_conn, &config.storage, &config.keyring);
+    let mut interval = tokio::time::interval(GC_INTERVAL);
+    loop {
+        interval.tick().await;
+        match service.remove_expired().await {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!("Removed {} expired upload sessions", removed),
+            Err(err) => tracing::error!("Upload session gc failed: {}", err),
+        }
+    }
+}
@@ -3,6 +3,7 @@ pub mod db;
 pub mod dto;
 pub mod entity;
 pub mod error;
+pub mod job;
 pub mod logger;
 pub mod middleware;
 pub mod repository;
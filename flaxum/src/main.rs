@@ -5,7 +5,7 @@ use flaxum::routes::root::app;
 use tokio::net::TcpListener;
 use tokio::task;
 
-use flaxum::{config::AppConfig, logger};
+use flaxum::{config::AppConfig, job, logger};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -16,6 +16,7 @@ async fn main() -> anyhow::Result<()> {
         tracing::warn!("worker spawn activation");
         file_worker::spawn_worker().await;
     });
+    job::spawn_jobs(config.clone());
     let listener = TcpListener::bind(config.env.api_address.to_string()).await?;
     let app = app(config.clone()).await;
     tracing::info!("Server start's on {}", &config.env.api_address.to_string());
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::Database,
+    entity::blob::{Blob, BlobCreateModel},
+    entity::object::UploadStatus,
+    scalar::Id,
+};
+
+use sqlx::Error as SqlxError;
+use sqlx::{PgConnection, Postgres, Transaction};
+
+/// Все запросы к блобам выполняются в транзакции или на соединении вызывающего
+#[derive(Clone)]
+pub struct BlobRepository;
+
+pub trait BlobRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn select_by_id(&self, conn: &mut PgConnection, id: Id) -> Result<Blob, SqlxError>;
+    async fn select_for_dedup(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        owner_id: Id,
+        hash_sha256: &str,
+        size: i64,
+    ) -> Result<Option<Blob>, SqlxError>;
+    async fn insert(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        create_model: BlobCreateModel,
+    ) -> Result<Blob, SqlxError>;
+    async fn acquire(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Blob, SqlxError>;
+    async fn release(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        ids: &[Id],
+    ) -> Result<Vec<Blob>, SqlxError>;
+    async fn set_upload_status(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        status: UploadStatus,
+    ) -> Result<(), SqlxError>;
+}
+
+impl BlobRepositoryTrait for BlobRepository {
+    fn new(_db_conn: &Arc<Database>) -> Self {
+        Self
+    }
+
+    async fn select_by_id(&self, conn: &mut PgConnection, id: Id) -> Result<Blob, SqlxError> {
+        let q = r#"SELECT * FROM "Blob" WHERE id = $1"#;
+
+        sqlx::query_as::<_, Blob>(q).bind(id).fetch_one(conn).await
+    }
+
+    /// Живой блоб владельца с тем же содержимым, строка блокируется до конца транзакции.
+    /// Блобы с неудачной загрузкой не переиспользуются
+    async fn select_for_dedup(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        owner_id: Id,
+        hash_sha256: &str,
+        size: i64,
+    ) -> Result<Option<Blob>, SqlxError> {
+        let q = r#"
+        SELECT * FROM "Blob"
+        WHERE owner_id = $1 AND hash_sha256 = $2 AND size = $3
+        AND ref_count > 0 AND decode_key IS NOT NULL AND upload_status != 'failed'
+        ORDER BY created_at ASC
+        LIMIT 1
+        FOR UPDATE
+        "#;
+
+        sqlx::query_as::<_, Blob>(q)
+            .bind(owner_id)
+            .bind(hash_sha256)
+            .bind(size)
+            .fetch_optional(&mut **tx)
+            .await
+    }
+
+    async fn insert(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        create_model: BlobCreateModel,
+    ) -> Result<Blob, SqlxError> {
+        let q = r#"
+        INSERT INTO "Blob" (id, owner_id, hash_sha256, size, decode_key, format_version)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, Blob>(q)
+            .bind(create_model.id)
+            .bind(create_model.owner_id)
+            .bind(create_model.hash_sha256)
+            .bind(create_model.size)
+            .bind(create_model.decode_key)
+            .bind(create_model.format_version)
+            .fetch_one(&mut **tx)
+            .await
+    }
+
+    /// Новая ссылка на существующий блоб
+    async fn acquire(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Blob, SqlxError> {
+        let q = r#"
+        UPDATE "Blob" SET ref_count = ref_count + 1
+        WHERE id = $1 AND ref_count > 0
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, Blob>(q)
+            .bind(id)
+            .fetch_one(&mut **tx)
+            .await
+    }
+
+    /// Снятие ссылок удаленных объектов, `ids` могут повторяться.
+    /// Возвращает блобы, на которые больше никто не ссылается
+    async fn release(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        ids: &[Id],
+    ) -> Result<Vec<Blob>, SqlxError> {
+        let q = r#"
+        WITH released AS (
+            SELECT id, COUNT(*)::INTEGER AS refs
+            FROM unnest($1::uuid[]) AS id
+            GROUP BY id
+        )
+        UPDATE "Blob" SET ref_count = GREATEST("Blob".ref_count - released.refs, 0)
+        FROM released
+        WHERE "Blob".id = released.id
+        RETURNING "Blob".*
+        "#;
+
+        let blobs = sqlx::query_as::<_, Blob>(q)
+            .bind(ids)
+            .fetch_all(&mut **tx)
+            .await?;
+        Ok(blobs
+            .into_iter()
+            .filter(|blob| blob.ref_count == 0)
+            .collect())
+    }
+
+    /// Статус загрузки блоба вместе со ссылающимися на него объектами
+    async fn set_upload_status(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        status: UploadStatus,
+    ) -> Result<(), SqlxError> {
+        let q = r#"UPDATE "Blob" SET upload_status = $1 WHERE id = $2"#;
+        sqlx::query(q)
+            .bind(status)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+
+        let q = r#"
+        UPDATE "Object" SET upload_status = $1, upload_s3 = $2
+        WHERE blob_id = $3
+        "#;
+        sqlx::query(q)
+            .bind(status)
+            .bind(status == UploadStatus::Stored)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::{Database, DatabaseTrait},
+    entity::key_rotation::WrappedKeyRow,
+    scalar::Id,
+};
+
+use sqlx::Error as SqlxError;
+
+/// Места хранения обернутых ключей данных
+#[derive(Debug, Clone, Copy)]
+pub enum KeySource {
+    Blob,
+    Object,
+    RobotObject,
+    /// Неотправленные события загрузки
+    UploadOutbox,
+    /// Еще не повторенные отложенные события загрузки
+    UploadDeadLetter,
+}
+
+impl KeySource {
+    pub const ALL: [KeySource; 5] = [
+        KeySource::Blob,
+        KeySource::Object,
+        KeySource::RobotObject,
+        KeySource::UploadOutbox,
+        KeySource::UploadDeadLetter,
+    ];
+
+    /// Ключ лежит в теле события, а не в колонке `decode_key`
+    pub fn is_payload(&self) -> bool {
+        matches!(self, KeySource::UploadOutbox | KeySource::UploadDeadLetter)
+    }
+
+    fn table(&self) -> &'static str {
+        match self {
+            KeySource::Blob => r#""Blob""#,
+            KeySource::Object => r#""Object""#,
+            KeySource::RobotObject => r#""RobotObject""#,
+            KeySource::UploadOutbox => r#""UploadOutbox""#,
+            KeySource::UploadDeadLetter => r#""UploadDeadLetter""#,
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        if self.is_payload() {
+            "payload"
+        } else {
+            "decode_key"
+        }
+    }
+
+    fn filter(&self) -> &'static str {
+        match self {
+            KeySource::UploadOutbox => "sent_at IS NULL",
+            KeySource::UploadDeadLetter => "replayed_at IS NULL",
+            _ => "decode_key IS NOT NULL",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyRotationRepository {
+    pub(crate) db_conn: Arc<Database>,
+}
+
+pub trait KeyRotationRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn select_batch(
+        &self,
+        source: KeySource,
+        after: Option<Id>,
+        limit: i64,
+    ) -> Result<Vec<WrappedKeyRow>, SqlxError>;
+    async fn update_value(
+        &self,
+        source: KeySource,
+        id: Id,
+        old_value: &str,
+        new_value: &str,
+    ) -> Result<bool, SqlxError>;
+}
+
+impl KeyRotationRepositoryTrait for KeyRotationRepository {
+    fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    /// Следующая пачка строк по возрастанию id, начиная после `after`
+    async fn select_batch(
+        &self,
+        source: KeySource,
+        after: Option<Id>,
+        limit: i64,
+    ) -> Result<Vec<WrappedKeyRow>, SqlxError> {
+        let q = format!(
+            r#"
+        SELECT id, {column} AS value FROM {table}
+        WHERE {filter} AND ($1::uuid IS NULL OR id > $1)
+        ORDER BY id
+        LIMIT $2
+        "#,
+            column = source.column(),
+            table = source.table(),
+            filter = source.filter(),
+        );
+
+        sqlx::query_as::<_, WrappedKeyRow>(&q)
+            .bind(after)
+            .bind(limit)
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Замена значения, только если оно не изменилось с момента чтения
+    async fn update_value(
+        &self,
+        source: KeySource,
+        id: Id,
+        old_value: &str,
+        new_value: &str,
+    ) -> Result<bool, SqlxError> {
+        let q = format!(
+            r#"
+        UPDATE {table} SET {column} = $3
+        WHERE id = $1 AND {column} = $2
+        "#,
+            column = source.column(),
+            table = source.table(),
+        );
+
+        let res = sqlx::query(&q)
+            .bind(id)
+            .bind(old_value)
+            .bind(new_value)
+            .execute(self.db_conn.get_pool())
+            .await?;
+        Ok(res.rows_affected() > 0)
+    }
+}
@@ -1,6 +1,15 @@
+pub(crate) mod blob_repository;
 pub(crate) mod object_repository;
-pub(crate) mod s3_repository;
 pub(crate) mod user_repository;
 pub(crate) mod uxo_repository;
 pub(crate) mod robot_object_repository;
 pub(crate) mod robot_repository;
+pub(crate) mod upload_session_repository;
+pub(crate) mod share_link_repository;
+pub(crate) mod upload_dead_letter_repository;
+pub(crate) mod upload_outbox_repository;
+pub(crate) mod key_rotation_repository;
+pub(crate) mod storage_verification_repository;
+pub(crate) mod storage_repository;
+pub(crate) mod storage_gc_repository;
+pub(crate) mod object_job_repository;
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::{Database, DatabaseTrait},
+    entity::object_job::{ObjectJob, ObjectJobKind, ObjectJobStatus},
+    scalar::Id,
+};
+use chrono::Utc;
+
+use sqlx::Error as SqlxError;
+
+#[derive(Clone)]
+pub struct ObjectJobRepository {
+    pub(crate) db_conn: Arc<Database>,
+}
+
+pub trait ObjectJobRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn insert(
+        &self,
+        id: Id,
+        user_id: Id,
+        kind: ObjectJobKind,
+        total: i32,
+    ) -> Result<ObjectJob, SqlxError>;
+    async fn select_by_id(&self, id: Id) -> Result<Option<ObjectJob>, SqlxError>;
+    async fn update_progress(&self, id: Id, processed: i32, skipped: i32) -> Result<(), SqlxError>;
+    async fn finish(
+        &self,
+        id: Id,
+        status: ObjectJobStatus,
+        object_id: Option<Id>,
+        error: Option<String>,
+    ) -> Result<(), SqlxError>;
+    async fn fail_interrupted(&self) -> Result<u64, SqlxError>;
+}
+
+impl ObjectJobRepositoryTrait for ObjectJobRepository {
+    fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    async fn insert(
+        &self,
+        id: Id,
+        user_id: Id,
+        kind: ObjectJobKind,
+        total: i32,
+    ) -> Result<ObjectJob, SqlxError> {
+        let q = r#"
+        INSERT INTO "ObjectJob" (id, user_id, kind, total)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ObjectJob>(q)
+            .bind(id)
+            .bind(user_id)
+            .bind(kind)
+            .bind(total)
+            .fetch_one(self.db_conn.get_pool())
+            .await
+    }
+
+    async fn select_by_id(&self, id: Id) -> Result<Option<ObjectJob>, SqlxError> {
+        let q = r#"SELECT * FROM "ObjectJob" WHERE id = $1"#;
+
+        sqlx::query_as::<_, ObjectJob>(q)
+            .bind(id)
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+
+    async fn update_progress(&self, id: Id, processed: i32, skipped: i32) -> Result<(), SqlxError> {
+        let q = r#"
+        UPDATE "ObjectJob" SET processed = $2, skipped = $3, updated_at = $4
+        WHERE id = $1
+        "#;
+        sqlx::query(q)
+            .bind(id)
+            .bind(processed)
+            .bind(skipped)
+            .bind(Utc::now().naive_utc())
+            .execute(self.db_conn.get_pool())
+            .await?;
+        Ok(())
+    }
+
+    async fn finish(
+        &self,
+        id: Id,
+        status: ObjectJobStatus,
+        object_id: Option<Id>,
+        error: Option<String>,
+    ) -> Result<(), SqlxError> {
+        let q = r#"
+        UPDATE "ObjectJob" SET status = $2, object_id = $3, error = $4, updated_at = $5
+        WHERE id = $1
+        "#;
+        sqlx::query(q)
+            .bind(id)
+            .bind(status)
+            .bind(object_id)
+            .bind(error)
+            .bind(Utc::now().naive_utc())
+            .execute(self.db_conn.get_pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Операции, прерванные остановкой API: задачи живут в процессе и после
+    /// перезапуска не продолжаются
+    async fn fail_interrupted(&self) -> Result<u64, SqlxError> {
+        let q = r#"
+        UPDATE "ObjectJob" SET status = 'failed', error = 'interrupted by restart', updated_at = $1
+        WHERE status = 'running'
+        "#;
+        let res = sqlx::query(q)
+            .bind(Utc::now().naive_utc())
+            .execute(self.db_conn.get_pool())
+            .await?;
+        Ok(res.rows_affected())
+    }
+}
@@ -1,17 +1,24 @@
 use std::sync::Arc;
 
 use crate::{
-    config::database::{Database, DatabaseTrait},
+    config::{
+        database::{Database, DatabaseTrait},
+        MAX_TREE_DEPTH,
+    },
     db::pagination_query_builder,
     dto::object::GetObjectListDto,
-    entity::object::{Object, ObjectCreateModel, ObjectsPaginated},
+    entity::object::{Object, ObjectCreateModel, ObjectPathItem, ObjectsPaginated, SubtreeItem},
     entity::pagination::Pagination,
+    entity::trash::ExpiredTrashItem,
     scalar::Id,
 };
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 
 use sqlx::Error as SqlxError;
-use sqlx::{self, Postgres, QueryBuilder, Row, Transaction};
+use sqlx::{self, PgConnection, Postgres, QueryBuilder, Row, Transaction};
+
+/// Класс advisory-блокировок корня владельца при переносах и выборе имени в корне
+const OBJECT_ROOT_LOCK_CLASS: i32 = 0x464c_5854;
 
 #[derive(Clone)]
 pub struct ObjectRepository {
@@ -23,6 +30,7 @@ pub trait ObjectRepositoryTrait {
 
     async fn select_list(&self, pagination: Pagination) -> Result<ObjectsPaginated, SqlxError>;
     async fn select_by_id(&self, id: Id) -> Result<Object, SqlxError>;
+    async fn select_by_id_tx(&self, conn: &mut PgConnection, id: Id) -> Result<Object, SqlxError>;
     async fn select_own_list(
         &self,
         pagination: Pagination,
@@ -40,6 +48,39 @@ pub trait ObjectRepositoryTrait {
         pagination: Pagination,
         owner_id: Id,
     ) -> Result<ObjectsPaginated, SqlxError>;
+    async fn select_children(
+        &self,
+        pagination: Pagination,
+        parent_id: Id,
+    ) -> Result<ObjectsPaginated, SqlxError>;
+    async fn is_in_subtree(&self, object_id: Id, root_id: Id) -> Result<bool, SqlxError>;
+    async fn is_in_subtree_tx(
+        &self,
+        conn: &mut PgConnection,
+        object_id: Id,
+        root_id: Id,
+    ) -> Result<bool, SqlxError>;
+    async fn select_path(&self, user_id: Id, id: Id) -> Result<Vec<ObjectPathItem>, SqlxError>;
+    async fn select_subtree(&self, user_id: Id, id: Id) -> Result<Vec<SubtreeItem>, SqlxError>;
+    async fn exists_in_folder_tx(
+        &self,
+        conn: &mut PgConnection,
+        owner_id: Id,
+        parent_id: Option<Id>,
+        name: &str,
+        exclude_id: Id,
+    ) -> Result<bool, SqlxError>;
+    async fn select_in_folder(
+        &self,
+        owner_id: Id,
+        parent_id: Option<Id>,
+        name: &str,
+    ) -> Result<Option<Object>, SqlxError>;
+    async fn select_expired_trash(
+        &self,
+        expired_before: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<ExpiredTrashItem>, SqlxError>;
 
     async fn insert_object(
         &self,
@@ -47,9 +88,50 @@ pub trait ObjectRepositoryTrait {
         create_model: ObjectCreateModel,
     ) -> Result<Object, SqlxError>;
 
-    async fn mark_as_deleted(&self, id: Id) -> Result<Object, SqlxError>;
-    async fn mark_as_restored(&self, id: Id) -> Result<Object, SqlxError>;
-    async fn mark_as_eliminated(&self, id: Id) -> Result<Object, SqlxError>;
+    async fn lock_move(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        owner_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<(), SqlxError>;
+
+    async fn lock_folder(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        owner_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<(), SqlxError>;
+    async fn update_info(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        name: &str,
+        parent_id: Option<Id>,
+    ) -> Result<Object, SqlxError>;
+
+    async fn has_undeletable_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        id: Id,
+        with_trash: bool,
+    ) -> Result<bool, SqlxError>;
+    async fn mark_as_deleted(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Object, SqlxError>;
+    async fn mark_as_restored(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Object, SqlxError>;
+    async fn mark_as_eliminated(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Vec<Object>, SqlxError>;
 }
 
 impl ObjectRepositoryTrait for ObjectRepository {
@@ -60,15 +142,21 @@ impl ObjectRepositoryTrait for ObjectRepository {
     }
 
     async fn select_by_id(&self, id: Id) -> Result<Object, SqlxError> {
+        let mut conn = self.db_conn.get_pool().acquire().await?;
+        self.select_by_id_tx(&mut conn, id).await
+    }
+
+    /// То же, что `select_by_id`, на соединении `conn`: внутри транзакции видны ее изменения
+    async fn select_by_id_tx(&self, conn: &mut PgConnection, id: Id) -> Result<Object, SqlxError> {
         let q = r#"
         SELECT 
-        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256
+        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256, blob_id, upload_status
         FROM "Object"
         WHERE eliminated is false and id = $1 "#;
 
         sqlx::query_as::<_, Object>(q)
             .bind(id)
-            .fetch_one(self.db_conn.get_pool())
+            .fetch_one(conn)
             .await
     }
 
@@ -119,20 +207,70 @@ impl ObjectRepositoryTrait for ObjectRepository {
     ) -> Result<ObjectsPaginated, SqlxError> {
         let mut q = QueryBuilder::new(
             r#"
-            SELECT *, COUNT(*) OVER() as total_count
-            FROM "Object" 
-            JOIN "UserXObject" ON "Object".id = "UserXObject".object_id
+            SELECT "Object".*, COUNT(*) OVER() as total_count
+            FROM "Object"
             WHERE "Object".eliminated is false and "Object".in_trash is false
-            AND "Object".owner_id != "#,
+            "#,
         );
-        q.push_bind(uxo_owner);
 
         if let Some(parent_id) = body.parent_id {
+            // Содержимое доступной папки наследует ее права,
+            // кроме объектов с явным запретом на чтение
             q.push(r#" AND "Object".parent_id = "#);
             q.push_bind(parent_id);
+            q.push(
+                r#" AND NOT EXISTS (
+                SELECT 1 FROM "UserXObject"
+                WHERE "UserXObject".object_id = "Object".id
+                AND "UserXObject".can_read IS FALSE
+                AND "UserXObject".user_id = "#,
+            );
+            q.push_bind(uxo_owner);
+            q.push(")");
         } else {
-            q.push(r#" AND "UserXObject".user_id = "#);
+            q.push(r#" AND "Object".owner_id != "#);
+            q.push_bind(uxo_owner);
+            q.push(
+                r#" AND EXISTS (
+                SELECT 1 FROM "UserXObject"
+                WHERE "UserXObject".object_id = "Object".id
+                AND "UserXObject".can_read IS TRUE
+                AND "UserXObject".user_id = "#,
+            );
             q.push_bind(uxo_owner);
+            q.push(")");
+            // Объект в доступной папке виден в ней, в корне он не повторяется
+            q.push(
+                r#" AND NOT EXISTS (
+                WITH RECURSIVE ancestors AS (
+                    SELECT parent.id, parent.parent_id, 1 AS depth
+                    FROM "Object" AS parent
+                    WHERE parent.id = "Object".parent_id
+                    UNION ALL
+                    SELECT parent.id, parent.parent_id, ancestors.depth + 1
+                    FROM "Object" AS parent
+                    JOIN ancestors ON parent.id = ancestors.parent_id
+                    WHERE ancestors.depth < "#,
+            );
+            q.push_bind(MAX_TREE_DEPTH);
+            q.push(
+                r#"
+                )
+                SELECT 1 FROM (
+                    SELECT "UserXObject".can_read
+                    FROM ancestors
+                    JOIN "UserXObject" ON "UserXObject".object_id = ancestors.id
+                    WHERE "UserXObject".user_id = "#,
+            );
+            q.push_bind(uxo_owner);
+            q.push(
+                r#"
+                    ORDER BY ancestors.depth ASC
+                    LIMIT 1
+                ) AS nearest
+                WHERE nearest.can_read IS TRUE
+            )"#,
+            );
         };
         q.push(" ORDER BY type asc ");
 
@@ -163,10 +301,11 @@ impl ObjectRepositoryTrait for ObjectRepository {
             r#"
             SELECT *, COUNT(*) OVER() as total_count
             FROM "Object"
-            where eliminated is false and in_trash is true and owner_id = 
+            where eliminated is false and in_trash is true and trash_root_id = id and owner_id = 
             "#,
         );
         q.push_bind(owner_id);
+        q.push(" ORDER BY updated_at desc ");
         let mut q = pagination_query_builder(q, &pagination);
         let res = q.build().fetch_all(self.db_conn.get_pool()).await?;
 
@@ -186,6 +325,232 @@ impl ObjectRepositoryTrait for ObjectRepository {
         ))
     }
 
+    /// Содержимое папки без учета владельца, доступ проверяется выше
+    async fn select_children(
+        &self,
+        pagination: Pagination,
+        parent_id: Id,
+    ) -> Result<ObjectsPaginated, SqlxError> {
+        let mut q = QueryBuilder::new(
+            r#"SELECT *, COUNT(*) OVER() as total_count
+            FROM "Object"
+            WHERE eliminated IS FALSE AND in_trash IS FALSE AND parent_id = "#,
+        );
+        q.push_bind(parent_id);
+        q.push(" ORDER BY type asc, created_at desc ");
+
+        let mut q = pagination_query_builder(q, &pagination);
+        let res = q.build().fetch_all(self.db_conn.get_pool()).await?;
+        let mut total_count = 0;
+        let objects: Vec<Object> = res
+            .into_iter()
+            .map(|row| {
+                total_count = row.get::<i64, _>("total_count");
+                Object::from(row)
+            })
+            .collect();
+        Ok(ObjectsPaginated::build(
+            objects,
+            pagination.limit,
+            pagination.offset,
+            total_count,
+        ))
+    }
+
+    /// Объект совпадает с `root_id` или вложен в него,
+    /// и ни один объект на пути к нему не удален
+    async fn is_in_subtree(&self, object_id: Id, root_id: Id) -> Result<bool, SqlxError> {
+        let mut conn = self.db_conn.get_pool().acquire().await?;
+        self.is_in_subtree_tx(&mut conn, object_id, root_id).await
+    }
+
+    async fn is_in_subtree_tx(
+        &self,
+        conn: &mut PgConnection,
+        object_id: Id,
+        root_id: Id,
+    ) -> Result<bool, SqlxError> {
+        let q = r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id, 0 AS depth
+            FROM "Object"
+            WHERE id = $1 AND eliminated IS FALSE AND in_trash IS FALSE
+            UNION ALL
+            SELECT "Object".id, "Object".parent_id, ancestors.depth + 1
+            FROM "Object"
+            JOIN ancestors ON "Object".id = ancestors.parent_id
+            WHERE ancestors.id != $2 AND ancestors.depth < $3
+            AND "Object".eliminated IS FALSE AND "Object".in_trash IS FALSE
+        )
+        SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = $2)
+        "#;
+
+        sqlx::query_scalar::<_, bool>(q)
+            .bind(object_id)
+            .bind(root_id)
+            .bind(MAX_TREE_DEPTH)
+            .fetch_one(conn)
+            .await
+    }
+
+    /// Папки от корня до родителя объекта вместе с правом чтения
+    /// из записи `UserXObject` пользователя на каждую из них
+    async fn select_path(&self, user_id: Id, id: Id) -> Result<Vec<ObjectPathItem>, SqlxError> {
+        let q = r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id, name, 0 AS depth
+            FROM "Object"
+            WHERE id = $2
+            UNION ALL
+            SELECT "Object".id, "Object".parent_id, "Object".name, ancestors.depth + 1
+            FROM "Object"
+            JOIN ancestors ON "Object".id = ancestors.parent_id
+            WHERE ancestors.depth < $3
+        )
+        SELECT ancestors.id, ancestors.name, "UserXObject".can_read
+        FROM ancestors
+        LEFT JOIN "UserXObject"
+            ON "UserXObject".object_id = ancestors.id AND "UserXObject".user_id = $1
+        WHERE ancestors.depth > 0
+        ORDER BY ancestors.depth DESC
+        "#;
+
+        sqlx::query_as::<_, ObjectPathItem>(q)
+            .bind(user_id)
+            .bind(id)
+            .bind(MAX_TREE_DEPTH)
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Объект и все вложенные в него, кроме удаленных; родители идут раньше детей
+    async fn select_subtree(&self, user_id: Id, id: Id) -> Result<Vec<SubtreeItem>, SqlxError> {
+        let q = r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, 0 AS depth
+            FROM "Object"
+            WHERE id = $2 AND eliminated IS FALSE AND in_trash IS FALSE
+            UNION ALL
+            SELECT "Object".id, subtree.depth + 1
+            FROM "Object"
+            JOIN subtree ON "Object".parent_id = subtree.id
+            WHERE subtree.depth < $3
+            AND "Object".eliminated IS FALSE AND "Object".in_trash IS FALSE
+        )
+        SELECT
+        o.id, o.parent_id, o.owner_id, o.creator_id, o.name, o.size, o.type AS "type_", o.mimetype, o.created_at, o.updated_at, o.in_trash, o.eliminated, o.upload_s3, o.decode_key, o.hash_sha256, o.blob_id, o.upload_status,
+        "UserXObject".can_read
+        FROM subtree
+        JOIN "Object" o ON o.id = subtree.id
+        LEFT JOIN "UserXObject"
+            ON "UserXObject".object_id = o.id AND "UserXObject".user_id = $1
+        ORDER BY subtree.depth, o.id
+        "#;
+
+        sqlx::query_as::<_, SubtreeItem>(q)
+            .bind(user_id)
+            .bind(id)
+            .bind(MAX_TREE_DEPTH)
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Есть ли в папке другой объект с именем `name`; корень у каждого владельца свой
+    async fn exists_in_folder_tx(
+        &self,
+        conn: &mut PgConnection,
+        owner_id: Id,
+        parent_id: Option<Id>,
+        name: &str,
+        exclude_id: Id,
+    ) -> Result<bool, SqlxError> {
+        let q = r#"
+        SELECT EXISTS (
+            SELECT 1 FROM "Object"
+            WHERE name = $3 AND id != $4 AND eliminated IS FALSE AND in_trash IS FALSE
+            AND (parent_id = $2 OR ($2::uuid IS NULL AND parent_id IS NULL AND owner_id = $1))
+        )
+        "#;
+
+        sqlx::query_scalar::<_, bool>(q)
+            .bind(owner_id)
+            .bind(parent_id)
+            .bind(name)
+            .bind(exclude_id)
+            .fetch_one(conn)
+            .await
+    }
+
+    /// Папка с именем `name` в папке `parent_id` (в корне - среди объектов `owner_id`)
+    async fn select_in_folder(
+        &self,
+        owner_id: Id,
+        parent_id: Option<Id>,
+        name: &str,
+    ) -> Result<Option<Object>, SqlxError> {
+        let q = r#"
+        SELECT
+        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256, blob_id, upload_status
+        FROM "Object"
+        WHERE name = $3 AND type = 'dir' AND eliminated IS FALSE AND in_trash IS FALSE
+        AND (parent_id = $2 OR ($2::uuid IS NULL AND parent_id IS NULL AND owner_id = $1))
+        ORDER BY created_at
+        LIMIT 1
+        "#;
+
+        sqlx::query_as::<_, Object>(q)
+            .bind(owner_id)
+            .bind(parent_id)
+            .bind(name)
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Удаленные в корзину объекты, пролежавшие там дольше срока хранения
+    async fn select_expired_trash(
+        &self,
+        expired_before: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<ExpiredTrashItem>, SqlxError> {
+        let q = r#"
+        WITH RECURSIVE roots AS (
+            SELECT id, owner_id, name, type, trashed_at
+            FROM "Object"
+            WHERE eliminated IS FALSE AND in_trash IS TRUE
+            AND trash_root_id = id AND trashed_at <= $1
+            ORDER BY trashed_at ASC
+            LIMIT $2
+        ), subtree AS (
+            SELECT id AS root_id, id, 0 AS depth FROM roots
+            UNION ALL
+            SELECT subtree.root_id, "Object".id, subtree.depth + 1
+            FROM "Object"
+            JOIN subtree ON "Object".parent_id = subtree.id
+            WHERE subtree.depth < $3 AND "Object".eliminated IS FALSE
+        )
+        SELECT
+            roots.id,
+            roots.owner_id,
+            roots.name,
+            roots.type AS "type_",
+            roots.trashed_at,
+            COUNT(*) AS object_count,
+            COALESCE(SUM("Object".size) FILTER (WHERE "Object".type = 'file'), 0)::BIGINT AS total_size
+        FROM roots
+        JOIN subtree ON subtree.root_id = roots.id
+        JOIN "Object" ON "Object".id = subtree.id
+        GROUP BY roots.id, roots.owner_id, roots.name, roots.type, roots.trashed_at
+        ORDER BY roots.trashed_at ASC
+        "#;
+
+        sqlx::query_as::<_, ExpiredTrashItem>(q)
+            .bind(expired_before)
+            .bind(limit)
+            .bind(MAX_TREE_DEPTH)
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+
     async fn insert_object(
         &self,
         tx: &mut Transaction<'static, Postgres>,
@@ -193,11 +558,11 @@ impl ObjectRepositoryTrait for ObjectRepository {
     ) -> Result<Object, SqlxError> {
         let q = r#"
     INSERT INTO "Object" 
-    (id, parent_id, owner_id, creator_id, name, size, type, mimetype, upload_s3, decode_key, hash_sha256) 
+    (id, parent_id, owner_id, creator_id, name, size, type, mimetype, upload_s3, decode_key, hash_sha256, blob_id, upload_status) 
     VALUES 
-    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) 
+    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) 
     RETURNING 
-    id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256
+    id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256, blob_id, upload_status
     "#;
 
         sqlx::query_as::<_, Object>(q)
@@ -213,55 +578,257 @@ impl ObjectRepositoryTrait for ObjectRepository {
             .bind(create_model.upload_s3)
             .bind(create_model.decode_key)
             .bind(create_model.hash_sha256)
+            .bind(create_model.blob_id)
+            .bind(create_model.upload_status)
             .fetch_one(&mut **tx)
             .await
     }
 
-    async fn mark_as_deleted(&self, id: Id) -> Result<Object, SqlxError> {
+    /// Блокировка до конца транзакции всего, что затрагивает перенос `id` в `parent_id`:
+    /// самого объекта и папок от назначения до корня, при переносе в корень - корня владельца.
+    /// Переносы, которые могут дать цикл или совпадение имен, ждут друг друга,
+    /// остальные идут параллельно
+    async fn lock_move(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        owner_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<(), SqlxError> {
+        if parent_id.is_none() {
+            sqlx::query("SELECT pg_advisory_xact_lock($1, hashtext($2::text))")
+                .bind(OBJECT_ROOT_LOCK_CLASS)
+                .bind(owner_id)
+                .execute(&mut **tx)
+                .await?;
+        }
         let q = r#"
-            UPDATE "Object" SET in_trash = $1, updated_at = $2  
-            WHERE id = $3
-            RETURNING 
-            id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256
-                    "#;
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id, 0 AS depth FROM "Object" WHERE id = $2
+            UNION ALL
+            SELECT "Object".id, "Object".parent_id, ancestors.depth + 1
+            FROM "Object"
+            JOIN ancestors ON "Object".id = ancestors.parent_id
+            WHERE ancestors.depth < $3
+        )
+        SELECT id FROM "Object"
+        WHERE id = $1 OR id IN (SELECT id FROM ancestors)
+        ORDER BY id
+        FOR NO KEY UPDATE
+        "#;
+
+        // Пока блокировка ждала чужой перенос, цепочка папок могла измениться
+        let mut locked: Vec<Id> = Vec::new();
+        loop {
+            let ids = sqlx::query_scalar::<_, Id>(q)
+                .bind(id)
+                .bind(parent_id)
+                .bind(MAX_TREE_DEPTH)
+                .fetch_all(&mut **tx)
+                .await?;
+            if ids == locked {
+                return Ok(());
+            }
+            locked = ids;
+        }
+    }
+
+    /// Блокировка папки `parent_id` до конца транзакции, в корне - корня владельца.
+    /// Выбор свободного имени в папке ждет переносы в нее и другие такие же выборы
+    async fn lock_folder(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        owner_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<(), SqlxError> {
+        match parent_id {
+            Some(parent_id) => {
+                sqlx::query(r#"SELECT id FROM "Object" WHERE id = $1 FOR NO KEY UPDATE"#)
+                    .bind(parent_id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            None => {
+                sqlx::query("SELECT pg_advisory_xact_lock($1, hashtext($2::text))")
+                    .bind(OBJECT_ROOT_LOCK_CLASS)
+                    .bind(owner_id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_info(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        name: &str,
+        parent_id: Option<Id>,
+    ) -> Result<Object, SqlxError> {
+        let q = r#"
+        UPDATE "Object" SET name = $2, parent_id = $3, updated_at = $4
+        WHERE id = $1 AND eliminated IS FALSE
+        RETURNING
+        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256, blob_id, upload_status
+        "#;
 
         sqlx::query_as::<_, Object>(q)
-            .bind(true)
+            .bind(id)
+            .bind(name)
+            .bind(parent_id)
             .bind(Utc::now().naive_utc())
+            .fetch_one(&mut **tx)
+            .await
+    }
+
+    /// Есть ли под объектом `id`, право удаления которого уже проверено, объекты,
+    /// которые пользователь удалять не может. Право каждого объекта - его владельца
+    /// или ближайшей записи `UserXObject` по цепочке папок, как в `AccessService`.
+    /// Без `with_trash` уже лежащие в корзине объекты и их содержимое не проверяются
+    async fn has_undeletable_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        id: Id,
+        with_trash: bool,
+    ) -> Result<bool, SqlxError> {
+        let q = r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, 0 AS depth, TRUE AS can_delete FROM "Object" WHERE id = $2
+            UNION ALL
+            SELECT
+                "Object".id,
+                subtree.depth + 1,
+                "Object".owner_id = $1 OR COALESCE("UserXObject".can_delete, subtree.can_delete)
+            FROM "Object"
+            JOIN subtree ON "Object".parent_id = subtree.id
+            LEFT JOIN "UserXObject"
+                ON "UserXObject".object_id = "Object".id AND "UserXObject".user_id = $1
+            WHERE subtree.depth < $3 AND "Object".eliminated IS FALSE
+            AND ($4 OR "Object".in_trash IS FALSE)
+        )
+        SELECT EXISTS (SELECT 1 FROM subtree WHERE can_delete IS FALSE)
+        "#;
+
+        sqlx::query_scalar::<_, bool>(q)
+            .bind(user_id)
             .bind(id)
-            .fetch_one(self.db_conn.get_pool())
+            .bind(MAX_TREE_DEPTH)
+            .bind(with_trash)
+            .fetch_one(conn)
             .await
     }
 
-    async fn mark_as_restored(&self, id: Id) -> Result<Object, SqlxError> {
+    /// Перемещение в корзину объекта вместе со всем поддеревом.
+    /// Уже удаленные ранее вложенные объекты сохраняют свой `trash_root_id`
+    async fn mark_as_deleted(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Object, SqlxError> {
         let q = r#"
-        UPDATE "Object" SET in_trash = $1, updated_at = $2  
-        WHERE id = $3
-        RETURNING 
-        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256
-      "#;
+        WITH RECURSIVE subtree AS (
+            SELECT id, 0 AS depth FROM "Object" WHERE id = $1
+            UNION ALL
+            SELECT "Object".id, subtree.depth + 1
+            FROM "Object"
+            JOIN subtree ON "Object".parent_id = subtree.id
+            WHERE subtree.depth < $3 AND "Object".eliminated IS FALSE
+        ), updated AS (
+            UPDATE "Object" SET in_trash = TRUE, trash_root_id = $1, trashed_at = $2, updated_at = $2
+            WHERE id IN (SELECT id FROM subtree) AND in_trash IS FALSE
+            RETURNING *
+        )
+        SELECT
+        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256, blob_id, upload_status
+        FROM updated
+        WHERE id = $1
+        "#;
 
         sqlx::query_as::<_, Object>(q)
-            .bind(false)
-            .bind(Utc::now().naive_utc())
             .bind(id)
-            .fetch_one(self.db_conn.get_pool())
+            .bind(Utc::now().naive_utc())
+            .bind(MAX_TREE_DEPTH)
+            .fetch_one(&mut **tx)
             .await
     }
 
-    async fn mark_as_eliminated(&self, id: Id) -> Result<Object, SqlxError> {
+    /// Восстановление объекта и всего, что было удалено вместе с ним.
+    /// Если родитель в корзине или удален окончательно, объект возвращается в корень
+    async fn mark_as_restored(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Object, SqlxError> {
         let q = r#"
-        UPDATE "Object" SET eliminated = $1, updated_at = $2  
-        WHERE id = $3
-        RETURNING 
-        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256
-          "#;
+        WITH RECURSIVE target AS (
+            SELECT id, trash_root_id FROM "Object" WHERE id = $1 AND in_trash IS TRUE
+        ), subtree AS (
+            SELECT id, 0 AS depth FROM target
+            UNION ALL
+            SELECT "Object".id, subtree.depth + 1
+            FROM "Object"
+            JOIN subtree ON "Object".parent_id = subtree.id
+            WHERE subtree.depth < $3
+            AND "Object".trash_root_id = (SELECT trash_root_id FROM target)
+        ), updated AS (
+            UPDATE "Object" SET
+                in_trash = FALSE,
+                trash_root_id = NULL,
+                trashed_at = NULL,
+                updated_at = $2,
+                parent_id = CASE
+                    WHEN "Object".id = $1 AND NOT EXISTS (
+                        SELECT 1 FROM "Object" AS parent
+                        WHERE parent.id = "Object".parent_id
+                        AND parent.in_trash IS FALSE AND parent.eliminated IS FALSE
+                    ) THEN NULL
+                    ELSE "Object".parent_id
+                END
+            WHERE id IN (SELECT id FROM subtree)
+            RETURNING *
+        )
+        SELECT
+        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256, blob_id, upload_status
+        FROM updated
+        WHERE id = $1
+        "#;
 
         sqlx::query_as::<_, Object>(q)
-            .bind(true)
+            .bind(id)
             .bind(Utc::now().naive_utc())
+            .bind(MAX_TREE_DEPTH)
+            .fetch_one(&mut **tx)
+            .await
+    }
+
+    /// Окончательное удаление объекта со всем поддеревом, возвращает все затронутые объекты
+    async fn mark_as_eliminated(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Vec<Object>, SqlxError> {
+        let q = r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, 0 AS depth FROM "Object" WHERE id = $1 AND eliminated IS FALSE
+            UNION ALL
+            SELECT "Object".id, subtree.depth + 1
+            FROM "Object"
+            JOIN subtree ON "Object".parent_id = subtree.id
+            WHERE subtree.depth < $3 AND "Object".eliminated IS FALSE
+        )
+        UPDATE "Object" SET eliminated = TRUE, updated_at = $2
+        WHERE id IN (SELECT id FROM subtree)
+        RETURNING
+        id, parent_id, owner_id, creator_id, name, size, type AS "type_", mimetype, created_at, updated_at, in_trash, eliminated, upload_s3, decode_key, hash_sha256, blob_id, upload_status
+        "#;
+
+        sqlx::query_as::<_, Object>(q)
             .bind(id)
-            .fetch_one(self.db_conn.get_pool())
+            .bind(Utc::now().naive_utc())
+            .bind(MAX_TREE_DEPTH)
+            .fetch_all(&mut **tx)
             .await
     }
 
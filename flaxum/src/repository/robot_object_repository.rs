@@ -4,7 +4,7 @@ use crate::{
     config::database::{Database, DatabaseTrait},
     db::pagination_query_builder,
     dto::object::GetObjectListDto,
-    entity::object::{Object, ObjectCreateModel, ObjectsPaginated},
+    entity::object::{Object, ObjectCreateModel, ObjectsPaginated, UploadStatus},
     entity::pagination::Pagination,
     scalar::Id,
 };
@@ -22,6 +22,12 @@ pub struct RobotObjectRepository {
 pub trait RobotObjectRepositoryTrait {
     fn new(db_conn: &Arc<Database>) -> Self;
 
+    async fn set_upload_status(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        status: UploadStatus,
+    ) -> Result<(), SqlxError>;
 }
 
 impl RobotObjectRepositoryTrait for RobotObjectRepository {
@@ -30,4 +36,20 @@ impl RobotObjectRepositoryTrait for RobotObjectRepository {
             db_conn: Arc::clone(db_conn),
         }
     }
+
+    async fn set_upload_status(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        status: UploadStatus,
+    ) -> Result<(), SqlxError> {
+        let q = r#"UPDATE "RobotObject" SET upload_status = $1, upload_s3 = $2 WHERE id = $3"#;
+        sqlx::query(q)
+            .bind(status)
+            .bind(status == UploadStatus::Stored)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
 }
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::{Database, DatabaseTrait},
+    db::pagination_query_builder,
+    entity::pagination::Pagination,
+    entity::share_link::{ShareLink, ShareLinkCreateModel, ShareLinksPaginated},
+    scalar::Id,
+};
+
+use sqlx::Error as SqlxError;
+use sqlx::{FromRow, QueryBuilder, Row};
+
+#[derive(Clone)]
+pub struct ShareLinkRepository {
+    pub(crate) db_conn: Arc<Database>,
+}
+
+pub trait ShareLinkRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn insert(&self, create_model: ShareLinkCreateModel) -> Result<ShareLink, SqlxError>;
+    async fn select_by_token(&self, token: &str) -> Result<Option<ShareLink>, SqlxError>;
+    async fn select_own_list(
+        &self,
+        pagination: Pagination,
+        owner_id: Id,
+        object_id: Option<Id>,
+    ) -> Result<ShareLinksPaginated, SqlxError>;
+    async fn increment_download_count(&self, id: Id) -> Result<Option<ShareLink>, SqlxError>;
+    async fn delete(&self, id: Id, owner_id: Id) -> Result<Option<ShareLink>, SqlxError>;
+}
+
+impl ShareLinkRepositoryTrait for ShareLinkRepository {
+    fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    async fn insert(&self, create_model: ShareLinkCreateModel) -> Result<ShareLink, SqlxError> {
+        let q = r#"
+        INSERT INTO "ShareLink"
+        (id, object_id, owner_id, token, password_hash, expires_at, max_downloads, read_only)
+        VALUES
+        ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ShareLink>(q)
+            .bind(create_model.id)
+            .bind(create_model.object_id)
+            .bind(create_model.owner_id)
+            .bind(create_model.token)
+            .bind(create_model.password_hash)
+            .bind(create_model.expires_at)
+            .bind(create_model.max_downloads)
+            .bind(create_model.read_only)
+            .fetch_one(self.db_conn.get_pool())
+            .await
+    }
+
+    async fn select_by_token(&self, token: &str) -> Result<Option<ShareLink>, SqlxError> {
+        let q = r#"SELECT * FROM "ShareLink" WHERE token = $1"#;
+
+        sqlx::query_as::<_, ShareLink>(q)
+            .bind(token)
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+
+    async fn select_own_list(
+        &self,
+        pagination: Pagination,
+        owner_id: Id,
+        object_id: Option<Id>,
+    ) -> Result<ShareLinksPaginated, SqlxError> {
+        let mut q = QueryBuilder::new(
+            r#"SELECT *, COUNT(*) OVER() as total_count
+            FROM "ShareLink"
+            WHERE owner_id = "#,
+        );
+        q.push_bind(owner_id);
+        if let Some(object_id) = object_id {
+            q.push(" AND object_id = ");
+            q.push_bind(object_id);
+        }
+        q.push(" ORDER BY created_at desc ");
+
+        let mut q = pagination_query_builder(q, &pagination);
+        let res = q.build().fetch_all(self.db_conn.get_pool()).await?;
+        let mut total = 0;
+        let items = res
+            .iter()
+            .map(|row| {
+                total = row.get::<i64, _>("total_count");
+                ShareLink::from_row(row)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ShareLinksPaginated {
+            items,
+            limit: pagination.limit,
+            offset: pagination.offset,
+            total,
+        })
+    }
+
+    /// Учет скачивания; `None`, если лимит скачиваний уже исчерпан.
+    /// Проверка и увеличение счетчика - один запрос, параллельные скачивания не превысят лимит
+    async fn increment_download_count(&self, id: Id) -> Result<Option<ShareLink>, SqlxError> {
+        let q = r#"
+        UPDATE "ShareLink" SET download_count = download_count + 1
+        WHERE id = $1 AND (max_downloads IS NULL OR download_count < max_downloads)
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, ShareLink>(q)
+            .bind(id)
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+
+    async fn delete(&self, id: Id, owner_id: Id) -> Result<Option<ShareLink>, SqlxError> {
+        let q = r#"DELETE FROM "ShareLink" WHERE id = $1 AND owner_id = $2 RETURNING *"#;
+
+        sqlx::query_as::<_, ShareLink>(q)
+            .bind(id)
+            .bind(owner_id)
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::{Database, DatabaseTrait},
+    entity::storage_gc::StoredContent,
+    scalar::Id,
+};
+use chrono::NaiveDateTime;
+
+use sqlx::Error as SqlxError;
+
+#[derive(Clone)]
+pub struct StorageGcRepository {
+    pub(crate) db_conn: Arc<Database>,
+}
+
+pub trait StorageGcRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn select_tmp_in_use(&self, ids: &[Id]) -> Result<Vec<Id>, SqlxError>;
+    async fn select_referenced(&self, ids: &[Id]) -> Result<Vec<Id>, SqlxError>;
+    async fn select_stored(
+        &self,
+        created_before: NaiveDateTime,
+        after: Option<Id>,
+        limit: i64,
+    ) -> Result<Vec<StoredContent>, SqlxError>;
+}
+
+impl StorageGcRepositoryTrait for StorageGcRepository {
+    fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    /// Id из имен временных файлов, которые еще понадобятся: открытая сессия загрузки,
+    /// ожидающая загрузки запись, событие в dead letter, которое можно повторить,
+    /// или архив выполняющейся распаковки
+    async fn select_tmp_in_use(&self, ids: &[Id]) -> Result<Vec<Id>, SqlxError> {
+        let q = r#"
+        SELECT id FROM "UploadSession" WHERE id = ANY($1)
+        UNION
+        SELECT id FROM "Blob" WHERE id = ANY($1) AND ref_count > 0 AND upload_status = 'pending'
+        UNION
+        SELECT id FROM "RobotObject" WHERE id = ANY($1) AND upload_status = 'pending'
+        UNION
+        SELECT object_id FROM "UploadDeadLetter"
+        WHERE object_id = ANY($1) AND replayed_at IS NULL
+        UNION
+        SELECT id FROM "ObjectJob" WHERE id = ANY($1) AND status = 'running'
+        "#;
+
+        sqlx::query_scalar::<_, Id>(q)
+            .bind(ids)
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Id, на содержимое которых ссылается блоб с живыми ссылками или файл робота
+    async fn select_referenced(&self, ids: &[Id]) -> Result<Vec<Id>, SqlxError> {
+        let q = r#"
+        SELECT id FROM "Blob" WHERE id = ANY($1) AND ref_count > 0
+        UNION
+        SELECT id FROM "RobotObject" WHERE id = ANY($1)
+        "#;
+
+        sqlx::query_scalar::<_, Id>(q)
+            .bind(ids)
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Загруженное содержимое, созданное до `created_before`, по возрастанию id после `after`
+    async fn select_stored(
+        &self,
+        created_before: NaiveDateTime,
+        after: Option<Id>,
+        limit: i64,
+    ) -> Result<Vec<StoredContent>, SqlxError> {
+        let q = r#"
+        SELECT target_type, id, owner_id FROM (
+            SELECT 'blob'::scrubTarget AS target_type, id, owner_id FROM "Blob"
+            WHERE upload_status = 'stored' AND ref_count > 0 AND created_at < $1
+            UNION ALL
+            SELECT 'robot_object'::scrubTarget, id, robot_id FROM "RobotObject"
+            WHERE upload_status = 'stored' AND created_at < $1
+        ) c
+        WHERE $2::uuid IS NULL OR c.id > $2
+        ORDER BY c.id
+        LIMIT $3
+        "#;
+
+        sqlx::query_as::<_, StoredContent>(q)
+            .bind(created_before)
+            .bind(after)
+            .bind(limit)
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+}
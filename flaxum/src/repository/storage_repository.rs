@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use crate::entity::blob::Blob;
+use crate::entity::object::Object;
+use crate::utils::range::ByteRange;
+use file_worker::storage::{
+    StorageBackend, StorageError, StorageStream, StoredObject, StoredUpload,
+};
+
+#[derive(Clone)]
+pub struct StorageRepository {
+    pub(crate) storage: Arc<dyn StorageBackend>,
+}
+
+pub trait StorageRepositoryTrait {
+    fn new(storage: &Arc<dyn StorageBackend>) -> Self;
+
+    async fn get_stream(
+        &self,
+        obj: &Object,
+        range: Option<ByteRange>,
+    ) -> Result<StorageStream, StorageError>;
+    async fn get_key_stream(&self, storage_key: &str) -> Result<StorageStream, StorageError>;
+    async fn delete_blob(&self, blob: &Blob) -> Result<(), StorageError>;
+    async fn delete_blobs(&self, blobs: &[Blob]);
+    async fn list_all(&self) -> Result<Vec<StoredObject>, StorageError>;
+    async fn exists(&self, storage_key: &str) -> Result<bool, StorageError>;
+    async fn delete_key(&self, storage_key: &str) -> Result<(), StorageError>;
+    async fn copy_key(&self, from: &str, to: &str) -> Result<(), StorageError>;
+    async fn list_uploads(&self) -> Result<Vec<StoredUpload>, StorageError>;
+    async fn abort_upload(&self, upload: &StoredUpload) -> Result<(), StorageError>;
+}
+
+impl StorageRepositoryTrait for StorageRepository {
+    fn new(storage: &Arc<dyn StorageBackend>) -> Self {
+        Self {
+            storage: Arc::clone(storage),
+        }
+    }
+
+    /// Зашифрованное тело объекта из хранилища, без загрузки в память.
+    /// При `range` запрашивается только нужный диапазон байт
+    async fn get_stream(
+        &self,
+        obj: &Object,
+        range: Option<ByteRange>,
+    ) -> Result<StorageStream, StorageError> {
+        self.storage
+            .get(&obj.storage_key(), range.map(|range| range.to_inclusive()))
+            .await
+    }
+
+    /// Все тело объекта хранилища по ключу
+    async fn get_key_stream(&self, storage_key: &str) -> Result<StorageStream, StorageError> {
+        self.storage.get(storage_key, None).await
+    }
+
+    async fn delete_blob(&self, blob: &Blob) -> Result<(), StorageError> {
+        self.storage.delete(&blob.storage_key()).await
+    }
+
+    /// Удаление блобов без ссылок; ошибки только логируются,
+    /// оставшееся в хранилище содержимое подбирает сверка хранилища
+    async fn delete_blobs(&self, blobs: &[Blob]) {
+        for blob in blobs {
+            if let Err(err) = self.delete_blob(blob).await {
+                tracing::error!("Failed to delete blob {} from storage: {}", blob.id, err);
+            }
+        }
+    }
+
+    async fn list_all(&self) -> Result<Vec<StoredObject>, StorageError> {
+        self.storage.list("").await
+    }
+
+    async fn exists(&self, storage_key: &str) -> Result<bool, StorageError> {
+        let objects = self.storage.list(storage_key).await?;
+        Ok(objects.iter().any(|object| object.key == storage_key))
+    }
+
+    async fn delete_key(&self, storage_key: &str) -> Result<(), StorageError> {
+        self.storage.delete(storage_key).await
+    }
+
+    async fn copy_key(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        self.storage.copy(from, to).await
+    }
+
+    async fn list_uploads(&self) -> Result<Vec<StoredUpload>, StorageError> {
+        self.storage.list_uploads().await
+    }
+
+    async fn abort_upload(&self, upload: &StoredUpload) -> Result<(), StorageError> {
+        self.storage.abort_upload(upload).await
+    }
+}
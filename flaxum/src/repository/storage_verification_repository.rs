@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::{Database, DatabaseTrait},
+    db::pagination_query_builder,
+    entity::pagination::Pagination,
+    entity::storage_verification::{
+        ScrubCandidate, ScrubReport, StorageVerification, StorageVerificationCreateModel,
+        VerificationStatus,
+    },
+};
+use chrono::NaiveDateTime;
+
+use sqlx::Error as SqlxError;
+use sqlx::{FromRow, QueryBuilder, Row};
+
+#[derive(Clone)]
+pub struct StorageVerificationRepository {
+    pub(crate) db_conn: Arc<Database>,
+}
+
+pub trait StorageVerificationRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn select_candidates(
+        &self,
+        verified_before: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<ScrubCandidate>, SqlxError>;
+    async fn upsert(
+        &self,
+        create_model: StorageVerificationCreateModel,
+    ) -> Result<StorageVerification, SqlxError>;
+    async fn select_list(
+        &self,
+        pagination: Pagination,
+        include_ok: bool,
+    ) -> Result<(Vec<StorageVerification>, i64), SqlxError>;
+    async fn select_summary(&self) -> Result<ScrubReport, SqlxError>;
+}
+
+impl StorageVerificationRepositoryTrait for StorageVerificationRepository {
+    fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    /// Загруженное содержимое, которое еще не проверялось или проверялось до `verified_before`.
+    /// Непроверенное идет первым, затем проверенное давнее всего
+    async fn select_candidates(
+        &self,
+        verified_before: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<ScrubCandidate>, SqlxError> {
+        let q = r#"
+        SELECT target_type, id, owner_id, hash_sha256, size, decode_key, format_version FROM (
+            SELECT 'blob'::scrubTarget AS target_type, b.id, b.owner_id, b.hash_sha256,
+                b.size, b.decode_key, b.format_version, v.verified_at
+            FROM "Blob" b
+            LEFT JOIN "StorageVerification" v ON v.target_type = 'blob' AND v.target_id = b.id
+            WHERE b.upload_status = 'stored' AND b.ref_count > 0 AND b.decode_key IS NOT NULL
+            UNION ALL
+            SELECT 'robot_object'::scrubTarget, r.id, r.robot_id, r.hash_sha256,
+                COALESCE(r.size, 0), r.decode_key, r.format_version, v.verified_at
+            FROM "RobotObject" r
+            LEFT JOIN "StorageVerification" v
+                ON v.target_type = 'robot_object' AND v.target_id = r.id
+            WHERE r.upload_status = 'stored'
+        ) c
+        WHERE c.verified_at IS NULL OR c.verified_at < $1
+        ORDER BY c.verified_at NULLS FIRST
+        LIMIT $2
+        "#;
+
+        sqlx::query_as::<_, ScrubCandidate>(q)
+            .bind(verified_before)
+            .bind(limit)
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Результат проверки заменяет предыдущий
+    async fn upsert(
+        &self,
+        create_model: StorageVerificationCreateModel,
+    ) -> Result<StorageVerification, SqlxError> {
+        let q = r#"
+        INSERT INTO "StorageVerification"
+            (target_type, target_id, storage_key, status, expected_sha256, actual_sha256, error, verified_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+        ON CONFLICT (target_type, target_id) DO UPDATE SET
+            storage_key = EXCLUDED.storage_key,
+            status = EXCLUDED.status,
+            expected_sha256 = EXCLUDED.expected_sha256,
+            actual_sha256 = EXCLUDED.actual_sha256,
+            error = EXCLUDED.error,
+            verified_at = EXCLUDED.verified_at
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, StorageVerification>(q)
+            .bind(create_model.target_type)
+            .bind(create_model.target_id)
+            .bind(create_model.storage_key)
+            .bind(create_model.status)
+            .bind(create_model.expected_sha256)
+            .bind(create_model.actual_sha256)
+            .bind(create_model.error)
+            .fetch_one(self.db_conn.get_pool())
+            .await
+    }
+
+    async fn select_list(
+        &self,
+        pagination: Pagination,
+        include_ok: bool,
+    ) -> Result<(Vec<StorageVerification>, i64), SqlxError> {
+        let mut q = QueryBuilder::new(
+            r#"SELECT *, COUNT(*) OVER() as total_count
+            FROM "StorageVerification""#,
+        );
+        if !include_ok {
+            q.push(" WHERE status != 'ok'");
+        }
+        q.push(" ORDER BY verified_at desc ");
+
+        let mut q = pagination_query_builder(q, &pagination);
+        let res = q.build().fetch_all(self.db_conn.get_pool()).await?;
+        let mut total = 0;
+        let items = res
+            .iter()
+            .map(|row| {
+                total = row.get::<i64, _>("total_count");
+                StorageVerification::from_row(row)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((items, total))
+    }
+
+    async fn select_summary(&self) -> Result<ScrubReport, SqlxError> {
+        let q = r#"SELECT status, COUNT(*) AS count FROM "StorageVerification" GROUP BY status"#;
+
+        let rows = sqlx::query(q).fetch_all(self.db_conn.get_pool()).await?;
+        let mut summary = ScrubReport::default();
+        for row in rows {
+            let count = row.get::<i64, _>("count");
+            summary.checked += count;
+            match row.get::<VerificationStatus, _>("status") {
+                VerificationStatus::Ok => summary.ok = count,
+                VerificationStatus::Mismatch => summary.mismatch = count,
+                VerificationStatus::Missing => summary.missing = count,
+                VerificationStatus::Error => summary.error = count,
+            }
+        }
+        Ok(summary)
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::{Database, DatabaseTrait},
+    db::pagination_query_builder,
+    entity::pagination::Pagination,
+    entity::upload_dead_letter::{UploadDeadLetter, UploadDeadLettersPaginated},
+    scalar::Id,
+};
+use chrono::Utc;
+
+use sqlx::Error as SqlxError;
+use sqlx::{FromRow, Postgres, QueryBuilder, Row, Transaction};
+
+#[derive(Clone)]
+pub struct UploadDeadLetterRepository {
+    pub(crate) db_conn: Arc<Database>,
+}
+
+pub trait UploadDeadLetterRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn select_list(
+        &self,
+        pagination: Pagination,
+        include_replayed: bool,
+    ) -> Result<UploadDeadLettersPaginated, SqlxError>;
+    async fn select_by_id(&self, id: Id) -> Result<Option<UploadDeadLetter>, SqlxError>;
+    async fn mark_replayed(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Option<UploadDeadLetter>, SqlxError>;
+}
+
+impl UploadDeadLetterRepositoryTrait for UploadDeadLetterRepository {
+    fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    async fn select_list(
+        &self,
+        pagination: Pagination,
+        include_replayed: bool,
+    ) -> Result<UploadDeadLettersPaginated, SqlxError> {
+        let mut q = QueryBuilder::new(
+            r#"SELECT *, COUNT(*) OVER() as total_count
+            FROM "UploadDeadLetter""#,
+        );
+        if !include_replayed {
+            q.push(" WHERE replayed_at IS NULL");
+        }
+        q.push(" ORDER BY created_at desc ");
+
+        let mut q = pagination_query_builder(q, &pagination);
+        let res = q.build().fetch_all(self.db_conn.get_pool()).await?;
+        let mut total = 0;
+        let items = res
+            .iter()
+            .map(|row| {
+                total = row.get::<i64, _>("total_count");
+                UploadDeadLetter::from_row(row)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(UploadDeadLettersPaginated {
+            items,
+            limit: pagination.limit,
+            offset: pagination.offset,
+            total,
+        })
+    }
+
+    async fn select_by_id(&self, id: Id) -> Result<Option<UploadDeadLetter>, SqlxError> {
+        let q = r#"SELECT * FROM "UploadDeadLetter" WHERE id = $1"#;
+
+        sqlx::query_as::<_, UploadDeadLetter>(q)
+            .bind(id)
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Отметка о повторной отправке; `None`, если событие уже отправили повторно
+    async fn mark_replayed(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<Option<UploadDeadLetter>, SqlxError> {
+        let q = r#"
+        UPDATE "UploadDeadLetter" SET replayed_at = $1
+        WHERE id = $2 AND replayed_at IS NULL
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, UploadDeadLetter>(q)
+            .bind(Utc::now().naive_utc())
+            .bind(id)
+            .fetch_optional(&mut **tx)
+            .await
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::{Database, DatabaseTrait},
+    entity::upload_outbox::{UploadOutboxCreateModel, UploadOutboxMessage},
+    scalar::Id,
+};
+use chrono::{NaiveDateTime, Utc};
+
+use sqlx::Error as SqlxError;
+use sqlx::{Postgres, Transaction};
+
+#[derive(Clone)]
+pub struct UploadOutboxRepository {
+    pub(crate) db_conn: Arc<Database>,
+}
+
+pub trait UploadOutboxRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn insert(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        create_model: UploadOutboxCreateModel,
+    ) -> Result<UploadOutboxMessage, SqlxError>;
+    async fn select_pending(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        limit: i64,
+    ) -> Result<Vec<UploadOutboxMessage>, SqlxError>;
+    async fn mark_sent(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<(), SqlxError>;
+    async fn record_failure(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        error: &str,
+    ) -> Result<(), SqlxError>;
+    async fn delete_sent_before(&self, sent_before: NaiveDateTime) -> Result<u64, SqlxError>;
+}
+
+impl UploadOutboxRepositoryTrait for UploadOutboxRepository {
+    fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    async fn insert(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        create_model: UploadOutboxCreateModel,
+    ) -> Result<UploadOutboxMessage, SqlxError> {
+        let q = r#"
+        INSERT INTO "UploadOutbox" (id, routing_key, payload)
+        VALUES ($1, $2, $3)
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, UploadOutboxMessage>(q)
+            .bind(create_model.id)
+            .bind(create_model.routing_key)
+            .bind(create_model.payload)
+            .fetch_one(&mut **tx)
+            .await
+    }
+
+    /// Неотправленные события в порядке записи. Строки блокируются до конца транзакции,
+    /// параллельный relay пропускает их
+    async fn select_pending(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        limit: i64,
+    ) -> Result<Vec<UploadOutboxMessage>, SqlxError> {
+        let q = r#"
+        SELECT * FROM "UploadOutbox"
+        WHERE sent_at IS NULL
+        ORDER BY created_at ASC
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+        "#;
+
+        sqlx::query_as::<_, UploadOutboxMessage>(q)
+            .bind(limit)
+            .fetch_all(&mut **tx)
+            .await
+    }
+
+    async fn mark_sent(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+    ) -> Result<(), SqlxError> {
+        let q = r#"
+        UPDATE "UploadOutbox" SET sent_at = $1, attempts = attempts + 1, last_error = NULL
+        WHERE id = $2
+        "#;
+        sqlx::query(q)
+            .bind(Utc::now().naive_utc())
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        error: &str,
+    ) -> Result<(), SqlxError> {
+        let q = r#"
+        UPDATE "UploadOutbox" SET attempts = attempts + 1, last_error = $1
+        WHERE id = $2
+        "#;
+        sqlx::query(q)
+            .bind(error)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_sent_before(&self, sent_before: NaiveDateTime) -> Result<u64, SqlxError> {
+        let q = r#"DELETE FROM "UploadOutbox" WHERE sent_at < $1"#;
+        let res = sqlx::query(q)
+            .bind(sent_before)
+            .execute(self.db_conn.get_pool())
+            .await?;
+        Ok(res.rows_affected())
+    }
+}
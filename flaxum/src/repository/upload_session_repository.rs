@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use crate::{
+    config::database::{Database, DatabaseTrait},
+    entity::upload_session::{UploadSession, UploadSessionCreateModel},
+    scalar::Id,
+};
+use chrono::{NaiveDateTime, Utc};
+
+use sqlx::Error as SqlxError;
+use sqlx::{Postgres, Transaction};
+
+#[derive(Clone)]
+pub struct UploadSessionRepository {
+    pub(crate) db_conn: Arc<Database>,
+}
+
+pub trait UploadSessionRepositoryTrait {
+    fn new(db_conn: &Arc<Database>) -> Self;
+
+    async fn insert(
+        &self,
+        create_model: UploadSessionCreateModel,
+    ) -> Result<UploadSession, SqlxError>;
+    async fn select_by_id(&self, id: Id, user_id: Id) -> Result<Option<UploadSession>, SqlxError>;
+    async fn claim(
+        &self,
+        id: Id,
+        user_id: Id,
+        offset: i64,
+        writer_id: Id,
+        writing_until: NaiveDateTime,
+    ) -> Result<Option<UploadSession>, SqlxError>;
+    async fn renew_lease(
+        &self,
+        id: Id,
+        writer_id: Id,
+        writing_until: NaiveDateTime,
+    ) -> Result<bool, SqlxError>;
+    async fn release_lease(&self, id: Id, writer_id: Id) -> Result<(), SqlxError>;
+    async fn update_offset(
+        &self,
+        id: Id,
+        writer_id: Id,
+        old_offset: i64,
+        new_offset: i64,
+        expires_at: NaiveDateTime,
+    ) -> Result<Option<UploadSession>, SqlxError>;
+    async fn delete_claimed(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        writer_id: Id,
+    ) -> Result<Option<UploadSession>, SqlxError>;
+    async fn delete(&self, id: Id) -> Result<(), SqlxError>;
+    async fn delete_expired(&self) -> Result<Vec<UploadSession>, SqlxError>;
+}
+
+impl UploadSessionRepositoryTrait for UploadSessionRepository {
+    fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    async fn insert(
+        &self,
+        create_model: UploadSessionCreateModel,
+    ) -> Result<UploadSession, SqlxError> {
+        let q = r#"
+        INSERT INTO "UploadSession"
+        (id, user_id, parent_id, name, mimetype, size, expires_at)
+        VALUES
+        ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, UploadSession>(q)
+            .bind(create_model.id)
+            .bind(create_model.user_id)
+            .bind(create_model.parent_id)
+            .bind(create_model.name)
+            .bind(create_model.mimetype)
+            .bind(create_model.size)
+            .bind(create_model.expires_at)
+            .fetch_one(self.db_conn.get_pool())
+            .await
+    }
+
+    async fn select_by_id(&self, id: Id, user_id: Id) -> Result<Option<UploadSession>, SqlxError> {
+        let q = r#"
+        SELECT * FROM "UploadSession"
+        WHERE id = $1 AND user_id = $2 AND expires_at > $3
+        "#;
+
+        sqlx::query_as::<_, UploadSession>(q)
+            .bind(id)
+            .bind(user_id)
+            .bind(Utc::now().naive_utc())
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Право записи в файл сессии с позиции `offset` до `writing_until`.
+    /// `None`, если сессии нет, смещение другое или в нее еще пишет другой запрос
+    async fn claim(
+        &self,
+        id: Id,
+        user_id: Id,
+        offset: i64,
+        writer_id: Id,
+        writing_until: NaiveDateTime,
+    ) -> Result<Option<UploadSession>, SqlxError> {
+        let q = r#"
+        UPDATE "UploadSession" SET
+            writer_id = $4,
+            writing_until = $5,
+            expires_at = GREATEST(expires_at, $5)
+        WHERE id = $1 AND user_id = $2 AND upload_offset = $3 AND expires_at > $6
+        AND (writing_until IS NULL OR writing_until <= $6)
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, UploadSession>(q)
+            .bind(id)
+            .bind(user_id)
+            .bind(offset)
+            .bind(writer_id)
+            .bind(writing_until)
+            .bind(Utc::now().naive_utc())
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Продление права записи; `false`, если его уже забрал другой запрос
+    async fn renew_lease(
+        &self,
+        id: Id,
+        writer_id: Id,
+        writing_until: NaiveDateTime,
+    ) -> Result<bool, SqlxError> {
+        let q = r#"
+        UPDATE "UploadSession" SET
+            writing_until = $3,
+            expires_at = GREATEST(expires_at, $3)
+        WHERE id = $1 AND writer_id = $2
+        "#;
+
+        let res = sqlx::query(q)
+            .bind(id)
+            .bind(writer_id)
+            .bind(writing_until)
+            .execute(self.db_conn.get_pool())
+            .await?;
+        Ok(res.rows_affected() == 1)
+    }
+
+    async fn release_lease(&self, id: Id, writer_id: Id) -> Result<(), SqlxError> {
+        let q = r#"
+        UPDATE "UploadSession" SET writer_id = NULL, writing_until = NULL
+        WHERE id = $1 AND writer_id = $2
+        "#;
+
+        sqlx::query(q)
+            .bind(id)
+            .bind(writer_id)
+            .execute(self.db_conn.get_pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Сдвиг смещения с продлением сессии и снятием права записи;
+    /// `None`, если право записи успел забрать другой запрос
+    async fn update_offset(
+        &self,
+        id: Id,
+        writer_id: Id,
+        old_offset: i64,
+        new_offset: i64,
+        expires_at: NaiveDateTime,
+    ) -> Result<Option<UploadSession>, SqlxError> {
+        let q = r#"
+        UPDATE "UploadSession" SET
+            upload_offset = $1,
+            updated_at = $2,
+            expires_at = $3,
+            writer_id = NULL,
+            writing_until = NULL
+        WHERE id = $4 AND writer_id = $5 AND upload_offset = $6
+        RETURNING *
+        "#;
+
+        sqlx::query_as::<_, UploadSession>(q)
+            .bind(new_offset)
+            .bind(Utc::now().naive_utc())
+            .bind(expires_at)
+            .bind(id)
+            .bind(writer_id)
+            .bind(old_offset)
+            .fetch_optional(self.db_conn.get_pool())
+            .await
+    }
+
+    /// Удаление сессии, право записи в которую держит `writer_id`
+    async fn delete_claimed(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        writer_id: Id,
+    ) -> Result<Option<UploadSession>, SqlxError> {
+        let q = r#"DELETE FROM "UploadSession" WHERE id = $1 AND writer_id = $2 RETURNING *"#;
+        sqlx::query_as::<_, UploadSession>(q)
+            .bind(id)
+            .bind(writer_id)
+            .fetch_optional(&mut **tx)
+            .await
+    }
+
+    async fn delete(&self, id: Id) -> Result<(), SqlxError> {
+        let q = r#"DELETE FROM "UploadSession" WHERE id = $1"#;
+        sqlx::query(q)
+            .bind(id)
+            .execute(self.db_conn.get_pool())
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self) -> Result<Vec<UploadSession>, SqlxError> {
+        let q = r#"DELETE FROM "UploadSession" WHERE expires_at <= $1 RETURNING *"#;
+        sqlx::query_as::<_, UploadSession>(q)
+            .bind(Utc::now().naive_utc())
+            .fetch_all(self.db_conn.get_pool())
+            .await
+    }
+}
@@ -2,11 +2,13 @@ use crate::config::database::{Database, DatabaseTrait};
 use crate::db::pagination_query_builder;
 use crate::dto::user::{CreateUserDto, CreateUserOut, UpdateUserMeDto};
 use crate::entity::pagination::Pagination;
-use crate::entity::user::{AdminUser, AdminUsersPaginated, PublicUser, User, UserRole};
+use crate::entity::user::{
+    AdminUser, AdminUsersPaginated, PublicUser, StorageUsage, User, UserRole,
+};
 use crate::scalar::Id;
 use sqlx::Error as SqlxError;
 use sqlx::Row;
-use sqlx::{self, Postgres, QueryBuilder};
+use sqlx::{self, Postgres, QueryBuilder, Transaction};
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -20,7 +22,7 @@ pub trait UserRepositoryTrait {
     async fn create_user(&self, payload: CreateUserDto) -> Result<CreateUserOut, SqlxError>;
     // async fn delete_user;
 
-    // async fn select_by_id(&self, id: u64) -> Result<User, SqlxError>;
+    async fn select_by_id(&self, id: Id) -> Result<User, SqlxError>;
     async fn select_by_email(&self, email: String) -> Option<User>;
     async fn update_user_me(
         &self,
@@ -28,6 +30,23 @@ pub trait UserRepositoryTrait {
         id: Id,
     ) -> Result<PublicUser, SqlxError>;
     async fn update_password(&self, hash_password: String, id: Id) -> Result<(), SqlxError>;
+    async fn recalculate_storage_size(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        ids: &[Id],
+    ) -> Result<(), SqlxError>;
+    async fn reserve_storage(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        size: i64,
+    ) -> Result<Option<i64>, SqlxError>;
+    async fn update_storage_quota(
+        &self,
+        id: Id,
+        storage_quota: Option<i64>,
+    ) -> Result<AdminUser, SqlxError>;
+    async fn select_storage_usage(&self, id: Id) -> Result<StorageUsage, SqlxError>;
 
     async fn select_user_list(
         &self,
@@ -76,6 +95,14 @@ impl UserRepositoryTrait for UserRepository {
         Ok(user)
     }
 
+    async fn select_by_id(&self, id: Id) -> Result<User, SqlxError> {
+        let q = r#" SELECT * FROM "User" WHERE id = $1"#;
+        sqlx::query_as::<_, User>(q)
+            .bind(id)
+            .fetch_one(self.db_conn.get_pool())
+            .await
+    }
+
     async fn select_by_email(&self, email: String) -> Option<User> {
         let q = r#" SELECT * FROM "User" WHERE email = $1"#;
         sqlx::query_as::<_, User>(q)
@@ -169,4 +196,85 @@ impl UserRepositoryTrait for UserRepository {
             .await?;
         Ok(())
     }
+
+    /// Пересчет занятого места по файлам, которые еще не удалены окончательно
+    async fn recalculate_storage_size(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        ids: &[Id],
+    ) -> Result<(), SqlxError> {
+        let q = r#"
+        UPDATE "User" SET storage_size = COALESCE((
+            SELECT SUM(size) FROM "Object"
+            WHERE owner_id = "User".id AND eliminated IS FALSE AND type = 'file'
+        ), 0)
+        WHERE id = ANY($1)
+        "#;
+        sqlx::query(q).bind(ids).execute(&mut **tx).await?;
+        Ok(())
+    }
+
+    /// Учет нового файла в занятом месте, если он помещается в квоту.
+    /// `None` - квота будет превышена. Файлы роботов не учитываются
+    async fn reserve_storage(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        id: Id,
+        size: i64,
+    ) -> Result<Option<i64>, SqlxError> {
+        let q = r#"
+        UPDATE "User" SET storage_size = COALESCE(storage_size, 0) + $1
+        WHERE id = $2
+        AND (storage_quota IS NULL OR COALESCE(storage_size, 0) + $1 <= storage_quota)
+        RETURNING storage_size
+        "#;
+        sqlx::query_scalar::<_, i64>(q)
+            .bind(size)
+            .bind(id)
+            .fetch_optional(&mut **tx)
+            .await
+    }
+
+    async fn update_storage_quota(
+        &self,
+        id: Id,
+        storage_quota: Option<i64>,
+    ) -> Result<AdminUser, SqlxError> {
+        let q = r#"
+        UPDATE "User" SET storage_quota = $1, updated_at = now()
+        WHERE id = $2
+        RETURNING *
+        "#;
+        let user = sqlx::query_as::<_, User>(q)
+            .bind(storage_quota)
+            .bind(id)
+            .fetch_one(self.db_conn.get_pool())
+            .await?;
+        Ok(AdminUser::from(user))
+    }
+
+    async fn select_storage_usage(&self, id: Id) -> Result<StorageUsage, SqlxError> {
+        let q = r#"
+        SELECT
+            COALESCE("User".storage_size, 0) AS storage_size,
+            "User".storage_quota,
+            COALESCE(SUM("Object".size) FILTER (WHERE "Object".in_trash IS NOT TRUE), 0)::BIGINT AS live_size,
+            COUNT("Object".id) FILTER (WHERE "Object".in_trash IS NOT TRUE) AS live_files,
+            COALESCE(SUM("Object".size) FILTER (WHERE "Object".in_trash IS TRUE), 0)::BIGINT AS trash_size,
+            COUNT("Object".id) FILTER (WHERE "Object".in_trash IS TRUE) AS trash_files,
+            (
+                SELECT COALESCE(SUM("Blob".size), 0) FROM "Blob"
+                WHERE "Blob".owner_id = "User".id AND "Blob".ref_count > 0
+            )::BIGINT AS physical_size
+        FROM "User"
+        LEFT JOIN "Object" ON "Object".owner_id = "User".id
+            AND "Object".eliminated IS NOT TRUE AND "Object".type = 'file'
+        WHERE "User".id = $1
+        GROUP BY "User".id
+        "#;
+        sqlx::query_as::<_, StorageUsage>(q)
+            .bind(id)
+            .fetch_one(self.db_conn.get_pool())
+            .await
+    }
 }
@@ -1,11 +1,14 @@
 use crate::{
-    config::database::{Database, DatabaseTrait},
-    dto::uxo::{DeleteAccessDto, GiveAccessDto},
+    config::{
+        database::{Database, DatabaseTrait},
+        MAX_TREE_DEPTH,
+    },
+    dto::uxo::DeleteAccessDto,
     entity::object::{PublicUserXObject, UserXObject, UxOAccess},
     scalar::Id,
 };
 use sqlx::Error as SqlxError;
-use sqlx::{self, Postgres, Transaction};
+use sqlx::{self, PgConnection, Postgres, Transaction};
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -24,15 +27,35 @@ pub trait UxoRepositoryTrait {
         access: UxOAccess,
     ) -> Result<UserXObject, SqlxError>;
 
+    async fn select_effective_uxo(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+    ) -> Result<Option<UserXObject>, SqlxError>;
+
+    async fn select_uxo_for_update(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+    ) -> Result<Option<UserXObject>, SqlxError>;
+
     async fn select_object_uxo_list(&self, obj_id: Id)
         -> Result<Vec<PublicUserXObject>, SqlxError>;
-    async fn insert_access_by_email(
+    async fn upsert_access(
         &self,
+        tx: &mut Transaction<'static, Postgres>,
+        recipient_id: Id,
         obj_id: Id,
-        access_dto: GiveAccessDto,
+        access: UxOAccess,
     ) -> Result<PublicUserXObject, SqlxError>;
 
-    async fn delete_access_by_user_id(&self, access_dto: DeleteAccessDto) -> Result<(), SqlxError>;
+    async fn delete_access_by_user_id(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        access_dto: DeleteAccessDto,
+    ) -> Result<(), SqlxError>;
 }
 
 impl UxoRepositoryTrait for UxoRepository {
@@ -69,6 +92,71 @@ impl UxoRepositoryTrait for UxoRepository {
         Ok(uxo)
     }
 
+    /// Ближайшая к объекту запись доступа пользователя среди самого объекта и его предков.
+    /// Доступ к папке наследуется всем содержимым; запись на вложенном объекте
+    /// переопределяет унаследованную, запись без прав работает как запрет
+    async fn select_effective_uxo(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+    ) -> Result<Option<UserXObject>, SqlxError> {
+        let q = r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id, 0 AS depth
+            FROM "Object"
+            WHERE id = $2
+            UNION ALL
+            SELECT "Object".id, "Object".parent_id, ancestors.depth + 1
+            FROM "Object"
+            JOIN ancestors ON "Object".id = ancestors.parent_id
+            WHERE ancestors.depth < $3
+        )
+        SELECT
+            "UserXObject".user_id,
+            "UserXObject".object_id,
+            "UserXObject".can_read,
+            "UserXObject".can_edit,
+            "UserXObject".can_delete,
+            "UserXObject".created_at,
+            "UserXObject".updated_at
+        FROM ancestors
+        JOIN "UserXObject" ON "UserXObject".object_id = ancestors.id
+        WHERE "UserXObject".user_id = $1
+        ORDER BY ancestors.depth ASC
+        LIMIT 1
+        "#;
+
+        sqlx::query_as::<_, UserXObject>(q)
+            .bind(user_id)
+            .bind(object_id)
+            .bind(MAX_TREE_DEPTH)
+            .fetch_optional(conn)
+            .await
+    }
+
+    /// Запись доступа пользователя на сам объект, без наследования от папок.
+    /// Строка блокируется до конца транзакции
+    async fn select_uxo_for_update(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+    ) -> Result<Option<UserXObject>, SqlxError> {
+        let q = r#"
+        SELECT user_id, object_id, can_read, can_edit, can_delete, created_at, updated_at
+        FROM "UserXObject"
+        WHERE user_id = $1 AND object_id = $2
+        FOR UPDATE
+        "#;
+
+        sqlx::query_as::<_, UserXObject>(q)
+            .bind(user_id)
+            .bind(object_id)
+            .fetch_optional(conn)
+            .await
+    }
+
     async fn select_object_uxo_list(
         &self,
         obj_id: Id,
@@ -95,17 +183,26 @@ impl UxoRepositoryTrait for UxoRepository {
             .await
     }
 
-    async fn insert_access_by_email(
+    /// Выдача доступа; существующая запись получателя перезаписывается.
+    /// Проверять, может ли выдающий ее менять, должен вызывающий
+    async fn upsert_access(
         &self,
+        tx: &mut Transaction<'static, Postgres>,
+        recipient_id: Id,
         obj_id: Id,
-        access_dto: GiveAccessDto,
+        access: UxOAccess,
     ) -> Result<PublicUserXObject, SqlxError> {
         let q = r#"
         WITH inserted AS (
             INSERT INTO "UserXObject" 
             (user_id, object_id, can_read, can_edit, can_delete) 
             VALUES 
-            ((SELECT id FROM "User" WHERE email = $1), $2, $3, $4, $5) 
+            ($1, $2, $3, $4, $5) 
+            ON CONFLICT (user_id, object_id) DO UPDATE SET
+                can_read = EXCLUDED.can_read,
+                can_edit = EXCLUDED.can_edit,
+                can_delete = EXCLUDED.can_delete,
+                updated_at = now()
             RETURNING user_id, object_id, can_read, can_edit, can_delete, created_at, updated_at
         )
         SELECT 
@@ -123,23 +220,27 @@ impl UxoRepositoryTrait for UxoRepository {
         "#;
 
         sqlx::query_as::<_, PublicUserXObject>(q)
-            .bind(access_dto.recipient_email)
+            .bind(recipient_id)
             .bind(obj_id)
-            .bind(access_dto.can_read)
-            .bind(access_dto.can_edit)
-            .bind(access_dto.can_delete)
-            .fetch_one(self.db_conn.get_pool())
+            .bind(access.can_read)
+            .bind(access.can_edit)
+            .bind(access.can_delete)
+            .fetch_one(&mut **tx)
             .await
     }
 
-    async fn delete_access_by_user_id(&self, access_dto: DeleteAccessDto) -> Result<(), SqlxError> {
+    async fn delete_access_by_user_id(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        access_dto: DeleteAccessDto,
+    ) -> Result<(), SqlxError> {
         let q = r#"
         DELETE FROM "UserXObject" WHERE user_id = $1 AND object_id = $2
         "#;
         sqlx::query(q)
             .bind(access_dto.recipient_id)
             .bind(access_dto.obj_id)
-            .execute(self.db_conn.get_pool())
+            .execute(&mut **tx)
             .await?;
         Ok(())
     }
@@ -0,0 +1,151 @@
+use std::time::SystemTime;
+
+use axum::body::Body;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum_extra::headers::{AcceptRanges, ContentRange, ETag, HeaderMapExt, LastModified};
+
+use crate::entity::object::Object;
+use crate::utils::range::ByteRange;
+
+/// Расшифрованный файл, отдаваемый клиенту потоком
+pub struct FileResponse {
+    status: StatusCode,
+    name: String,
+    mimetype: Option<String>,
+    content_length: Option<u64>,
+    content_range: Option<ContentRange>,
+    etag: Option<ETag>,
+    last_modified: Option<LastModified>,
+    accept_ranges: bool,
+    body: Body,
+}
+
+impl FileResponse {
+    pub fn new(obj: &Object, body: Body) -> Self {
+        FileResponse {
+            status: StatusCode::OK,
+            name: obj.name.clone(),
+            mimetype: obj.mimetype.clone(),
+            content_length: obj.size.map(|size| size as u64),
+            content_range: None,
+            etag: Self::etag(obj),
+            last_modified: Some(Self::last_modified(obj)),
+            accept_ranges: true,
+            body,
+        }
+    }
+
+    /// Архив, собираемый на лету: размер заранее неизвестен, диапазоны не поддерживаются
+    pub fn archive(name: String, body: Body) -> Self {
+        FileResponse {
+            status: StatusCode::OK,
+            name,
+            mimetype: Some("application/zip".to_string()),
+            content_length: None,
+            content_range: None,
+            etag: None,
+            last_modified: None,
+            accept_ranges: false,
+            body,
+        }
+    }
+
+    /// 206 Partial Content для диапазона `range` из `size` байт
+    pub fn partial(obj: &Object, body: Body, range: ByteRange, size: u64) -> Self {
+        let mut res = Self::new(obj, body);
+        res.status = StatusCode::PARTIAL_CONTENT;
+        res.content_length = Some(range.length());
+        res.content_range = ContentRange::bytes(range.start..=range.end, size).ok();
+        res
+    }
+
+    /// 416 Range Not Satisfiable
+    pub fn not_satisfiable(obj: &Object, size: u64) -> Self {
+        let mut res = Self::new(obj, Body::empty());
+        res.status = StatusCode::RANGE_NOT_SATISFIABLE;
+        res.content_length = Some(0);
+        res.content_range = Some(ContentRange::unsatisfied_bytes(size));
+        res
+    }
+
+    /// Ответ отдает файл с первого байта: целиком или диапазоном от начала
+    pub fn starts_at_beginning(&self) -> bool {
+        self.status.is_success()
+            && self
+                .content_range
+                .as_ref()
+                .and_then(ContentRange::bytes_range)
+                .is_none_or(|(start, _)| start == 0)
+    }
+
+    /// Сильный ETag по хешу содержимого
+    pub fn etag(obj: &Object) -> Option<ETag> {
+        obj.hash_sha256
+            .as_ref()
+            .and_then(|hash| format!("\"{}\"", hash.trim()).parse().ok())
+    }
+
+    pub fn last_modified(obj: &Object) -> LastModified {
+        let time = obj.updated_at.unwrap_or(obj.created_at).and_utc();
+        LastModified::from(SystemTime::from(time))
+    }
+}
+
+impl IntoResponse for FileResponse {
+    fn into_response(self) -> Response {
+        let mut res = Response::new(self.body);
+        *res.status_mut() = self.status;
+
+        let headers = res.headers_mut();
+        let mimetype = self
+            .mimetype
+            .as_deref()
+            .and_then(|m| HeaderValue::from_str(m).ok())
+            .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+        headers.insert(header::CONTENT_TYPE, mimetype);
+        if let Some(content_length) = self.content_length {
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(content_length));
+        }
+        if let Ok(disposition) = HeaderValue::from_str(&content_disposition(&self.name)) {
+            headers.insert(header::CONTENT_DISPOSITION, disposition);
+        }
+        if self.accept_ranges {
+            headers.typed_insert(AcceptRanges::bytes());
+        }
+        if let Some(content_range) = self.content_range {
+            headers.typed_insert(content_range);
+        }
+        if let Some(etag) = self.etag {
+            headers.typed_insert(etag);
+        }
+        if let Some(last_modified) = self.last_modified {
+            headers.typed_insert(last_modified);
+        }
+        res
+    }
+}
+
+/// `attachment` c ASCII-именем и RFC 5987 `filename*` для кириллицы и прочего
+fn content_disposition(name: &str) -> String {
+    let ascii_name: String = name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    let mut encoded = String::with_capacity(name.len() * 3);
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii_name, encoded
+    )
+}
@@ -1 +1,2 @@
 pub(crate) mod api_response;
+pub(crate) mod file_response;
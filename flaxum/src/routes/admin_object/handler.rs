@@ -1,11 +1,25 @@
+use crate::dto::object::PurgeTrashDto;
+use crate::dto::storage_gc::RunStorageGcDto;
+use crate::dto::storage_verification::{GetStorageVerificationListDto, RunScrubDto};
+use crate::dto::upload_dead_letter::GetUploadDeadLetterListDto;
+use crate::entity::key_rotation::KeyRotationReport;
 use crate::entity::object::ObjectsPaginated;
 use crate::entity::pagination::Pagination;
+use crate::entity::storage_gc::StorageGcReport;
+use crate::entity::storage_verification::{ScrubReport, StorageVerificationReport};
+use crate::entity::trash::TrashPurgeReport;
+use crate::entity::upload_dead_letter::{UploadDeadLetter, UploadDeadLettersPaginated};
 use crate::entity::user::User;
 use crate::error::api_error::ApiError;
+use crate::scalar::Id;
+use crate::service::scrub_service::SCRUB_BATCH_SIZE;
 use crate::state::object_state::ObjectState;
 use axum::Extension;
-use axum::{extract::State, Json};
-use axum_extra::extract::OptionalQuery;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use axum_extra::extract::{OptionalQuery, Query};
 use validator::Validate;
 
 pub async fn admin_get_object_list(
@@ -22,3 +36,89 @@ pub async fn admin_get_object_list(
         .await?;
     Ok(Json(res))
 }
+
+/// Очистка корзины от объектов старше срока хранения, `dryRun` - без удаления
+pub async fn admin_purge_trash(
+    State(state): State<ObjectState>,
+    Query(q): Query<PurgeTrashDto>,
+    Extension(_): Extension<User>,
+) -> Result<Json<TrashPurgeReport>, ApiError> {
+    let res = state.trash_service.purge_expired(q.dry_run).await?;
+    Ok(Json(res))
+}
+
+/// События загрузки, отложенные file_worker; `includeReplayed` - вместе с уже повторенными
+pub async fn admin_get_upload_dead_letter_list(
+    State(state): State<ObjectState>,
+    OptionalQuery(pagination): OptionalQuery<Pagination>,
+    Query(q): Query<GetUploadDeadLetterListDto>,
+    Extension(_): Extension<User>,
+) -> Result<Json<UploadDeadLettersPaginated>, ApiError> {
+    let pagination = pagination.unwrap_or_default();
+    pagination.validate().unwrap();
+
+    let res = state
+        .upload_dead_letter_service
+        .get_list(pagination, q.include_replayed)
+        .await?;
+    Ok(Json(res))
+}
+
+pub async fn admin_replay_upload_dead_letter(
+    State(state): State<ObjectState>,
+    Path(id): Path<Id>,
+    Extension(_): Extension<User>,
+) -> Result<Json<UploadDeadLetter>, ApiError> {
+    let res = state.upload_dead_letter_service.replay(id).await?;
+    Ok(Json(res))
+}
+
+/// Переобертка всех ключей данных текущим KEK после ротации `MASTER_KEY`
+pub async fn admin_rewrap_keys(
+    State(state): State<ObjectState>,
+    Extension(_): Extension<User>,
+) -> Result<Json<KeyRotationReport>, ApiError> {
+    let res = state.key_rotation_service.rewrap_all().await?;
+    Ok(Json(res))
+}
+
+/// Внеочередной проход скрабера по `limit` единицам содержимого
+pub async fn admin_run_scrub(
+    State(state): State<ObjectState>,
+    Query(q): Query<RunScrubDto>,
+    Extension(_): Extension<User>,
+) -> Result<Json<ScrubReport>, ApiError> {
+    let limit = q
+        .limit
+        .unwrap_or(SCRUB_BATCH_SIZE)
+        .clamp(1, SCRUB_BATCH_SIZE);
+    let res = state.scrub_service.run_batch(limit).await?;
+    Ok(Json(res))
+}
+
+/// Результаты проверок содержимого; по умолчанию только расхождения и ошибки
+pub async fn admin_get_scrub_report(
+    State(state): State<ObjectState>,
+    OptionalQuery(pagination): OptionalQuery<Pagination>,
+    Query(q): Query<GetStorageVerificationListDto>,
+    Extension(_): Extension<User>,
+) -> Result<Json<StorageVerificationReport>, ApiError> {
+    let pagination = pagination.unwrap_or_default();
+    pagination.validate().unwrap();
+
+    let res = state
+        .scrub_service
+        .get_report(pagination, q.include_ok)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Сверка временных файлов и хранилища с БД; `cleanup` - вместе с очисткой найденного
+pub async fn admin_run_storage_gc(
+    State(state): State<ObjectState>,
+    Query(q): Query<RunStorageGcDto>,
+    Extension(_): Extension<User>,
+) -> Result<Json<StorageGcReport>, ApiError> {
+    let res = state.storage_gc_service.run(q.cleanup).await?;
+    Ok(Json(res))
+}
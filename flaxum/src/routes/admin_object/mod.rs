@@ -4,5 +4,22 @@ use crate::state::object_state::ObjectState;
 use axum::{routing::post, Router};
 
 pub fn routes() -> Router<ObjectState> {
-    Router::new().route("/admin/object/list", post(handler::admin_get_object_list))
+    Router::new()
+        .route("/admin/object/list", post(handler::admin_get_object_list))
+        .route(
+            "/admin/object/trash/purge",
+            post(handler::admin_purge_trash),
+        )
+        .route(
+            "/admin/upload/dead-letter/list",
+            post(handler::admin_get_upload_dead_letter_list),
+        )
+        .route(
+            "/admin/upload/dead-letter/{id}/replay",
+            post(handler::admin_replay_upload_dead_letter),
+        )
+        .route("/admin/key/rewrap", post(handler::admin_rewrap_keys))
+        .route("/admin/scrub/run", post(handler::admin_run_scrub))
+        .route("/admin/scrub/report", post(handler::admin_get_scrub_report))
+        .route("/admin/storage/gc", post(handler::admin_run_storage_gc))
 }
@@ -1,8 +1,9 @@
 use crate::dto::user::{
-    AdminChangePasswordDto, AdminCreateUserDto, AdminCreateUserOut, ChangePasswordDto,
+    AdminChangePasswordDto, AdminCreateUserDto, AdminCreateUserOut, AdminSetStorageQuotaDto,
+    ChangePasswordDto,
 };
 use crate::entity::pagination::Pagination;
-use crate::entity::user::{AdminUsersPaginated, User};
+use crate::entity::user::{AdminUser, AdminUsersPaginated, User};
 use crate::error::api_error::ApiError;
 use crate::error::request_error::ValidatedRequest;
 use crate::response::api_response::OkMessage;
@@ -47,3 +48,13 @@ pub async fn admin_get_user_list(
     let res: AdminUsersPaginated = state.user_service.admin_get_user_list(pagination).await?;
     Ok(Json(res))
 }
+
+/// Установка квоты хранилища пользователя
+pub async fn admin_set_storage_quota(
+    State(state): State<UserState>,
+    Extension(_): Extension<User>,
+    ValidatedRequest(payload): ValidatedRequest<AdminSetStorageQuotaDto>,
+) -> Result<Json<AdminUser>, ApiError> {
+    let res = state.user_service.admin_set_storage_quota(payload).await?;
+    Ok(Json(res))
+}
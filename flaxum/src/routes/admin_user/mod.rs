@@ -1,7 +1,10 @@
 mod handler;
 
 use crate::state::user_state::UserState;
-use axum::{routing::post, Router};
+use axum::{
+    routing::{post, put},
+    Router,
+};
 
 pub fn routes() -> Router<UserState> {
     Router::new()
@@ -11,4 +14,5 @@ pub fn routes() -> Router<UserState> {
             post(handler::admin_change_user_password),
         )
         .route("/admin/user/list", post(handler::admin_get_user_list))
+        .route("/admin/user/quota", put(handler::admin_set_storage_quota))
 }
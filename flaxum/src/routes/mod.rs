@@ -4,6 +4,8 @@ mod admin_robot;
 
 mod auth;
 mod object;
+mod share;
+mod upload;
 pub mod root;
 mod user;
 mod uxo;
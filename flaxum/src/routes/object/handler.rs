@@ -1,9 +1,12 @@
 use crate::dto::object::{
-    CreateFolderDto, DeleteObjectDto, DownloadFileDto, GetObjectListDto, UploadFileDto,
+    BulkObjectDto, CopyObjectDto, CreateFolderDto, DeleteObjectDto, DownloadFileDto,
+    DownloadZipDto, GetObjectInfoDto, GetObjectJobDto, GetObjectListDto, UpdateObjectDto,
+    UploadFileDto,
 };
 use crate::entity::pagination::Pagination;
 use crate::error::api_error::ApiError;
-use crate::error::request_error::ValidatedRequest;
+use crate::error::request_error::{RequestError, ValidatedRequest};
+use crate::response::file_response::FileResponse;
 use crate::scalar::Id;
 use crate::state::object_state::ObjectState;
 
@@ -12,12 +15,16 @@ use axum::{
     Extension, Json,
 };
 use axum_extra::extract::{OptionalQuery, Query};
+use axum_extra::headers::{ContentLength, IfRange, Range};
+use axum_extra::TypedHeader;
 
 use validator::Validate;
 
 use crate::entity::object::{
-    DownloadFileUrl, Object, ObjectCreateModel, ObjectType, ObjectsPaginated,
+    BulkObjectReport, Object, ObjectCreateModel, ObjectInfo, ObjectType, ObjectsPaginated,
+    UploadReport,
 };
+use crate::entity::object_job::ObjectJob;
 use crate::entity::user::User;
 
 /// Получение собственных объектов
@@ -89,6 +96,8 @@ pub async fn create_own_folder(
         upload_s3: None,
         decode_key: None,
         hash_sha256: None,
+        blob_id: None,
+        upload_status: None,
     };
 
     let res = state
@@ -102,8 +111,9 @@ pub async fn upload_file(
     State(state): State<ObjectState>,
     Extension(current_user): Extension<User>,
     OptionalQuery(dto_param): OptionalQuery<UploadFileDto>,
+    content_length: Option<TypedHeader<ContentLength>>,
     multipart: Multipart,
-) -> Result<Json<Object>, ApiError> {
+) -> Result<Json<UploadReport>, ApiError> {
     let parent_id = match dto_param {
         Some(x) => x.parent_id,
         None => None,
@@ -111,28 +121,135 @@ pub async fn upload_file(
 
     let res = state
         .object_service
-        .upload_own_file(multipart, parent_id, current_user.id)
+        .upload_own_file(
+            multipart,
+            parent_id,
+            current_user.id,
+            content_length.map(|TypedHeader(ContentLength(length))| length),
+        )
+        .await?;
+    Ok(Json(res))
+}
+
+/// Загрузка ZIP/TAR-архива с распаковкой в папку `parentId`, прогресс - по id операции
+pub async fn upload_archive(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    OptionalQuery(dto_param): OptionalQuery<UploadFileDto>,
+    multipart: Multipart,
+) -> Result<Json<ObjectJob>, ApiError> {
+    let parent_id = dto_param.and_then(|dto| dto.parent_id);
+    let res = state
+        .object_job_service
+        .start_extract(current_user.id, parent_id, multipart)
         .await?;
     Ok(Json(res))
 }
 
+/// Скачивание файла потоком с расшифровкой на лету, с поддержкой `Range`/`If-Range`
 pub async fn download_file(
     State(state): State<ObjectState>,
-    Extension(_): Extension<User>,
+    Extension(current_user): Extension<User>,
     Query(q): Query<DownloadFileDto>,
-) -> Result<Json<DownloadFileUrl>, ApiError> {
-    let res = state.object_service.download_own_file(q.file_id).await?;
-    Ok(Json(res))
+    range: Option<TypedHeader<Range>>,
+    if_range: Option<TypedHeader<IfRange>>,
+) -> Result<FileResponse, ApiError> {
+    let res = state
+        .object_service
+        .download_own_file(
+            current_user.id,
+            q.file_id,
+            range.map(|TypedHeader(range)| range),
+            if_range.map(|TypedHeader(if_range)| if_range),
+        )
+        .await?;
+    Ok(res)
+}
+
+/// Скачивание файлов и папок одним ZIP-архивом, собираемым на лету
+pub async fn download_zip(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Query(q): Query<DownloadZipDto>,
+) -> Result<FileResponse, ApiError> {
+    q.validate().map_err(RequestError::from)?;
+    let res = state
+        .object_service
+        .download_zip(current_user.id, q.object_ids)
+        .await?;
+    Ok(res)
 }
 
 pub async fn delete_object(
     State(state): State<ObjectState>,
-    Extension(_): Extension<User>,
+    Extension(current_user): Extension<User>,
     ValidatedRequest(dto): ValidatedRequest<DeleteObjectDto>,
 ) -> Result<Json<Object>, ApiError> {
-    let res = state.object_service.delete_own_object(dto).await?;
+    let res = state
+        .object_service
+        .delete_own_object(current_user.id, dto)
+        .await?;
     Ok(Json(res))
 }
 
-pub async fn get_info() {}
-pub async fn update_info() {}
+/// Метаданные объекта: путь от корня, владелец и права текущего пользователя
+pub async fn get_info(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Query(q): Query<GetObjectInfoDto>,
+) -> Result<Json<ObjectInfo>, ApiError> {
+    let res = state
+        .object_service
+        .get_info(current_user.id, q.object_id)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Переименование и перенос объекта
+pub async fn update_info(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    ValidatedRequest(dto): ValidatedRequest<UpdateObjectDto>,
+) -> Result<Json<Object>, ApiError> {
+    let res = state
+        .object_service
+        .update_info(current_user.id, dto)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Копирование объекта со всем поддеревом в фоне; прогресс - по `GET /object/job`
+pub async fn copy_object(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    ValidatedRequest(dto): ValidatedRequest<CopyObjectDto>,
+) -> Result<Json<ObjectJob>, ApiError> {
+    let res = state
+        .object_job_service
+        .start_copy(current_user.id, dto)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Состояние фоновой операции над объектами
+pub async fn get_job(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Query(q): Query<GetObjectJobDto>,
+) -> Result<Json<ObjectJob>, ApiError> {
+    let res = state
+        .object_job_service
+        .get_job(current_user.id, q.job_id)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Одно действие над списком объектов с результатом по каждому
+pub async fn bulk_objects(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    ValidatedRequest(dto): ValidatedRequest<BulkObjectDto>,
+) -> Result<Json<BulkObjectReport>, ApiError> {
+    let res = state.object_service.bulk(current_user.id, dto).await?;
+    Ok(Json(res))
+}
@@ -12,15 +12,20 @@ pub fn routes() -> Router<ObjectState> {
     Router::new()
         .route("/folder", post(handler::create_own_folder))
         .route("/upload", post(handler::upload_file))
+        .route("/upload/archive", post(handler::upload_archive))
         .layer(DefaultBodyLimit::disable())
         .layer(RequestBodyLimitLayer::new(config::SIZE_1GB))
         .route("/download", get(handler::download_file))
+        .route("/download/zip", get(handler::download_zip))
         .route(
             "/object",
             get(handler::get_info)
                 .put(handler::update_info)
                 .delete(handler::delete_object),
         )
+        .route("/object/copy", post(handler::copy_object))
+        .route("/object/bulk", post(handler::bulk_objects))
+        .route("/object/job", get(handler::get_job))
         .route("/object/own/list", post(handler::get_own_list))
         .route("/object/trash/list", post(handler::get_trash_list))
         .route("/object/shared/list", post(handler::get_shared_list))
@@ -22,6 +22,8 @@ use super::admin_user;
 use super::admin_robot;
 
 use super::object;
+use super::share;
+use super::upload;
 use super::user;
 use super::uxo;
 
@@ -29,22 +31,26 @@ use super::auth;
 
 pub async fn app(config: Arc<AppConfig>) -> IntoMakeService<Router> {
     let db_conn = Arc::clone(&config.db_conn);
-    let s3_client = Arc::clone(&config.s3_client);
+    let storage = Arc::clone(&config.storage);
     let rmq_conn = Arc::clone(&config.rmq_conn);
 
     let auth_state = AuthState::new(&db_conn);
 
     let user_state = UserState::new(&db_conn);
-    let robot_state = RobotState::new(&db_conn, &s3_client, &rmq_conn);
+    let robot_state = RobotState::new(&db_conn, &storage, &rmq_conn);
 
-    let object_state = ObjectState::new(&db_conn, &s3_client, &rmq_conn);
+    let object_state = ObjectState::new(&db_conn, &storage, &config.keyring, &config.env);
     let token_state = TokenState::new(&db_conn);
 
-    let public_routes = auth::routes().with_state(auth_state);
+    let public_routes = Router::new()
+        .merge(auth::routes().with_state(auth_state))
+        .merge(share::public_routes().with_state(object_state.clone()));
 
     let user_access_routes = Router::new()
         .merge(object::routes().with_state(object_state.clone()))
+        .merge(upload::routes().with_state(object_state.clone()))
         .merge(uxo::routes().with_state(object_state.clone()))
+        .merge(share::routes().with_state(object_state.clone()))
         .merge(user::routes().with_state(user_state.clone()))
         .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
             token_state.clone(),
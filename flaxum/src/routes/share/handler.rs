@@ -0,0 +1,151 @@
+use crate::dto::object::{GetObjectListDto, UploadFileDto};
+use crate::dto::share_link::{CreateShareLinkDto, GetShareLinkListDto, PublicDownloadDto};
+use crate::entity::object::{PublicObject, PublicObjectsPaginated};
+use crate::entity::pagination::Pagination;
+use crate::entity::share_link::{PublicShareLink, ShareLink, ShareLinksPaginated};
+use crate::entity::user::User;
+use crate::error::api_error::ApiError;
+use crate::error::request_error::ValidatedRequest;
+use crate::response::api_response::OkMessage;
+use crate::response::file_response::FileResponse;
+use crate::scalar::Id;
+use crate::state::object_state::ObjectState;
+
+use axum::extract::{Multipart, Path, State};
+use axum::http::{HeaderMap, HeaderName};
+use axum::{Extension, Json};
+use axum_extra::extract::{OptionalQuery, Query};
+use axum_extra::headers::{ContentLength, IfRange, Range};
+use axum_extra::TypedHeader;
+
+use validator::Validate;
+
+/// Пароль ссылки передается заголовком, чтобы не попадать в логи запросов
+pub const SHARE_PASSWORD: HeaderName = HeaderName::from_static("share-password");
+
+fn share_password(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SHARE_PASSWORD)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+/// Создание публичной ссылки на объект
+pub async fn create_share_link(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Path(object_id): Path<Id>,
+    ValidatedRequest(payload): ValidatedRequest<CreateShareLinkDto>,
+) -> Result<Json<ShareLink>, ApiError> {
+    let res = state
+        .share_link_service
+        .create_link(current_user.id, object_id, payload)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Список собственных публичных ссылок
+pub async fn get_share_link_list(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    OptionalQuery(pagination): OptionalQuery<Pagination>,
+    ValidatedRequest(payload): ValidatedRequest<GetShareLinkListDto>,
+) -> Result<Json<ShareLinksPaginated>, ApiError> {
+    let pagination = pagination.unwrap_or_default();
+    pagination.validate().unwrap();
+
+    let res = state
+        .share_link_service
+        .get_own_list(current_user.id, pagination, payload)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Отзыв публичной ссылки
+pub async fn revoke_share_link(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Path(link_id): Path<Id>,
+) -> Result<Json<OkMessage>, ApiError> {
+    state
+        .share_link_service
+        .revoke_link(current_user.id, link_id)
+        .await?;
+    Ok(Json(OkMessage::default()))
+}
+
+/// Информация об объекте по публичной ссылке
+pub async fn get_public_info(
+    State(state): State<ObjectState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<PublicShareLink>, ApiError> {
+    let res = state
+        .share_link_service
+        .get_public_info(&token, share_password(&headers))
+        .await?;
+    Ok(Json(res))
+}
+
+/// Содержимое папки по публичной ссылке
+pub async fn get_public_list(
+    State(state): State<ObjectState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+    OptionalQuery(pagination): OptionalQuery<Pagination>,
+    ValidatedRequest(payload): ValidatedRequest<GetObjectListDto>,
+) -> Result<Json<PublicObjectsPaginated>, ApiError> {
+    let pagination = pagination.unwrap_or_default();
+    pagination.validate().unwrap();
+
+    let res = state
+        .share_link_service
+        .get_public_list(&token, share_password(&headers), pagination, payload)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Скачивание файла по публичной ссылке
+pub async fn download_public_file(
+    State(state): State<ObjectState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+    Query(q): Query<PublicDownloadDto>,
+    range: Option<TypedHeader<Range>>,
+    if_range: Option<TypedHeader<IfRange>>,
+) -> Result<FileResponse, ApiError> {
+    let res = state
+        .share_link_service
+        .download_public_file(
+            &token,
+            share_password(&headers),
+            q.file_id,
+            range.map(|TypedHeader(range)| range),
+            if_range.map(|TypedHeader(if_range)| if_range),
+        )
+        .await?;
+    Ok(res)
+}
+
+/// Загрузка файла в папку по публичной ссылке
+pub async fn upload_public_file(
+    State(state): State<ObjectState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+    OptionalQuery(dto_param): OptionalQuery<UploadFileDto>,
+    content_length: Option<TypedHeader<ContentLength>>,
+    multipart: Multipart,
+) -> Result<Json<PublicObject>, ApiError> {
+    let parent_id = dto_param.and_then(|x| x.parent_id);
+    let res = state
+        .share_link_service
+        .upload_public_file(
+            &token,
+            share_password(&headers),
+            parent_id,
+            multipart,
+            content_length.map(|TypedHeader(ContentLength(length))| length),
+        )
+        .await?;
+    Ok(Json(res))
+}
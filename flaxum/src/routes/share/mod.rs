@@ -0,0 +1,36 @@
+mod handler;
+
+use crate::{config, state::object_state::ObjectState};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post},
+    Router,
+};
+use tower_http::limit::RequestBodyLimitLayer;
+
+pub fn routes() -> Router<ObjectState> {
+    Router::new()
+        .route("/share/{object_id}", post(handler::create_share_link))
+        .route("/share/list", post(handler::get_share_link_list))
+        .route(
+            "/share/revoke/{link_id}",
+            delete(handler::revoke_share_link),
+        )
+}
+
+/// Маршруты без авторизации, доступ по токену ссылки
+pub fn public_routes() -> Router<ObjectState> {
+    Router::new()
+        .route(
+            "/public/share/{token}/upload",
+            post(handler::upload_public_file),
+        )
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(config::SIZE_1GB))
+        .route("/public/share/{token}", get(handler::get_public_info))
+        .route("/public/share/{token}/list", post(handler::get_public_list))
+        .route(
+            "/public/share/{token}/download",
+            get(handler::download_public_file),
+        )
+}
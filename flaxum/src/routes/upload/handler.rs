@@ -0,0 +1,100 @@
+use crate::dto::upload_session::CreateUploadSessionDto;
+use crate::entity::object::Object;
+use crate::entity::upload_session::UploadSession;
+use crate::entity::user::User;
+use crate::error::api_error::ApiError;
+use crate::error::request_error::ValidatedRequest;
+use crate::error::upload_error::UploadError;
+use crate::response::api_response::OkMessage;
+use crate::scalar::Id;
+use crate::state::object_state::ObjectState;
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+use axum::{Extension, Json};
+
+pub const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+pub const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+
+fn upload_headers(session: &UploadSession) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(UPLOAD_OFFSET, HeaderValue::from(session.upload_offset));
+    headers.insert(UPLOAD_LENGTH, HeaderValue::from(session.size));
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers
+}
+
+/// Создание сессии возобновляемой загрузки
+pub async fn create_upload_session(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    ValidatedRequest(payload): ValidatedRequest<CreateUploadSessionDto>,
+) -> Result<(HeaderMap, Json<UploadSession>), ApiError> {
+    let session = state
+        .upload_session_service
+        .create_session(payload, current_user.id)
+        .await?;
+    Ok((upload_headers(&session), Json(session)))
+}
+
+/// Текущее смещение загрузки в заголовке `Upload-Offset`
+pub async fn get_upload_offset(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Path(session_id): Path<Id>,
+) -> Result<HeaderMap, ApiError> {
+    let session = state
+        .upload_session_service
+        .get_session(session_id, current_user.id)
+        .await?;
+    Ok(upload_headers(&session))
+}
+
+/// Дозапись куска: тело запроса пишется с позиции из `Upload-Offset`
+pub async fn patch_upload_chunk(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Path(session_id): Path<Id>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<(HeaderMap, Json<UploadSession>), ApiError> {
+    let offset: i64 = headers
+        .get(UPLOAD_OFFSET)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .filter(|offset| *offset >= 0)
+        .ok_or(UploadError::InvalidOffset)?;
+
+    let session = state
+        .upload_session_service
+        .append_chunk(session_id, current_user.id, offset, body)
+        .await?;
+    Ok((upload_headers(&session), Json(session)))
+}
+
+/// Завершение загрузки и создание объекта
+pub async fn finish_upload_session(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Path(session_id): Path<Id>,
+) -> Result<Json<Object>, ApiError> {
+    let res = state
+        .upload_session_service
+        .finish(session_id, current_user.id)
+        .await?;
+    Ok(Json(res))
+}
+
+/// Отмена загрузки
+pub async fn abort_upload_session(
+    State(state): State<ObjectState>,
+    Extension(current_user): Extension<User>,
+    Path(session_id): Path<Id>,
+) -> Result<Json<OkMessage>, ApiError> {
+    state
+        .upload_session_service
+        .abort(session_id, current_user.id)
+        .await?;
+    Ok(Json(OkMessage::default()))
+}
@@ -0,0 +1,26 @@
+mod handler;
+
+use crate::{config, state::object_state::ObjectState};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{head, post},
+    Router,
+};
+use tower_http::limit::RequestBodyLimitLayer;
+
+pub fn routes() -> Router<ObjectState> {
+    Router::new()
+        .route(
+            "/upload/session/{session_id}",
+            head(handler::get_upload_offset)
+                .patch(handler::patch_upload_chunk)
+                .delete(handler::abort_upload_session),
+        )
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(config::SIZE_1GB))
+        .route("/upload/session", post(handler::create_upload_session))
+        .route(
+            "/upload/session/{session_id}/finish",
+            post(handler::finish_upload_session),
+        )
+}
@@ -1,5 +1,5 @@
 use crate::dto::user::{ChangePasswordDto, UpdateUserMeDto};
-use crate::entity::user::PublicUser;
+use crate::entity::user::{PublicUser, StorageUsage};
 use crate::error::api_error::ApiError;
 use crate::error::request_error::ValidatedRequest;
 use crate::response::api_response::OkMessage;
@@ -16,6 +16,18 @@ pub async fn get_me(
     Ok(Json(public_user))
 }
 
+/// Занятое место с разбивкой на живые файлы и корзину
+pub async fn get_usage(
+    State(state): State<UserState>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<StorageUsage>, ApiError> {
+    let usage = state
+        .user_service
+        .get_storage_usage(current_user.id)
+        .await?;
+    Ok(Json(usage))
+}
+
 pub async fn update_me(
     State(state): State<UserState>,
     Extension(current_user): Extension<User>,
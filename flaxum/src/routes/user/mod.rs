@@ -11,4 +11,5 @@ pub fn routes() -> Router<UserState> {
     Router::new()
         .route("/user/password", put(handler::change_password))
         .route("/user/me", get(handler::get_me).put(handler::update_me))
+        .route("/user/me/usage", get(handler::get_usage))
 }
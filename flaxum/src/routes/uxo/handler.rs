@@ -17,23 +17,26 @@ use axum::Json;
 /// Список доступов к файлу
 pub async fn list_access(
     State(state): State<ObjectState>,
-    Extension(_): Extension<User>,
+    Extension(current_user): Extension<User>,
     Path(object_id): Path<Id>,
 ) -> Result<Json<GetUxoListOut>, ApiError> {
-    let res = state.uxo_service.get_object_uxo_list(object_id).await?;
+    let res = state
+        .uxo_service
+        .get_object_uxo_list(current_user.id, object_id)
+        .await?;
     Ok(Json(res))
 }
 
 /// Дать доступ пользователю
 pub async fn post_give_access(
     State(state): State<ObjectState>,
-    Extension(_): Extension<User>,
+    Extension(current_user): Extension<User>,
     Path(object_id): Path<Id>,
     ValidatedRequest(payload): ValidatedRequest<GiveAccessDto>,
 ) -> Result<Json<PublicUserXObject>, ApiError> {
     let res = state
         .uxo_service
-        .give_access_by_email(object_id, payload)
+        .give_access_by_email(current_user.id, object_id, payload)
         .await?;
     Ok(Json(res))
 }
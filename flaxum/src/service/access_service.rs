@@ -0,0 +1,251 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::config::database::{Database, DatabaseTrait};
+use crate::entity::object::{Object, ObjectType, Permission, UxOAccess};
+use crate::error::api_error::ApiError;
+use crate::error::object_error::ObjectError;
+use crate::repository::object_repository::{ObjectRepository, ObjectRepositoryTrait};
+use crate::repository::uxo_repository::{UxoRepository, UxoRepositoryTrait};
+use crate::scalar::Id;
+use sqlx::PgConnection;
+
+/// Проверка прав пользователя на объект по `UserXObject`.
+/// Владелец объекта имеет все права, остальные получают права
+/// ближайшей записи по цепочке родительских папок.
+/// Методы `_tx` читают через соединение `conn` и внутри транзакции учитывают ее изменения
+#[derive(Clone)]
+pub struct AccessService {
+    db_conn: Arc<Database>,
+    object_repo: ObjectRepository,
+    uxo_repo: UxoRepository,
+}
+
+impl AccessService {
+    pub fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+            object_repo: ObjectRepository::new(db_conn),
+            uxo_repo: UxoRepository::new(db_conn),
+        }
+    }
+
+    /// Итоговые права пользователя на объект; `None`, если доступа нет совсем
+    pub async fn effective_access_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        obj: &Object,
+    ) -> Result<Option<UxOAccess>, ApiError> {
+        if obj.owner_id == user_id {
+            return Ok(Some(UxOAccess::owner()));
+        }
+        let uxo = self
+            .uxo_repo
+            .select_effective_uxo(conn, user_id, obj.id)
+            .await?;
+        Ok(uxo.as_ref().map(UxOAccess::from))
+    }
+
+    /// Объект, если у пользователя есть право `permission`.
+    /// 404 - объекта нет, 403 - нет права
+    pub async fn authorize(
+        &self,
+        user_id: Id,
+        object_id: Id,
+        permission: Permission,
+    ) -> Result<Object, ApiError> {
+        let mut conn = self.db_conn.get_pool().acquire().await?;
+        self.authorize_tx(&mut conn, user_id, object_id, permission)
+            .await
+    }
+
+    pub async fn authorize_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+        permission: Permission,
+    ) -> Result<Object, ApiError> {
+        let (obj, _) = self
+            .authorize_access_tx(conn, user_id, object_id, permission)
+            .await?;
+        Ok(obj)
+    }
+
+    /// То же, что `authorize`, вместе с итоговыми правами пользователя
+    pub async fn authorize_access(
+        &self,
+        user_id: Id,
+        object_id: Id,
+        permission: Permission,
+    ) -> Result<(Object, UxOAccess), ApiError> {
+        let mut conn = self.db_conn.get_pool().acquire().await?;
+        self.authorize_access_tx(&mut conn, user_id, object_id, permission)
+            .await
+    }
+
+    pub async fn authorize_access_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+        permission: Permission,
+    ) -> Result<(Object, UxOAccess), ApiError> {
+        let obj = self
+            .object_repo
+            .select_by_id_tx(conn, object_id)
+            .await
+            .map_err(|err| match err {
+                sqlx::Error::RowNotFound => ApiError::from(ObjectError::ObjectNotFound),
+                err => ApiError::from(err),
+            })?;
+        match self.effective_access_tx(conn, user_id, &obj).await? {
+            Some(access) if access.allows(permission) => Ok((obj, access)),
+            _ => Err(ObjectError::AccessDenied)?,
+        }
+    }
+
+    /// Объект, на который пользователь может выдать доступ `granted` получателю `recipient_id`:
+    /// нужно право редактирования, выдать можно только в пределах собственных прав
+    /// и перезаписать только запись с правами, которые есть у него самого.
+    /// Доступ владельца объекта не меняется. Запись получателя блокируется до конца транзакции
+    pub async fn authorize_grant_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+        recipient_id: Id,
+        granted: &UxOAccess,
+    ) -> Result<Object, ApiError> {
+        let (obj, access) = self
+            .authorize_change_tx(conn, user_id, object_id, recipient_id)
+            .await?;
+        if !access.covers(granted) {
+            return Err(ObjectError::AccessDenied)?;
+        }
+        Ok(obj)
+    }
+
+    /// Объект, с которого пользователь может снять доступ получателя `recipient_id`:
+    /// нужно право редактирования и все права снимаемой записи
+    pub async fn authorize_revoke_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+        recipient_id: Id,
+    ) -> Result<Object, ApiError> {
+        let (obj, _) = self
+            .authorize_change_tx(conn, user_id, object_id, recipient_id)
+            .await?;
+        Ok(obj)
+    }
+
+    async fn authorize_change_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        object_id: Id,
+        recipient_id: Id,
+    ) -> Result<(Object, UxOAccess), ApiError> {
+        let (obj, access) = self
+            .authorize_access_tx(conn, user_id, object_id, Permission::Edit)
+            .await?;
+        if obj.owner_id == recipient_id {
+            return Err(ObjectError::OwnerAccess)?;
+        }
+        let existing = self
+            .uxo_repo
+            .select_uxo_for_update(conn, recipient_id, object_id)
+            .await?;
+        if existing.is_some_and(|uxo| !access.covers(&UxOAccess::from(&uxo))) {
+            return Err(ObjectError::AccessDenied)?;
+        }
+        Ok((obj, access))
+    }
+
+    /// Удаление поддерева `root`, право удаления которого уже проверено: 403, если в нем
+    /// есть объекты, которые пользователь удалять не может (чужие или с запретом).
+    /// `with_trash` - проверять и то, что уже лежит в корзине
+    pub async fn authorize_subtree_delete_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        root: &Object,
+        with_trash: bool,
+    ) -> Result<(), ApiError> {
+        let denied = self
+            .object_repo
+            .has_undeletable_tx(conn, user_id, root.id, with_trash)
+            .await?;
+        match denied {
+            true => Err(ObjectError::AccessDenied)?,
+            false => Ok(()),
+        }
+    }
+
+    /// Поддерево `root`, право чтения на который уже проверено: объекты, доступные
+    /// пользователю на чтение, и недоступные. Вложенное в недоступные папки не возвращается
+    pub async fn readable_subtree(
+        &self,
+        user_id: Id,
+        root: &Object,
+    ) -> Result<(Vec<Object>, Vec<Object>), ApiError> {
+        let subtree = self.object_repo.select_subtree(user_id, root.id).await?;
+        let mut readable_ids = HashSet::new();
+        let (mut readable, mut unreadable) = (Vec::new(), Vec::new());
+        for item in subtree {
+            let obj = item.object;
+            if obj.id == root.id {
+                readable_ids.insert(obj.id);
+                readable.push(obj);
+                continue;
+            }
+            if !obj.parent_id.is_some_and(|id| readable_ids.contains(&id)) {
+                continue;
+            }
+            match obj.owner_id == user_id || item.can_read.unwrap_or(true) {
+                true => {
+                    readable_ids.insert(obj.id);
+                    readable.push(obj);
+                }
+                false => unreadable.push(obj),
+            }
+        }
+        Ok((readable, unreadable))
+    }
+
+    /// Проверка папки назначения при создании объектов внутри нее
+    pub async fn authorize_parent(
+        &self,
+        user_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<Option<Object>, ApiError> {
+        let mut conn = self.db_conn.get_pool().acquire().await?;
+        self.authorize_parent_tx(&mut conn, user_id, parent_id)
+            .await
+    }
+
+    pub async fn authorize_parent_tx(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<Option<Object>, ApiError> {
+        let parent_id = match parent_id {
+            Some(parent_id) => parent_id,
+            None => return Ok(None),
+        };
+        let parent = self
+            .authorize_tx(conn, user_id, parent_id, Permission::Edit)
+            .await?;
+        if parent.in_trash {
+            return Err(ObjectError::ObjectNotFound)?;
+        }
+        match parent.type_ {
+            ObjectType::Dir => Ok(Some(parent)),
+            ObjectType::File => Err(ObjectError::ParentNotAFolder)?,
+        }
+    }
+}
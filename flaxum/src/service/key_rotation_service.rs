@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use crate::config::database::Database;
+use crate::entity::key_rotation::KeyRotationReport;
+use crate::error::api_error::ApiError;
+use crate::repository::key_rotation_repository::{
+    KeyRotationRepository, KeyRotationRepositoryTrait, KeySource,
+};
+
+use file_worker::cipher::{KeyError, Keyring};
+use serde_json::Value;
+
+const REWRAP_BATCH_SIZE: i64 = 500;
+
+/// Переобертка ключей данных текущим KEK после его ротации
+#[derive(Clone)]
+pub struct KeyRotationService {
+    key_rotation_repo: KeyRotationRepository,
+    keyring: Arc<Keyring>,
+}
+
+impl KeyRotationService {
+    pub fn new(db_conn: &Arc<Database>, keyring: &Arc<Keyring>) -> Self {
+        Self {
+            key_rotation_repo: KeyRotationRepository::new(db_conn),
+            keyring: Arc::clone(keyring),
+        }
+    }
+
+    /// Все ключи, обернутые не текущим KEK (прежним или еще открытые),
+    /// переоборачиваются текущим. Ключи, которые не удалось развернуть, считаются в `failed`
+    pub async fn rewrap_all(&self) -> Result<KeyRotationReport, ApiError> {
+        let mut report = KeyRotationReport {
+            kek_id: self.keyring.current_id().to_string(),
+            ..Default::default()
+        };
+        for source in KeySource::ALL {
+            let mut after = None;
+            loop {
+                let rows = self
+                    .key_rotation_repo
+                    .select_batch(source, after, REWRAP_BATCH_SIZE)
+                    .await?;
+                let Some(last) = rows.last() else {
+                    break;
+                };
+                after = Some(last.id);
+
+                for row in rows {
+                    let rewrapped = match source.is_payload() {
+                        true => self.rewrap_payload(&row.value),
+                        false => self.rewrap_key(&row.value),
+                    };
+                    match rewrapped {
+                        Ok(Some(new_value)) => {
+                            if self
+                                .key_rotation_repo
+                                .update_value(source, row.id, &row.value, &new_value)
+                                .await?
+                            {
+                                report.rewrapped += 1;
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            tracing::warn!(
+                                "failed to rewrap key of {:?} {}: {}",
+                                source,
+                                row.id,
+                                err
+                            );
+                            report.failed += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        tracing::info!(
+            "rewrapped {} data keys with KEK {}, {} failed",
+            report.rewrapped,
+            report.kek_id,
+            report.failed
+        );
+        Ok(report)
+    }
+
+    fn rewrap_key(&self, decode_key: &str) -> Result<Option<String>, KeyError> {
+        if self.keyring.is_current(decode_key) {
+            return Ok(None);
+        }
+        self.keyring.rewrap(decode_key).map(Some)
+    }
+
+    /// Тело события загрузки с переобернутым полем `key`
+    fn rewrap_payload(&self, payload: &str) -> Result<Option<String>, KeyError> {
+        let mut event: Value = serde_json::from_str(payload).map_err(|_| KeyError::Corrupted)?;
+        let Some(key) = event.get("key").and_then(Value::as_str) else {
+            return Err(KeyError::Corrupted);
+        };
+        let Some(new_key) = self.rewrap_key(key)? else {
+            return Ok(None);
+        };
+        event["key"] = Value::String(new_key);
+        Ok(Some(event.to_string()))
+    }
+}
@@ -1,6 +1,16 @@
+pub(crate) mod access_service;
 pub(crate) mod object_service;
 pub(crate) mod token_service;
 pub(crate) mod user_service;
 pub(crate) mod uxo_service;
 pub(crate) mod robot_object_service;
-pub(crate) mod robot_service;
\ No newline at end of file
+pub(crate) mod robot_service;
+pub(crate) mod upload_session_service;
+pub(crate) mod share_link_service;
+pub(crate) mod trash_service;
+pub(crate) mod upload_dead_letter_service;
+pub(crate) mod upload_outbox_service;
+pub(crate) mod key_rotation_service;
+pub(crate) mod scrub_service;
+pub(crate) mod storage_gc_service;
+pub(crate) mod object_job_service;
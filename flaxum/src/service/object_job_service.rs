@@ -0,0 +1,917 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::config::database::{Database, DatabaseTrait};
+use crate::config::{ARCHIVE_MAX_ENTRIES, ARCHIVE_MAX_UNPACKED_SIZE, TMP_DIR};
+use crate::dto::object::CopyObjectDto;
+use crate::entity::blob::{Blob, BlobCreateModel};
+use crate::entity::object::{
+    Object, ObjectCreateModel, ObjectType, Permission, UploadStatus, UxOAccess,
+};
+use crate::entity::object_job::{ObjectJob, ObjectJobKind, ObjectJobStatus};
+use crate::error::api_error::ApiError;
+use crate::error::backend_error::BackendError;
+use crate::error::object_error::ObjectError;
+use crate::error::upload_error::UploadError;
+use crate::error::user_error::UserError;
+use crate::repository::blob_repository::{BlobRepository, BlobRepositoryTrait};
+use crate::repository::object_job_repository::{ObjectJobRepository, ObjectJobRepositoryTrait};
+use crate::repository::object_repository::{ObjectRepository, ObjectRepositoryTrait};
+use crate::repository::storage_repository::{StorageRepository, StorageRepositoryTrait};
+use crate::repository::user_repository::{UserRepository, UserRepositoryTrait};
+use crate::repository::uxo_repository::{UxoRepository, UxoRepositoryTrait};
+use crate::scalar::Id;
+use crate::service::access_service::AccessService;
+use crate::service::object_service::ObjectService;
+use crate::utils::archive::{self, ArchiveEntry, ArchiveFormat, EntryKind};
+
+use axum::extract::Multipart;
+use file_worker::cipher::Keyring;
+use file_worker::storage::{StorageBackend, StorageError};
+use sha2::{Digest, Sha256};
+use sqlx::{Postgres, Transaction};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// Сколько объектов копируется в одной транзакции, после каждой пачки обновляется прогресс
+const COPY_BATCH_SIZE: usize = 200;
+/// Сколько имен вида `name (N)` перебирается при совпадении имени в папке назначения
+const COPY_NAME_ATTEMPTS: usize = 100;
+/// Через сколько записей архива обновляется прогресс распаковки
+const EXTRACT_PROGRESS_STEP: i32 = 50;
+
+/// Файл архива, распакованный во временный `tmp/{user_id}.{id}`
+struct ExtractedFile {
+    index: usize,
+    id: Id,
+    size: u64,
+    hash_sha256: String,
+}
+
+/// Долгие операции над деревом объектов: выполняются в фоне, прогресс хранится в `ObjectJob`
+#[derive(Clone)]
+pub struct ObjectJobService {
+    db_conn: Arc<Database>,
+    job_repo: ObjectJobRepository,
+    object_repo: ObjectRepository,
+    uxo_repo: UxoRepository,
+    user_repo: UserRepository,
+    blob_repo: BlobRepository,
+    storage_repo: StorageRepository,
+    access_service: AccessService,
+    object_service: ObjectService,
+}
+
+impl ObjectJobService {
+    pub fn new(
+        db_conn: &Arc<Database>,
+        storage: &Arc<dyn StorageBackend>,
+        keyring: &Arc<Keyring>,
+    ) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+            job_repo: ObjectJobRepository::new(db_conn),
+            object_repo: ObjectRepository::new(db_conn),
+            uxo_repo: UxoRepository::new(db_conn),
+            user_repo: UserRepository::new(db_conn),
+            blob_repo: BlobRepository::new(db_conn),
+            storage_repo: StorageRepository::new(storage),
+            access_service: AccessService::new(db_conn),
+            object_service: ObjectService::new(db_conn, storage, keyring),
+        }
+    }
+
+    /// Операция пользователя; чужие операции не видны
+    pub async fn get_job(&self, user_id: Id, job_id: Id) -> Result<ObjectJob, ApiError> {
+        match self.job_repo.select_by_id(job_id).await? {
+            Some(job) if job.user_id == user_id => Ok(job),
+            _ => Err(ObjectError::JobNotFound)?,
+        }
+    }
+
+    /// Копирование файла или папки со всем поддеревом в папку `parent_id`.
+    /// Права и квота проверяются сразу, само копирование идет в фоне.
+    /// Копии принадлежат пользователю; объекты поддерева, которые ему не видны, пропускаются
+    pub async fn start_copy(&self, user_id: Id, dto: CopyObjectDto) -> Result<ObjectJob, ApiError> {
+        let source = self
+            .access_service
+            .authorize(user_id, dto.object_id, Permission::Read)
+            .await?;
+        if source.in_trash {
+            return Err(ObjectError::ObjectNotFound)?;
+        }
+        if let Some(parent) = self
+            .access_service
+            .authorize_parent(user_id, dto.parent_id)
+            .await?
+        {
+            if self.object_repo.is_in_subtree(parent.id, source.id).await? {
+                return Err(ObjectError::CopyIntoItself)?;
+            }
+        }
+
+        let (items, _) = self
+            .access_service
+            .readable_subtree(user_id, &source)
+            .await?;
+        let size: i64 = items
+            .iter()
+            .filter(|obj| matches!(obj.type_, ObjectType::File))
+            .filter_map(|obj| obj.size)
+            .sum();
+        let user = self.user_repo.select_by_id(user_id).await?;
+        if let Some(quota) = user.storage_quota {
+            if user.storage_size + size > quota {
+                return Err(UserError::StorageQuotaExceeded)?;
+            }
+        }
+        let job = self
+            .job_repo
+            .insert(
+                Id::new_v4(),
+                user_id,
+                ObjectJobKind::Copy,
+                items.len() as i32,
+            )
+            .await?;
+        let service = self.clone();
+        let job_id = job.id;
+        tokio::spawn(async move {
+            let mut root_id = None;
+            let res = service
+                .copy_tree(job_id, user_id, dto.parent_id, items, &mut root_id)
+                .await;
+            let (status, error) = match res {
+                Ok(()) => (ObjectJobStatus::Done, None),
+                Err(err) => {
+                    tracing::error!("Copy job {} failed: {}", job_id, err);
+                    (ObjectJobStatus::Failed, Some(err.to_string()))
+                }
+            };
+            if let Err(err) = service
+                .job_repo
+                .finish(job_id, status, root_id, error)
+                .await
+            {
+                tracing::error!("Failed to finish copy job {}: {}", job_id, err);
+            }
+        });
+        Ok(job)
+    }
+
+    /// Имя в папке назначения: исходное или первое свободное `name (N).ext`.
+    /// Папка блокируется до конца транзакции `tx`, в которой создается объект с этим именем
+    async fn free_name(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        user_id: Id,
+        parent_id: Option<Id>,
+        name: &str,
+        type_: &ObjectType,
+    ) -> Result<String, ApiError> {
+        self.object_repo.lock_folder(tx, user_id, parent_id).await?;
+        if !self
+            .object_repo
+            .exists_in_folder_tx(tx, user_id, parent_id, name, Id::nil())
+            .await?
+        {
+            return Ok(name.to_string());
+        }
+        let (stem, ext) = match (type_, name.rsplit_once('.')) {
+            (ObjectType::File, Some((stem, ext))) if !stem.is_empty() => (stem, format!(".{ext}")),
+            _ => (name, String::new()),
+        };
+        for n in 1..=COPY_NAME_ATTEMPTS {
+            let candidate = format!("{stem} ({n}){ext}");
+            if !self
+                .object_repo
+                .exists_in_folder_tx(tx, user_id, parent_id, &candidate, Id::nil())
+                .await?
+            {
+                return Ok(candidate);
+            }
+        }
+        Err(ObjectError::NameConflict)?
+    }
+
+    /// Копирование пачками, `items` упорядочены от корня. В `root_id` - созданная копия корня,
+    /// ее имя подбирается в транзакции, которая ее создает
+    async fn copy_tree(
+        &self,
+        job_id: Id,
+        user_id: Id,
+        parent_id: Option<Id>,
+        items: Vec<Object>,
+        root_id: &mut Option<Id>,
+    ) -> Result<(), ApiError> {
+        let Some(source_id) = items.first().map(|obj| obj.id) else {
+            return Ok(());
+        };
+        let mut new_ids: HashMap<Id, Id> = HashMap::new();
+        let (mut processed, mut skipped) = (0, 0);
+        for batch in items.chunks(COPY_BATCH_SIZE) {
+            let mut tx = self.db_conn.get_pool().begin().await?;
+            let mut copied = Vec::with_capacity(batch.len());
+            for obj in batch {
+                let (new_parent_id, new_name) = match obj.id == source_id {
+                    true => (
+                        parent_id,
+                        self.free_name(&mut tx, user_id, parent_id, &obj.name, &obj.type_)
+                            .await?,
+                    ),
+                    false => match obj.parent_id.and_then(|id| new_ids.get(&id)) {
+                        Some(new_parent_id) => (Some(*new_parent_id), obj.name.clone()),
+                        None => {
+                            skipped += 1;
+                            continue;
+                        }
+                    },
+                };
+                match self
+                    .copy_object(&mut tx, user_id, obj, new_parent_id, new_name)
+                    .await?
+                {
+                    Some(new_obj) => copied.push((obj.id, new_obj.id)),
+                    None => skipped += 1,
+                }
+            }
+            tx.commit().await?;
+
+            processed += copied.len() as i32;
+            new_ids.extend(copied);
+            if root_id.is_none() {
+                *root_id = new_ids.get(&source_id).copied();
+            }
+            self.job_repo
+                .update_progress(job_id, processed, skipped)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Копия одного объекта; `None` - файл без готового содержимого, копировать нечего
+    async fn copy_object(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        user_id: Id,
+        obj: &Object,
+        parent_id: Option<Id>,
+        name: String,
+    ) -> Result<Option<Object>, ApiError> {
+        let mut create_model = ObjectCreateModel {
+            id: Id::new_v4(),
+            parent_id,
+            owner_id: user_id,
+            creator_id: user_id,
+            name,
+            size: obj.size,
+            type_: obj.type_.clone(),
+            mimetype: obj.mimetype.clone(),
+            upload_s3: None,
+            decode_key: None,
+            hash_sha256: obj.hash_sha256.clone(),
+            blob_id: None,
+            upload_status: None,
+        };
+        if let ObjectType::File = obj.type_ {
+            let Some(blob) = self.copy_blob(tx, user_id, obj, create_model.id).await? else {
+                return Ok(None);
+            };
+            self.user_repo
+                .reserve_storage(tx, user_id, obj.size.unwrap_or(0))
+                .await?
+                .ok_or(UserError::StorageQuotaExceeded)?;
+            create_model.blob_id = Some(blob.id);
+            create_model.decode_key = blob.decode_key;
+            create_model.upload_s3 = Some(blob.upload_status == UploadStatus::Stored);
+            create_model.upload_status = Some(blob.upload_status);
+        }
+
+        let new_obj = self.object_repo.insert_object(tx, create_model).await?;
+        self.uxo_repo
+            .insert_uxo(tx, new_obj.owner_id, new_obj.id, UxOAccess::owner())
+            .await?;
+        Ok(Some(new_obj))
+    }
+
+    /// Содержимое для копии файла. Свой файл ссылается на тот же блоб,
+    /// чужой - на блоб пользователя с тем же хешем или на копию содержимого в хранилище
+    /// под ключом пользователя. Копия хранит тот же обернутый ключ данных
+    async fn copy_blob(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        user_id: Id,
+        obj: &Object,
+        new_id: Id,
+    ) -> Result<Option<Blob>, ApiError> {
+        let Some(blob_id) = obj.blob_id else {
+            return Ok(None);
+        };
+        if obj.upload_status == Some(UploadStatus::Failed) {
+            return Ok(None);
+        }
+        if obj.owner_id == user_id {
+            return match self.blob_repo.acquire(tx, blob_id).await {
+                Ok(blob) => Ok(Some(blob)),
+                Err(sqlx::Error::RowNotFound) => Ok(None),
+                Err(err) => Err(err)?,
+            };
+        }
+
+        let size = obj.size.unwrap_or(0);
+        if let Some(hash_sha256) = &obj.hash_sha256 {
+            if let Some(blob) = self
+                .blob_repo
+                .select_for_dedup(tx, user_id, hash_sha256, size)
+                .await?
+            {
+                return Ok(Some(self.blob_repo.acquire(tx, blob.id).await?));
+            }
+        }
+        if obj.upload_status != Some(UploadStatus::Stored) {
+            return Ok(None);
+        }
+
+        // Содержимое копируется как есть, формат у копии тот же
+        let source = self.blob_repo.select_by_id(tx, blob_id).await?;
+        let create_model = BlobCreateModel {
+            id: new_id,
+            owner_id: user_id,
+            hash_sha256: obj.hash_sha256.clone(),
+            size,
+            decode_key: obj.decode_key.clone(),
+            format_version: source.format_version,
+        };
+        let storage_key = format!("{}/{}", create_model.owner_id, create_model.id);
+        // Ключ в хранилище без строки в БД подберет сборщик мусора, если транзакция не пройдет
+        match self
+            .storage_repo
+            .copy_key(&obj.storage_key(), &storage_key)
+            .await
+        {
+            Ok(()) => {}
+            Err(StorageError::NotFound(key)) => {
+                tracing::warn!("Content of object {} is missing: {}", obj.id, key);
+                return Ok(None);
+            }
+            Err(err) => Err(err)?,
+        }
+        let mut blob = self.blob_repo.insert(tx, create_model).await?;
+        self.blob_repo
+            .set_upload_status(tx, blob.id, UploadStatus::Stored)
+            .await?;
+        blob.upload_status = UploadStatus::Stored;
+        Ok(Some(blob))
+    }
+
+    /// Распаковка архива из поля `file` multipart-запроса в папку `parent_id`: папки создаются
+    /// по путям записей, файлы проходят обычную загрузку с шифрованием. Формат, число записей,
+    /// размер и квота проверяются сразу, распаковка идет в фоне. Записи с путями за пределами
+    /// архива, ссылки и нечитаемые записи пропускаются
+    pub async fn start_extract(
+        &self,
+        user_id: Id,
+        parent_id: Option<Id>,
+        multipart: Multipart,
+    ) -> Result<ObjectJob, ApiError> {
+        self.access_service
+            .authorize_parent(user_id, parent_id)
+            .await?;
+
+        let job_id = Id::new_v4();
+        let archive_path = tmp_path(user_id, job_id);
+        let (format, entries, job) = match self
+            .prepare_extract(job_id, user_id, multipart, &archive_path)
+            .await
+        {
+            Ok(prepared) => prepared,
+            Err(err) => {
+                remove_tmp(&archive_path).await;
+                return Err(err);
+            }
+        };
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut root_id = None;
+            let res = service
+                .extract_tree(
+                    job_id,
+                    user_id,
+                    parent_id,
+                    &archive_path,
+                    format,
+                    entries,
+                    &mut root_id,
+                )
+                .await;
+            remove_tmp(&archive_path).await;
+            let (status, error) = match res {
+                Ok(()) => (ObjectJobStatus::Done, None),
+                Err(err) => {
+                    tracing::error!("Extract job {} failed: {}", job_id, err);
+                    (ObjectJobStatus::Failed, Some(err.to_string()))
+                }
+            };
+            if let Err(err) = service
+                .job_repo
+                .finish(job_id, status, root_id, error)
+                .await
+            {
+                tracing::error!("Failed to finish extract job {}: {}", job_id, err);
+            }
+        });
+        Ok(job)
+    }
+
+    /// Прием архива во временный файл, чтение списка записей, проверка лимитов и квоты
+    async fn prepare_extract(
+        &self,
+        job_id: Id,
+        user_id: Id,
+        mut multipart: Multipart,
+        archive_path: &str,
+    ) -> Result<(ArchiveFormat, Vec<ArchiveEntry>, ObjectJob), ApiError> {
+        let mut received = false;
+        while let Some(mut field) = multipart.next_field().await? {
+            if field.name() != Some("file") {
+                continue;
+            }
+            let mut file = fs::File::create(archive_path).await?;
+            while let Some(chunk) = field.chunk().await? {
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+            received = true;
+            break;
+        }
+        if !received {
+            return Err(UploadError::NoFileField)?;
+        }
+
+        let path = archive_path.to_string();
+        let (format, entries) = tokio::task::spawn_blocking(move || {
+            let invalid = |err: io::Error| {
+                tracing::warn!("Archive {} cannot be read: {}", path, err);
+                UploadError::InvalidArchive
+            };
+            let mut file = std::fs::File::open(&path).map_err(invalid)?;
+            let format = ArchiveFormat::detect(&mut file)
+                .map_err(invalid)?
+                .ok_or(UploadError::InvalidArchive)?;
+            let entries = archive::list(file, format, ARCHIVE_MAX_ENTRIES)
+                .map_err(invalid)?
+                .ok_or(UploadError::ArchiveTooLarge)?;
+            Ok::<_, UploadError>((format, entries))
+        })
+        .await
+        .map_err(|err| BackendError::InternalError(err.to_string()))??;
+
+        let size: u64 = entries
+            .iter()
+            .filter(|entry| entry.kind == EntryKind::File && entry.path.is_some())
+            .map(|entry| entry.size)
+            .sum();
+        if size > ARCHIVE_MAX_UNPACKED_SIZE {
+            return Err(UploadError::ArchiveTooLarge)?;
+        }
+        self.object_service
+            .available_storage(user_id, size as i64)
+            .await?;
+
+        let job = self
+            .job_repo
+            .insert(
+                job_id,
+                user_id,
+                ObjectJobKind::Extract,
+                entries.len() as i32,
+            )
+            .await?;
+        Ok((format, entries, job))
+    }
+
+    /// Сначала создаются все папки архива, затем файлы распаковываются в отдельном потоке
+    /// по одному и регистрируются как обычная загрузка. В `root_id` - созданный объект,
+    /// если в корне архива он один
+    #[allow(clippy::too_many_arguments)]
+    async fn extract_tree(
+        &self,
+        job_id: Id,
+        user_id: Id,
+        parent_id: Option<Id>,
+        archive_path: &str,
+        format: ArchiveFormat,
+        entries: Vec<ArchiveEntry>,
+        root_id: &mut Option<Id>,
+    ) -> Result<(), ApiError> {
+        let (mut processed, mut skipped) = (0, 0);
+        let mut dirs: BTreeSet<Vec<String>> = BTreeSet::new();
+        let mut files: HashMap<usize, Vec<String>> = HashMap::new();
+        for (index, entry) in entries.into_iter().enumerate() {
+            match (entry.path, entry.kind) {
+                (Some(path), EntryKind::Dir) => {
+                    dirs.insert(path);
+                    processed += 1;
+                }
+                (Some(path), EntryKind::File) => {
+                    for n in 1..path.len() {
+                        dirs.insert(path[..n].to_vec());
+                    }
+                    files.insert(index, path);
+                }
+                _ => skipped += 1,
+            }
+        }
+
+        // Имена в созданных папках; в папке назначения имена подбираются через БД
+        let mut folder_ids: HashMap<Vec<String>, Id> = HashMap::new();
+        let mut used_names: HashSet<(Id, String)> = HashSet::new();
+        let mut roots = Vec::new();
+        let dirs: Vec<Vec<String>> = dirs.into_iter().collect();
+        for batch in dirs.chunks(COPY_BATCH_SIZE) {
+            let mut tx = self.db_conn.get_pool().begin().await?;
+            for path in batch {
+                let Some((name, parent_path)) = path.split_last() else {
+                    continue;
+                };
+                let (folder_parent_id, name) = match parent_path.is_empty() {
+                    true => (
+                        parent_id,
+                        self.free_name(&mut tx, user_id, parent_id, name, &ObjectType::Dir)
+                            .await?,
+                    ),
+                    false => (folder_ids.get(parent_path).copied(), name.clone()),
+                };
+                let create_model = ObjectCreateModel {
+                    id: Id::new_v4(),
+                    parent_id: folder_parent_id,
+                    owner_id: user_id,
+                    creator_id: user_id,
+                    name,
+                    type_: ObjectType::Dir,
+                    ..Default::default()
+                };
+                let folder = self
+                    .object_repo
+                    .insert_object(&mut tx, create_model)
+                    .await?;
+                self.uxo_repo
+                    .insert_uxo(&mut tx, user_id, folder.id, UxOAccess::owner())
+                    .await?;
+                match (parent_path.is_empty(), folder_parent_id) {
+                    (false, Some(folder_parent_id)) => {
+                        used_names.insert((folder_parent_id, folder.name));
+                    }
+                    _ => roots.push(folder.id),
+                }
+                folder_ids.insert(path.clone(), folder.id);
+            }
+            tx.commit().await?;
+        }
+        self.job_repo
+            .update_progress(job_id, processed, skipped)
+            .await?;
+
+        let (tx, mut rx) = mpsc::channel(1);
+        let wanted: HashSet<usize> = files.keys().copied().collect();
+        let path = archive_path.to_string();
+        let extractor = tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(&path)?;
+            let mut index = 0;
+            archive::extract(file, format, |entry, data| {
+                let current = index;
+                index += 1;
+                if !wanted.contains(&current) {
+                    return Ok(());
+                }
+                let extracted = match data {
+                    Some(data) => extract_entry(user_id, current, &entry, data),
+                    None => Err(current),
+                };
+                match tx.blocking_send(extracted) {
+                    Ok(()) => Ok(()),
+                    Err(mpsc::error::SendError(extracted)) => {
+                        if let Ok(file) = extracted {
+                            let _ = std::fs::remove_file(tmp_path(user_id, file.id));
+                        }
+                        Err(io::Error::other("extraction cancelled"))
+                    }
+                }
+            })
+        });
+
+        let res = async {
+            while let Some(extracted) = rx.recv().await {
+                match extracted {
+                    Ok(file) => {
+                        let path = files.remove(&file.index).unwrap_or_default();
+                        match self
+                            .store_extracted(
+                                user_id,
+                                parent_id,
+                                &folder_ids,
+                                &mut used_names,
+                                path,
+                                &file,
+                            )
+                            .await
+                        {
+                            Ok(Some(obj)) => {
+                                processed += 1;
+                                if obj.parent_id == parent_id {
+                                    roots.push(obj.id);
+                                }
+                            }
+                            Ok(None) => {
+                                skipped += 1;
+                                remove_tmp(&tmp_path(user_id, file.id)).await;
+                            }
+                            Err(err) => {
+                                remove_tmp(&tmp_path(user_id, file.id)).await;
+                                return Err(err);
+                            }
+                        }
+                    }
+                    Err(_) => skipped += 1,
+                }
+                if (processed + skipped) % EXTRACT_PROGRESS_STEP == 0 {
+                    self.job_repo
+                        .update_progress(job_id, processed, skipped)
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+        .await;
+        // Если регистрация файла не удалась, поток распаковки остановится на следующей записи
+        rx.close();
+        while let Some(extracted) = rx.recv().await {
+            if let Ok(file) = extracted {
+                remove_tmp(&tmp_path(user_id, file.id)).await;
+            }
+        }
+        let extracted = extractor
+            .await
+            .map_err(|err| BackendError::InternalError(err.to_string()))?;
+        res?;
+        if let Err(err) = extracted {
+            tracing::warn!("Archive {} is damaged: {}", archive_path, err);
+            return Err(UploadError::InvalidArchive)?;
+        }
+
+        if let [root] = roots.as_slice() {
+            *root_id = Some(*root);
+        }
+        self.job_repo
+            .update_progress(job_id, processed, skipped)
+            .await?;
+        Ok(())
+    }
+
+    /// Регистрация распакованного файла в его папке. `None` - в папке уже есть
+    /// объект с таким именем из того же архива. Временный файл при ошибке удаляет вызывающий
+    async fn store_extracted(
+        &self,
+        user_id: Id,
+        parent_id: Option<Id>,
+        folder_ids: &HashMap<Vec<String>, Id>,
+        used_names: &mut HashSet<(Id, String)>,
+        path: Vec<String>,
+        file: &ExtractedFile,
+    ) -> Result<Option<Object>, ApiError> {
+        let Some((name, parent_path)) = path.split_last() else {
+            return Ok(None);
+        };
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        let (file_parent_id, name) = match folder_ids.get(parent_path) {
+            Some(folder_id) => {
+                if !used_names.insert((*folder_id, name.clone())) {
+                    return Ok(None);
+                }
+                (Some(*folder_id), name.clone())
+            }
+            _ => (
+                parent_id,
+                self.free_name(&mut tx, user_id, parent_id, name, &ObjectType::File)
+                    .await?,
+            ),
+        };
+        let create_model = ObjectCreateModel {
+            id: file.id,
+            parent_id: file_parent_id,
+            owner_id: user_id,
+            creator_id: user_id,
+            name,
+            size: Some(file.size as i64),
+            type_: ObjectType::File,
+            mimetype: Some("application/octet-stream".to_string()),
+            upload_s3: Some(false),
+            hash_sha256: Some(file.hash_sha256.clone()),
+            ..Default::default()
+        };
+        let (obj, deduplicated) = self
+            .object_service
+            .store_uploaded_file_tx(&mut tx, create_model)
+            .await?;
+        tx.commit().await?;
+        if deduplicated {
+            self.object_service.remove_uploaded_tmp(&obj).await;
+        }
+        Ok(Some(obj))
+    }
+}
+
+/// Содержимое записи архива во временный файл; `Err` с номером записи, если прочитать
+/// ее не удалось или размер не совпал с заголовком
+fn extract_entry(
+    user_id: Id,
+    index: usize,
+    entry: &ArchiveEntry,
+    data: &mut dyn Read,
+) -> Result<ExtractedFile, usize> {
+    let id = Id::new_v4();
+    let path = tmp_path(user_id, id);
+    let res = (|| {
+        let mut file = std::fs::File::create(&path)?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; 64 * 1024];
+        let mut data = data.take(entry.size + 1);
+        let mut size = 0;
+        loop {
+            let n = data.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            file.write_all(&buf[..n])?;
+            size += n as u64;
+        }
+        if size != entry.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "entry size does not match its header",
+            ));
+        }
+        Ok(hex::encode(hasher.finalize()))
+    })();
+    match res {
+        Ok(hash_sha256) => Ok(ExtractedFile {
+            index,
+            id,
+            size: entry.size,
+            hash_sha256,
+        }),
+        Err(err) => {
+            tracing::warn!("Archive entry {} skipped: {}", index, err);
+            let _ = std::fs::remove_file(&path);
+            Err(index)
+        }
+    }
+}
+
+/// Временный файл `tmp/{user_id}.{id}`: архив распаковки или распакованный файл
+fn tmp_path(user_id: Id, id: Id) -> String {
+    format!("{}/{}.{}", TMP_DIR, user_id, id)
+}
+
+/// Удаление временного файла; отсутствие файла не ошибка
+async fn remove_tmp(path: &str) {
+    match fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => tracing::error!("Failed to remove {}: {}", path, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::user::CreateUserDto;
+    use file_worker::storage::LocalStorage;
+    use sqlx::PgPool;
+
+    fn extract(user_id: Id, declared: u64, content: &[u8]) -> Result<ExtractedFile, usize> {
+        std::fs::create_dir_all(TMP_DIR).unwrap();
+        let entry = ArchiveEntry {
+            path: Some(vec!["a.txt".to_string()]),
+            kind: EntryKind::File,
+            size: declared,
+        };
+        extract_entry(user_id, 7, &entry, &mut &content[..])
+    }
+
+    /// Временные файлы пользователя
+    fn tmp_files(user_id: Id) -> usize {
+        std::fs::read_dir(TMP_DIR)
+            .unwrap()
+            .filter(|file| {
+                let name = file.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with(&user_id.to_string())
+            })
+            .count()
+    }
+
+    #[test]
+    fn entry_of_declared_size_is_extracted() {
+        let user_id = Id::new_v4();
+        let file = extract(user_id, 5, b"hello").unwrap();
+        let path = tmp_path(user_id, file.id);
+        assert_eq!(file.index, 7);
+        assert_eq!(file.size, 5);
+        assert_eq!(file.hash_sha256, hex::encode(Sha256::digest(b"hello")));
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn entry_larger_than_declared_is_rejected() {
+        let user_id = Id::new_v4();
+        assert_eq!(extract(user_id, 4, b"hello, world").err(), Some(7));
+        assert_eq!(extract(user_id, 0, b"x").err(), Some(7));
+        assert_eq!(tmp_files(user_id), 0);
+    }
+
+    #[test]
+    fn entry_smaller_than_declared_is_rejected() {
+        let user_id = Id::new_v4();
+        assert_eq!(extract(user_id, 10, b"hello").err(), Some(7));
+        assert_eq!(tmp_files(user_id), 0);
+    }
+
+    async fn service(pool: PgPool) -> ObjectJobService {
+        let db_conn = Arc::new(Database::from_pool(pool));
+        let root = std::env::temp_dir().join(format!("flaxum-test-{}", Id::new_v4()));
+        let storage: Arc<dyn StorageBackend> = Arc::new(LocalStorage::init(root).await.unwrap());
+        let keyring = Arc::new(Keyring::from_hex(&"11".repeat(32), None).unwrap());
+        ObjectJobService::new(&db_conn, &storage, &keyring)
+    }
+
+    /// Ожидание завершения операции
+    async fn wait(service: &ObjectJobService, user_id: Id, job_id: Id) -> ObjectJob {
+        loop {
+            let job = service.get_job(user_id, job_id).await.unwrap();
+            if !matches!(job.status, ObjectJobStatus::Running) {
+                return job;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn concurrent_copies_get_distinct_names(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        service
+            .user_repo
+            .create_user(CreateUserDto {
+                email: "copy@flaxum.test".to_string(),
+                password: "password".to_string(),
+            })
+            .await
+            .unwrap();
+        let user_id = service
+            .user_repo
+            .select_by_email("copy@flaxum.test".to_string())
+            .await
+            .unwrap()
+            .id;
+        let source = service
+            .object_service
+            .create_own_folder(ObjectCreateModel {
+                id: Id::new_v4(),
+                owner_id: user_id,
+                creator_id: user_id,
+                name: "docs".to_string(),
+                size: Some(0),
+                type_: ObjectType::Dir,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let copy = || CopyObjectDto {
+            object_id: source.id,
+            parent_id: None,
+        };
+        let (first, second) = tokio::join!(
+            service.start_copy(user_id, copy()),
+            service.start_copy(user_id, copy()),
+        );
+        let mut names = Vec::new();
+        for job in [first.unwrap(), second.unwrap()] {
+            let job = wait(&service, user_id, job.id).await;
+            assert!(matches!(job.status, ObjectJobStatus::Done));
+            let obj = service
+                .object_repo
+                .select_by_id(job.object_id.unwrap())
+                .await
+                .unwrap();
+            names.push(obj.name);
+        }
+        names.sort();
+        assert_eq!(names, vec!["docs (1)", "docs (2)"]);
+    }
+}
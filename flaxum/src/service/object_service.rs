@@ -1,52 +1,109 @@
 use crate::config::database::{Database, DatabaseTrait};
-use crate::config::rabbitmq::{send_upload_user_event, UploadUserEvent};
-use crate::dto::object::{DeleteObjectDto, GetObjectListDto};
+use crate::config::rabbitmq::{UploadUserEvent, ROUTING_KEY_EVENT_UPLOAD_USER};
+use crate::db;
+use crate::dto::object::{
+    BulkObjectAction, BulkObjectDto, DeleteObjectDto, GetObjectListDto, UpdateObjectDto,
+};
+use crate::entity::blob::{Blob, BlobCreateModel, BlobFormatVersion};
+use crate::entity::upload_outbox::UploadOutboxCreateModel;
 use crate::entity::object::{
-    DownloadFileUrl, Object, ObjectCreateModel, ObjectType, ObjectsPaginated, UxOAccess,
+    BulkObjectReport, BulkObjectResult, Object, ObjectCreateModel, ObjectInfo, ObjectOwner,
+    ObjectType, ObjectsPaginated, Permission, UploadFileResult, UploadReport, UploadStatus,
+    UxOAccess,
 };
 use crate::entity::pagination::Pagination;
 use crate::entity::user::User;
 use crate::error::api_error::ApiError;
+use crate::error::backend_error::BackendError;
+use crate::error::object_error::ObjectError;
+use crate::error::upload_error::UploadError;
+use crate::error::user_error::UserError;
+use crate::repository::blob_repository::{BlobRepository, BlobRepositoryTrait};
 use crate::repository::object_repository::{ObjectRepository, ObjectRepositoryTrait};
-use crate::repository::s3_repository::{S3Repository, S3RepositoryTrait};
+use crate::repository::storage_repository::{StorageRepository, StorageRepositoryTrait};
+use crate::repository::upload_outbox_repository::{
+    UploadOutboxRepository, UploadOutboxRepositoryTrait,
+};
+use crate::repository::user_repository::{UserRepository, UserRepositoryTrait};
 use crate::repository::uxo_repository::{UxoRepository, UxoRepositoryTrait};
+use crate::response::file_response::FileResponse;
+use crate::service::access_service::AccessService;
 use crate::scalar::Id;
-use aes::cipher::KeyIvInit;
-use aws_sdk_s3::Client as S3Client;
+use crate::utils::archive;
+use crate::utils::blob_stream::{self, BlobDecryptor, SegmentReader};
+use crate::utils::range::{ByteRange, RequestedRange};
+use crate::utils::zip_stream::{self, ArchivePaths, ZipWriter};
+use axum::body::Body;
+use axum::extract::multipart::Field;
 use axum::extract::Multipart;
+use axum::response::IntoResponse;
+use axum_extra::headers::{IfRange, Range};
+use bytes::Bytes;
+use file_worker::cipher::{self, BlobCipher, CipherError, Keyring};
+use file_worker::storage::{StorageBackend, StorageStream};
+use futures::stream::{self, Stream, StreamExt};
 use tokio::fs;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
-use std::io::{Seek, SeekFrom, Write};
+use tokio::sync::mpsc;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Seek, SeekFrom, Write};
 use std::sync::Arc;
-use tokio::time::Instant;
 
-use aes::Aes256;
-use ctr::cipher::StreamCipher;
-use ctr::Ctr128BE;
+use ctr::cipher::StreamCipherSeek;
 use sha2::{digest::Digest, Sha256};
+use sqlx::{Postgres, Transaction};
+
+/// Точка отката каждого объекта в массовой операции
+const BULK_SAVEPOINT: &str = "bulk_item";
+/// Сколько кусков архива может ждать отправки клиенту
+const ZIP_CHANNEL_CAPACITY: usize = 16;
+/// Запись архива со списком пропущенных объектов
+const ZIP_SKIPPED_NAME: &str = "skipped.txt";
 
-type Aes256Ctr = Ctr128BE<Aes256>;
+/// Объект архива и его путь внутри архива
+struct ZipMember {
+    path: String,
+    obj: Object,
+}
+
+/// Объект, не попавший в архив, и причина
+struct ZipSkip {
+    path: String,
+    reason: &'static str,
+}
 
-use amqprs::connection::Connection as RMQConn;
+type ZipSender = mpsc::Sender<io::Result<Bytes>>;
 
 #[derive(Clone)]
 pub struct ObjectService {
     db_conn: Arc<Database>,
-    rmq_conn: Arc<RMQConn>,
     object_repo: ObjectRepository,
     uxo_repo: UxoRepository,
-    s3_repo: S3Repository,
+    user_repo: UserRepository,
+    blob_repo: BlobRepository,
+    outbox_repo: UploadOutboxRepository,
+    storage_repo: StorageRepository,
+    access_service: AccessService,
+    keyring: Arc<Keyring>,
 }
 
 // todo: add trait
 impl ObjectService {
-    pub fn new(db_conn: &Arc<Database>, s3_conn: &Arc<S3Client>, rmq_conn: &Arc<RMQConn>) -> Self {
+    pub fn new(
+        db_conn: &Arc<Database>,
+        storage: &Arc<dyn StorageBackend>,
+        keyring: &Arc<Keyring>,
+    ) -> Self {
         Self {
             db_conn: Arc::clone(db_conn),
-            rmq_conn: Arc::clone(rmq_conn),
             object_repo: ObjectRepository::new(db_conn),
             uxo_repo: UxoRepository::new(db_conn),
-            s3_repo: S3Repository::new(s3_conn),
+            user_repo: UserRepository::new(db_conn),
+            blob_repo: BlobRepository::new(db_conn),
+            outbox_repo: UploadOutboxRepository::new(db_conn),
+            storage_repo: StorageRepository::new(storage),
+            access_service: AccessService::new(db_conn),
+            keyring: Arc::clone(keyring),
         }
     }
 
@@ -56,7 +113,11 @@ impl ObjectService {
         current_user: User,
         body: GetObjectListDto,
     ) -> Result<ObjectsPaginated, ApiError> {
-        // todo: add check cur user have access to body.parent if Some(body.parent)
+        if let Some(parent_id) = body.parent_id {
+            self.access_service
+                .authorize(current_user.id, parent_id, Permission::Read)
+                .await?;
+        }
         let objects_paginated = self
             .object_repo
             .select_own_list(pagination, body, current_user.id)
@@ -69,7 +130,11 @@ impl ObjectService {
         current_user: User,
         body: GetObjectListDto,
     ) -> Result<ObjectsPaginated, ApiError> {
-        // todo: add check cur user have access to body.parent if Some(body.parent)
+        if let Some(parent_id) = body.parent_id {
+            self.access_service
+                .authorize(current_user.id, parent_id, Permission::Read)
+                .await?;
+        }
         let objects_paginated = self
             .object_repo
             .select_shared_list(pagination, body, current_user.id)
@@ -94,6 +159,9 @@ impl ObjectService {
         &self,
         obj_constructor: ObjectCreateModel,
     ) -> Result<Object, ApiError> {
+        self.access_service
+            .authorize_parent(obj_constructor.creator_id, obj_constructor.parent_id)
+            .await?;
         let mut tx = self.db_conn.get_pool().begin().await?;
         let new_obj: Object = self
             .object_repo
@@ -107,12 +175,191 @@ impl ObjectService {
         Ok(new_obj)
     }
 
+    /// Загрузка файлов из полей `file` multipart-запроса в папку `object_parent`.
+    /// Путь файла относительно папки (`docs/2024/report.pdf`) берется из поля `path` перед ним
+    /// или из имени файла, недостающие папки создаются. Ошибка одного файла не прерывает
+    /// загрузку остальных, в ответе результат по каждому файлу
     pub async fn upload_own_file(
         &self,
         mut multipart: Multipart,
         object_parent: Option<Id>,
         user_id: Id,
+        content_length: Option<u64>,
+    ) -> Result<UploadReport, ApiError> {
+        self.access_service
+            .authorize_parent(user_id, object_parent)
+            .await?;
+        let mut available = self
+            .available_storage(user_id, content_length.unwrap_or(0) as i64)
+            .await?;
+
+        let mut folders: HashMap<(Option<Id>, String), Id> = HashMap::new();
+        let mut path = None;
+        let mut items = Vec::new();
+        loop {
+            let field = match multipart.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => break,
+                Err(err) if items.is_empty() => return Err(err)?,
+                Err(err) => {
+                    tracing::warn!("Multipart upload of user {} interrupted: {}", user_id, err);
+                    break;
+                }
+            };
+            match field.name() {
+                Some("path") => path = Some(field.text().await?),
+                Some("file") => {
+                    let path = path
+                        .take()
+                        .or_else(|| field.file_name().map(str::to_string))
+                        .unwrap_or_default();
+                    let res = self
+                        .receive_path(
+                            field,
+                            &path,
+                            object_parent,
+                            user_id,
+                            available,
+                            &mut folders,
+                        )
+                        .await;
+                    let item = match res {
+                        Ok(obj) => {
+                            if let Some(available) = available.as_mut() {
+                                *available -= obj.size.unwrap_or(0);
+                            }
+                            UploadFileResult {
+                                path,
+                                ok: true,
+                                object: Some(obj),
+                                status: None,
+                                error: None,
+                            }
+                        }
+                        Err(err) => {
+                            let error = err.to_string();
+                            UploadFileResult {
+                                path,
+                                ok: false,
+                                object: None,
+                                status: Some(err.into_response().status().as_u16()),
+                                error: Some(error),
+                            }
+                        }
+                    };
+                    items.push(item);
+                }
+                _ => {}
+            }
+        }
+        if items.is_empty() {
+            return Err(UploadError::NoFileField)?;
+        }
+
+        let failed = items.iter().filter(|item| !item.ok).count();
+        Ok(UploadReport {
+            succeeded: items.len() - failed,
+            failed,
+            items,
+        })
+    }
+
+    /// Прием файла по относительному пути `path` от папки `object_parent`
+    async fn receive_path(
+        &self,
+        field: Field<'_>,
+        path: &str,
+        object_parent: Option<Id>,
+        user_id: Id,
+        available: Option<i64>,
+        folders: &mut HashMap<(Option<Id>, String), Id>,
+    ) -> Result<Object, ApiError> {
+        let parts = archive::entry_path(path).ok_or(UploadError::InvalidPath)?;
+        let Some((name, dirs)) = parts.split_last() else {
+            return Err(UploadError::InvalidPath)?;
+        };
+        let mut parent_id = object_parent;
+        for dir in dirs {
+            parent_id = Some(self.ensure_folder(user_id, parent_id, dir, folders).await?);
+        }
+        self.receive_field(field, name.clone(), parent_id, user_id, available)
+            .await
+    }
+
+    /// Папка `name` внутри `parent_id`: существующая, если пользователь может в нее
+    /// загружать, или новая. Найденные и созданные папки запоминаются в `folders`
+    async fn ensure_folder(
+        &self,
+        user_id: Id,
+        parent_id: Option<Id>,
+        name: &str,
+        folders: &mut HashMap<(Option<Id>, String), Id>,
+    ) -> Result<Id, ApiError> {
+        let key = (parent_id, name.to_string());
+        if let Some(folder_id) = folders.get(&key) {
+            return Ok(*folder_id);
+        }
+        let folder_id = match self
+            .object_repo
+            .select_in_folder(user_id, parent_id, name)
+            .await?
+        {
+            Some(existing) => {
+                self.access_service
+                    .authorize_parent(user_id, Some(existing.id))
+                    .await?;
+                existing.id
+            }
+            None => {
+                let folder = self
+                    .create_own_folder(ObjectCreateModel {
+                        id: Id::new_v4(),
+                        parent_id,
+                        owner_id: user_id,
+                        creator_id: user_id,
+                        name: name.to_string(),
+                        size: Some(0),
+                        type_: ObjectType::Dir,
+                        ..Default::default()
+                    })
+                    .await?;
+                folder.id
+            }
+        };
+        folders.insert(key, folder_id);
+        Ok(folder_id)
+    }
+
+    /// Свободное место пользователя с учетом `incoming` байт; `None` - квоты нет
+    pub async fn available_storage(
+        &self,
+        user_id: Id,
+        incoming: i64,
+    ) -> Result<Option<i64>, ApiError> {
+        let user = self.user_repo.select_by_id(user_id).await?;
+        let available = match user.storage_quota {
+            Some(quota) => quota - user.storage_size,
+            None => return Ok(None),
+        };
+        if incoming > available {
+            return Err(UserError::StorageQuotaExceeded)?;
+        }
+        Ok(Some(available))
+    }
+
+    /// Прием файла из поля `file` multipart-запроса в папку `object_parent` пользователя `user_id`,
+    /// права на папку должны быть проверены вызывающим.
+    /// Квота проверяется заранее по `Content-Length` и по мере чтения потока
+    pub async fn receive_file(
+        &self,
+        mut multipart: Multipart,
+        object_parent: Option<Id>,
+        user_id: Id,
+        content_length: Option<u64>,
     ) -> Result<Object, ApiError> {
+        let available = self
+            .available_storage(user_id, content_length.unwrap_or(0) as i64)
+            .await?;
         while let Some(multipart_field) = multipart.next_field().await.map_err(|e| {
             ApiError::BackendError(crate::error::backend_error::BackendError::InternalError(
                 format!("Failed to read multipart field: {}", e),
@@ -125,11 +372,6 @@ impl ObjectService {
             ))?;
 
             if field_name == "file" {
-                let mimetype = multipart_field
-                    .content_type()
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| "application/octet-stream".to_string());
-
                 let file_name = multipart_field
                     .file_name()
                     .ok_or(ApiError::BackendError(
@@ -138,126 +380,893 @@ impl ObjectService {
                         ),
                     ))?
                     .to_string();
+                return self
+                    .receive_field(
+                        multipart_field,
+                        file_name,
+                        object_parent,
+                        user_id,
+                        available,
+                    )
+                    .await;
+            }
+        }
 
-                let file_id = Id::new_v4();
-                let file_path = format!("tmp/{}.{}", user_id, file_id);
-                let mut file = fs::File::create(&file_path).await?;
-                let mut total_size: usize = 0;
-
-                let mut hasher = Sha256::new();
-                let mut stream = multipart_field;
-                loop {
-                    match stream.chunk().await {
-                        Ok(Some(chunk)) => {
-                            total_size += chunk.len();
-                            file.write_all(&chunk).await?;
-                            hasher.update(&chunk); 
-                        }
-                        Ok(None) => break,
-                        Err(e) => {
-                            return Err(ApiError::BackendError(
-                                crate::error::backend_error::BackendError::InternalError(format!(
-                                    "Failed to read file chunk: {}",
-                                    e
-                                )),
-                            ));
-                        }
+        Err(UploadError::NoFileField)?
+    }
+
+    /// Содержимое поля во временный файл с подсчетом хеша и регистрация файла `file_name`.
+    /// `available` - свободное место пользователя, `None` - квоты нет.
+    /// При любой ошибке временный файл удаляется
+    async fn receive_field(
+        &self,
+        multipart_field: Field<'_>,
+        file_name: String,
+        object_parent: Option<Id>,
+        user_id: Id,
+        available: Option<i64>,
+    ) -> Result<Object, ApiError> {
+        let mimetype = multipart_field
+            .content_type()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        let file_id = Id::new_v4();
+        let file_path = format!("tmp/{}.{}", user_id, file_id);
+        let received = Self::write_field(multipart_field, &file_path, available).await;
+        let (total_size, hash_sha256) = match received {
+            Ok(received) => received,
+            Err(err) => {
+                remove_tmp(&file_path).await;
+                return Err(err);
+            }
+        };
+
+        let mut obj_constructor = ObjectCreateModel::default();
+        obj_constructor.id = file_id;
+        obj_constructor.parent_id = object_parent;
+        obj_constructor.owner_id = user_id;
+        obj_constructor.creator_id = user_id;
+        obj_constructor.name = file_name;
+        obj_constructor.size = Some(total_size as i64);
+        obj_constructor.type_ = ObjectType::File;
+        obj_constructor.mimetype = Some(mimetype);
+        obj_constructor.upload_s3 = Some(false);
+        obj_constructor.hash_sha256 = Some(hash_sha256);
+
+        self.store_uploaded_file(obj_constructor).await
+    }
+
+    /// Запись поля в `file_path`: размер и SHA-256 содержимого
+    async fn write_field(
+        mut stream: Field<'_>,
+        file_path: &str,
+        available: Option<i64>,
+    ) -> Result<(usize, String), ApiError> {
+        let mut file = fs::File::create(file_path).await?;
+        let mut total_size: usize = 0;
+        let mut hasher = Sha256::new();
+        loop {
+            match stream.chunk().await {
+                Ok(Some(chunk)) => {
+                    total_size += chunk.len();
+                    if available.is_some_and(|available| total_size as i64 > available) {
+                        return Err(UserError::StorageQuotaExceeded)?;
                     }
+                    file.write_all(&chunk).await?;
+                    hasher.update(&chunk);
                 }
-                let mut obj_constructor = ObjectCreateModel::default();
-                obj_constructor.id = file_id;
-                obj_constructor.parent_id = object_parent;
-                obj_constructor.owner_id = user_id;
-                obj_constructor.creator_id = user_id;
-                obj_constructor.name = file_name;
-                obj_constructor.size = Some(total_size as i64);
-                obj_constructor.type_ = ObjectType::File;
-                obj_constructor.mimetype = Some(mimetype);
-                obj_constructor.upload_s3 = Some(false);
+                Ok(None) => break,
+                Err(e) => {
+                    return Err(ApiError::BackendError(
+                        crate::error::backend_error::BackendError::InternalError(format!(
+                            "Failed to read file chunk: {}",
+                            e
+                        )),
+                    ));
+                }
+            }
+        }
+        file.flush().await?;
+        file.seek(SeekFrom::Start(0)).await?;
+        Ok((total_size, hex::encode(hasher.finalize())))
+    }
+
+    /// Регистрация файла, уже лежащего в `tmp/{owner_id}.{id}`:
+    /// запись объекта и доступа владельца, событие на шифрование и загрузку в S3
+    /// пишется в outbox в той же транзакции.
+    /// Если у владельца уже есть блоб с тем же содержимым, объект ссылается на него,
+    /// а временный файл удаляется без повторной загрузки. Если файл зарегистрировать
+    /// не удалось, временный файл тоже удаляется
+    pub async fn store_uploaded_file(
+        &self,
+        obj_constructor: ObjectCreateModel,
+    ) -> Result<Object, ApiError> {
+        let tmp_path = format!("tmp/{}.{}", obj_constructor.owner_id, obj_constructor.id);
+        let res = async {
+            let mut tx = self.db_conn.get_pool().begin().await?;
+            let res = self
+                .store_uploaded_file_tx(&mut tx, obj_constructor)
+                .await?;
+            tx.commit().await?;
+            Ok::<_, ApiError>(res)
+        }
+        .await;
+        match res {
+            Ok((new_obj, deduplicated)) => {
+                if deduplicated {
+                    remove_tmp(&tmp_path).await;
+                }
+                Ok(new_obj)
+            }
+            Err(err) => {
+                remove_tmp(&tmp_path).await;
+                Err(err)
+            }
+        }
+    }
 
-                let key = UploadUserEvent::generate_key(32);
+    /// То же, что `store_uploaded_file`, в транзакции вызывающего. `true` вторым значением -
+    /// содержимое уже есть в хранилище, временный файл удаляется после фиксации
+    pub async fn store_uploaded_file_tx(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        mut obj_constructor: ObjectCreateModel,
+    ) -> Result<(Object, bool), ApiError> {
+        let size = obj_constructor.size.unwrap_or(0);
+        // Квота считается по логическому размеру: дубликат занимает место как отдельный файл
+        self.user_repo
+            .reserve_storage(tx, obj_constructor.owner_id, size)
+            .await?
+            .ok_or(UserError::StorageQuotaExceeded)?;
+
+        let existing = match &obj_constructor.hash_sha256 {
+            Some(hash_sha256) => {
+                self.blob_repo
+                    .select_for_dedup(tx, obj_constructor.owner_id, hash_sha256, size)
+                    .await?
+            }
+            None => None,
+        };
+        let event = match existing {
+            Some(blob) => {
+                let blob = self.blob_repo.acquire(tx, blob.id).await?;
+                obj_constructor.blob_id = Some(blob.id);
+                obj_constructor.decode_key = blob.decode_key;
+                obj_constructor.upload_s3 = Some(blob.upload_status == UploadStatus::Stored);
+                obj_constructor.upload_status = Some(blob.upload_status);
+                None
+            }
+            None => {
+                // В БД и в событие попадает только ключ данных, обернутый KEK
+                let key = self.keyring.wrap(&cipher::generate_data_key());
+                let blob = self
+                    .blob_repo
+                    .insert(
+                        tx,
+                        BlobCreateModel {
+                            id: obj_constructor.id,
+                            owner_id: obj_constructor.owner_id,
+                            hash_sha256: obj_constructor.hash_sha256.clone(),
+                            size,
+                            decode_key: Some(key.clone()),
+                            format_version: BlobFormatVersion::Segmented,
+                        },
+                    )
+                    .await?;
+                obj_constructor.blob_id = Some(blob.id);
                 obj_constructor.decode_key = Some(key.clone());
-                
-                file.seek(SeekFrom::Start(0)).await?;
+                obj_constructor.upload_s3 = Some(false);
+                obj_constructor.upload_status = Some(blob.upload_status);
+                Some(UploadUserEvent {
+                    user_id: blob.owner_id.to_string(),
+                    object_id: blob.id.to_string(),
+                    key,
+                })
+            }
+        };
+
+        let new_obj: Object = self.object_repo.insert_object(tx, obj_constructor).await?;
+        self.uxo_repo
+            .insert_uxo(tx, new_obj.owner_id, new_obj.id, UxOAccess::owner())
+            .await?;
+
+        // Событие публикует relay из outbox только после фиксации транзакции
+        match event {
+            Some(event) => {
+                let message = UploadOutboxCreateModel::new(ROUTING_KEY_EVENT_UPLOAD_USER, &event)
+                    .map_err(|err| BackendError::InternalError(err.to_string()))?;
+                self.outbox_repo.insert(tx, message).await?;
+                Ok((new_obj, false))
+            }
+            None => {
+                tracing::debug!(
+                    "object {} deduplicated to blob {:?}",
+                    new_obj.id,
+                    new_obj.blob_id
+                );
+                Ok((new_obj, true))
+            }
+        }
+    }
+
+    /// Удаление временного файла `tmp/{owner_id}.{id}` загруженного объекта
+    pub async fn remove_uploaded_tmp(&self, obj: &Object) {
+        remove_tmp(&format!("tmp/{}.{}", obj.owner_id, obj.id)).await;
+    }
+
+    pub async fn delete_own_object(
+        &self,
+        user_id: Id,
+        dto: DeleteObjectDto,
+    ) -> Result<Object, ApiError> {
+        let obj = self
+            .access_service
+            .authorize(user_id, dto.file_id, Permission::Delete)
+            .await?;
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        let mut unreferenced = Vec::new();
+        let res = match (dto.hard_delete, dto.delete_mark, obj.in_trash) {
+            (true, _, _) => {
+                let (eliminated, blobs) = self.eliminate_subtree(&mut tx, user_id, &obj).await?;
+                unreferenced = blobs;
+                eliminated
+                    .into_iter()
+                    .find(|item| item.id == obj.id)
+                    .ok_or(ObjectError::ObjectNotFound)?
+            }
+            (false, true, false) => self.trash(&mut tx, user_id, &obj).await?,
+            (false, false, true) => self.restore(&mut tx, &obj).await?,
+            _ => obj,
+        };
+        tx.commit().await?;
+        self.delete_blobs_later(unreferenced);
+        Ok(res)
+    }
 
-                let hash_res = hasher.finalize();
-                let hash_sha256 = hex::encode(hash_res).to_string();
+    /// Одно действие над списком объектов в одной транзакции. Права проверяются для каждого
+    /// объекта, ошибка одного объекта откатывает только его изменения
+    pub async fn bulk(
+        &self,
+        user_id: Id,
+        mut dto: BulkObjectDto,
+    ) -> Result<BulkObjectReport, ApiError> {
+        if dto.action == BulkObjectAction::Share && dto.access.is_none() {
+            return Err(ObjectError::MissingActionParameter("access"))?;
+        }
+        let mut object_ids = std::mem::take(&mut dto.object_ids);
+        let mut seen = HashSet::new();
+        object_ids.retain(|id| seen.insert(*id));
+
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        let mut unreferenced = Vec::new();
+        let mut items = Vec::with_capacity(object_ids.len());
+        for object_id in object_ids {
+            db::savepoint(&mut tx, BULK_SAVEPOINT).await?;
+            let res = self.bulk_item(&mut tx, user_id, object_id, &dto).await;
+            match res {
+                Ok((object, blobs)) => {
+                    db::release_savepoint(&mut tx, BULK_SAVEPOINT).await?;
+                    unreferenced.extend(blobs);
+                    items.push(BulkObjectResult {
+                        object_id,
+                        ok: true,
+                        object,
+                        status: None,
+                        error: None,
+                    });
+                }
+                Err(err) => {
+                    db::rollback_to_savepoint(&mut tx, BULK_SAVEPOINT).await?;
+                    let error = err.to_string();
+                    items.push(BulkObjectResult {
+                        object_id,
+                        ok: false,
+                        object: None,
+                        status: Some(err.into_response().status().as_u16()),
+                        error: Some(error),
+                    });
+                }
+            }
+        }
+        tx.commit().await?;
+        self.delete_blobs_later(unreferenced);
 
-                println!("hash_sha256 = {}", hash_sha256);
-                obj_constructor.hash_sha256 = Some(hash_sha256);
+        let succeeded = items.iter().filter(|item| item.ok).count();
+        Ok(BulkObjectReport {
+            succeeded,
+            failed: items.len() - succeeded,
+            items,
+        })
+    }
 
-                let mut tx = self.db_conn.get_pool().begin().await?;
-                let new_obj: Object = self
-                    .object_repo
-                    .insert_object(&mut tx, obj_constructor)
+    /// Действие массовой операции над одним объектом. Объект и права читаются в транзакции,
+    /// поэтому видны изменения предыдущих объектов. `None` вместо объекта - он уже изменен
+    /// вместе с папкой, обработанной раньше в этом же запросе
+    async fn bulk_item(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        user_id: Id,
+        object_id: Id,
+        dto: &BulkObjectDto,
+    ) -> Result<(Option<Object>, Vec<Blob>), ApiError> {
+        let permission = match dto.action {
+            BulkObjectAction::Trash | BulkObjectAction::Restore | BulkObjectAction::Eliminate => {
+                Permission::Delete
+            }
+            BulkObjectAction::Move | BulkObjectAction::Share => Permission::Edit,
+        };
+        let res = self
+            .access_service
+            .authorize_tx(tx, user_id, object_id, permission)
+            .await;
+        let obj = match res {
+            Ok(obj) => obj,
+            // Удален окончательно вместе с папкой: в транзакции его уже нет
+            Err(ApiError::ObjectError(ObjectError::ObjectNotFound))
+                if dto.action == BulkObjectAction::Eliminate =>
+            {
+                self.access_service
+                    .authorize(user_id, object_id, permission)
+                    .await?;
+                return Ok((None, Vec::new()));
+            }
+            Err(err) => return Err(err),
+        };
+
+        let res = match dto.action {
+            BulkObjectAction::Trash if obj.in_trash => Ok(obj),
+            BulkObjectAction::Trash => {
+                let obj = self.trash(tx, user_id, &obj).await?;
+                return Ok((Some(obj), Vec::new()));
+            }
+            BulkObjectAction::Restore if !obj.in_trash => Ok(obj),
+            BulkObjectAction::Restore => {
+                let obj = self.restore(tx, &obj).await?;
+                return Ok((Some(obj), Vec::new()));
+            }
+            BulkObjectAction::Eliminate => {
+                let (eliminated, blobs) = self.eliminate_subtree(tx, user_id, &obj).await?;
+                let obj = eliminated.into_iter().find(|item| item.id == obj.id);
+                return Ok((obj, blobs));
+            }
+            BulkObjectAction::Move => {
+                if obj.in_trash {
+                    return Err(ObjectError::ObjectNotFound)?;
+                }
+                if dto.parent_id.is_none() && obj.owner_id != user_id {
+                    return Err(ObjectError::AccessDenied)?;
+                }
+                let name = obj.name.clone();
+                let obj = self
+                    .rename_and_move(tx, user_id, obj, name, dto.parent_id)
+                    .await?;
+                return Ok((Some(obj), Vec::new()));
+            }
+            BulkObjectAction::Share => {
+                let access = dto
+                    .access
+                    .clone()
+                    .ok_or(ObjectError::MissingActionParameter("access"))?;
+                let granted = UxOAccess {
+                    can_read: access.can_read,
+                    can_edit: access.can_edit,
+                    can_delete: access.can_delete,
+                };
+                let recipient = self
+                    .user_repo
+                    .select_by_email(access.recipient_email)
+                    .await
+                    .ok_or(UserError::UserNotFound)?;
+                self.access_service
+                    .authorize_grant_tx(tx, user_id, obj.id, recipient.id, &granted)
                     .await?;
                 self.uxo_repo
-                    .insert_uxo(&mut tx, new_obj.owner_id, new_obj.id, UxOAccess::owner())
+                    .upsert_access(tx, recipient.id, obj.id, granted)
                     .await?;
+                Ok(obj)
+            }
+        };
+        match res {
+            Ok(obj) => Ok((Some(obj), Vec::new())),
+            Err(sqlx::Error::RowNotFound) => Ok((None, Vec::new())),
+            Err(err) => Err(err)?,
+        }
+    }
 
-                let event = UploadUserEvent {
-                    user_id: user_id.to_string(),
-                    object_id: file_id.to_string(),
-                    key: key,
-                };
-                tracing::debug!("transaction ready");
-                
-                let rmq_con = self.rmq_conn.clone();
-                tokio::spawn(async move {
-                    send_upload_user_event(event, &rmq_con).await;
-                });
-                tracing::debug!("send_upload_user_event finished");
-                tx.commit().await?;
-                return Ok(new_obj);
+    /// Метаданные объекта с путем от корня. Пользователь без доступа к верхним
+    /// папкам видит путь только от папки, которой с ним поделились
+    pub async fn get_info(&self, user_id: Id, id: Id) -> Result<ObjectInfo, ApiError> {
+        let (obj, access) = self
+            .access_service
+            .authorize_access(user_id, id, Permission::Read)
+            .await?;
+
+        let is_owner = obj.owner_id == user_id;
+        let mut can_read = false;
+        let path = self
+            .object_repo
+            .select_path(user_id, obj.id)
+            .await?
+            .into_iter()
+            .filter(|item| {
+                can_read = item.can_read.unwrap_or(can_read);
+                is_owner || can_read
+            })
+            .collect();
+        let owner = self.user_repo.select_by_id(obj.owner_id).await?;
+
+        Ok(ObjectInfo {
+            object: obj,
+            path,
+            owner: ObjectOwner::from(owner),
+            access,
+        })
+    }
+
+    /// Переименование и перенос в другую папку. В корень переносит только владелец
+    pub async fn update_info(&self, user_id: Id, dto: UpdateObjectDto) -> Result<Object, ApiError> {
+        let obj = self
+            .access_service
+            .authorize(user_id, dto.object_id, Permission::Edit)
+            .await?;
+        if obj.in_trash {
+            return Err(ObjectError::ObjectNotFound)?;
+        }
+
+        let parent_id = match dto.parent_id {
+            Some(Some(parent_id)) => Some(parent_id),
+            Some(None) if obj.owner_id != user_id => return Err(ObjectError::AccessDenied)?,
+            Some(None) => None,
+            None => obj.parent_id,
+        };
+        let name = dto.name.unwrap_or_else(|| obj.name.clone());
+        if name == obj.name && parent_id == obj.parent_id {
+            return Ok(obj);
+        }
+        if parent_id != obj.parent_id {
+            self.access_service
+                .authorize_parent(user_id, parent_id)
+                .await?;
+        }
+
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        let res = self
+            .rename_and_move(&mut tx, user_id, obj, name, parent_id)
+            .await?;
+        tx.commit().await?;
+        Ok(res)
+    }
+
+    /// Переименование и перенос в транзакции `tx`, права на сам объект проверяет вызывающий.
+    /// Папка назначения, циклы и совпадение имен проверяются в транзакции после блокировки
+    /// объекта и папок назначения
+    async fn rename_and_move(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        user_id: Id,
+        obj: Object,
+        name: String,
+        parent_id: Option<Id>,
+    ) -> Result<Object, ApiError> {
+        if name == obj.name && parent_id == obj.parent_id {
+            return Ok(obj);
+        }
+        self.object_repo
+            .lock_move(tx, obj.id, obj.owner_id, parent_id)
+            .await?;
+        if parent_id != obj.parent_id {
+            self.access_service
+                .authorize_parent_tx(tx, user_id, parent_id)
+                .await?;
+        }
+        if let Some(new_parent_id) = parent_id.filter(|_| parent_id != obj.parent_id) {
+            if self
+                .object_repo
+                .is_in_subtree_tx(tx, new_parent_id, obj.id)
+                .await?
+            {
+                return Err(ObjectError::MoveIntoItself)?;
             }
         }
+        if self
+            .object_repo
+            .exists_in_folder_tx(tx, obj.owner_id, parent_id, &name, obj.id)
+            .await?
+        {
+            return Err(ObjectError::NameConflict)?;
+        }
+        let res = self
+            .object_repo
+            .update_info(tx, obj.id, &name, parent_id)
+            .await?;
+        Ok(res)
+    }
+
+    /// Окончательное удаление поддерева: строки помечаются `eliminated`, занятое место
+    /// владельцев пересчитывается. Возвращает удаленные объекты и блобы без оставшихся ссылок
+    async fn eliminate_subtree(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        user_id: Id,
+        obj: &Object,
+    ) -> Result<(Vec<Object>, Vec<Blob>), ApiError> {
+        self.access_service
+            .authorize_subtree_delete_tx(tx, user_id, obj, true)
+            .await?;
+        let eliminated = self.object_repo.mark_as_eliminated(tx, obj.id).await?;
+
+        let mut owner_ids: Vec<Id> = eliminated.iter().map(|obj| obj.owner_id).collect();
+        owner_ids.sort();
+        owner_ids.dedup();
+        self.user_repo
+            .recalculate_storage_size(tx, &owner_ids)
+            .await?;
+
+        let blob_ids: Vec<Id> = eliminated.iter().filter_map(|obj| obj.blob_id).collect();
+        let unreferenced = self.blob_repo.release(tx, &blob_ids).await?;
+        Ok((eliminated, unreferenced))
+    }
 
-        Err(ApiError::UserError(
-            crate::error::user_error::UserError::InvalidPassword,
-        ))
+    /// Перемещение в корзину; удалять пользователь должен мочь все поддерево
+    async fn trash(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        user_id: Id,
+        obj: &Object,
+    ) -> Result<Object, ApiError> {
+        self.access_service
+            .authorize_subtree_delete_tx(tx, user_id, obj, false)
+            .await?;
+        Ok(self.object_repo.mark_as_deleted(tx, obj.id).await?)
     }
 
-    pub async fn delete_own_object(&self, dto: DeleteObjectDto) -> Result<Object, ApiError> {
-        //todo: add check access
-        let res = match dto.hard_delete {
-            true => self.object_repo.mark_as_eliminated(dto.file_id).await?,
-            false => match dto.delete_mark {
-                true => self.object_repo.mark_as_deleted(dto.file_id).await?,
-                false => self.object_repo.mark_as_restored(dto.file_id).await?,
+    /// Восстановление в прежнюю папку, а если ее уже нет - в корень владельца.
+    /// Объект с тем же именем на этом месте - конфликт
+    async fn restore(
+        &self,
+        tx: &mut Transaction<'static, Postgres>,
+        obj: &Object,
+    ) -> Result<Object, ApiError> {
+        let parent_id = match obj.parent_id {
+            Some(parent_id) => match self.object_repo.select_by_id_tx(tx, parent_id).await {
+                Ok(parent) => (!parent.in_trash).then_some(parent.id),
+                Err(sqlx::Error::RowNotFound) => None,
+                Err(err) => Err(err)?,
             },
+            None => None,
         };
+        let taken = self
+            .object_repo
+            .exists_in_folder_tx(tx, obj.owner_id, parent_id, &obj.name, obj.id)
+            .await?;
+        if taken {
+            return Err(ObjectError::NameConflict)?;
+        }
+        Ok(self.object_repo.mark_as_restored(tx, obj.id).await?)
+    }
 
-        Ok(res)
+    /// Удаление блобов из хранилища в фоне, после фиксации транзакции
+    fn delete_blobs_later(&self, blobs: Vec<Blob>) {
+        if blobs.is_empty() {
+            return;
+        }
+        let storage_repo = self.storage_repo.clone();
+        tokio::spawn(async move {
+            storage_repo.delete_blobs(&blobs).await;
+        });
     }
 
-    pub async fn download_own_file(&self, id: Id) -> Result<DownloadFileUrl, ApiError> {
-        let obj = self.object_repo.select_by_id(id).await?;
-        let data: Vec<u8> = self.s3_repo.get_bytes(&obj).await.unwrap();
+    pub async fn download_own_file(
+        &self,
+        user_id: Id,
+        id: Id,
+        range: Option<Range>,
+        if_range: Option<IfRange>,
+    ) -> Result<FileResponse, ApiError> {
+        let obj = self
+            .access_service
+            .authorize(user_id, id, Permission::Read)
+            .await?;
+        self.stream_file(obj, range, if_range).await
+    }
 
-        let mut key = [0u8; 32];
-        let nonce: [u8; 16] = {
-            hex::decode_to_slice(obj.clone().decode_key.unwrap(), &mut key).unwrap();    
-            let mut hasher = Sha256::new();
-            Digest::update(&mut hasher, key);
-            let result = hasher.finalize();
-            result[..16].try_into().unwrap()
+    /// Потоковая выдача файла: тело из хранилища расшифровывается и проверяется по мере чтения.
+    /// Для `Range` из хранилища читаются только сегменты, в которые попадает диапазон
+    /// (у блобов старого формата - сам диапазон). Права на объект проверяет вызывающий
+    pub async fn stream_file(
+        &self,
+        obj: Object,
+        range: Option<Range>,
+        if_range: Option<IfRange>,
+    ) -> Result<FileResponse, ApiError> {
+        let decode_key = match (&obj.type_, &obj.decode_key) {
+            (ObjectType::File, Some(key)) => key,
+            _ => return Err(ObjectError::NotAFile)?,
         };
-        let mut cipher = Aes256Ctr::new(&key.into(), &nonce.into());
+        match obj.upload_status {
+            Some(UploadStatus::Pending) => return Err(ObjectError::UploadPending)?,
+            Some(UploadStatus::Failed) => return Err(ObjectError::UploadFailed)?,
+            _ => {}
+        }
+        let data_key = self
+            .keyring
+            .unwrap(decode_key)
+            .map_err(|_| ObjectError::InvalidDecodeKey)?;
 
-        let now = Instant::now();
-        let mut decoded_data = data;
-        cipher.apply_keystream(&mut decoded_data);
-        tracing::debug!("Decrupted elapsed: {:.2?}", now.elapsed());
+        let size = obj.size.unwrap_or(0) as u64;
+        let range = match if_range {
+            Some(if_range)
+                if if_range.is_modified(
+                    FileResponse::etag(&obj).as_ref(),
+                    Some(&FileResponse::last_modified(&obj)),
+                ) =>
+            {
+                None
+            }
+            _ => range,
+        };
+        let byte_range = match RequestedRange::resolve(range.as_ref(), size) {
+            RequestedRange::Full => None,
+            RequestedRange::Partial(byte_range) => Some(byte_range),
+            RequestedRange::NotSatisfiable => return Ok(FileResponse::not_satisfiable(&obj, size)),
+        };
+        let (body, prefix, decryptor) = self.open_blob(&obj, &data_key, size, byte_range).await?;
 
-        let mut hasher = Sha256::new();
-        hasher.update(&decoded_data);
-        let hash_res = hasher.finalize();
-        let hash_sha256 = hex::encode(hash_res).to_string();
-        println!("new hash = {}, old hash = {}", hash_sha256, &obj.hash_sha256.clone().unwrap().to_string());
+        let stream = blob_stream::decrypt_stream(obj.id, body, prefix, decryptor);
+        let body = Body::from_stream(stream);
+        match byte_range {
+            Some(byte_range) => Ok(FileResponse::partial(&obj, body, byte_range, size)),
+            None => Ok(FileResponse::new(&obj, body)),
+        }
+    }
 
-        let _ = self.s3_repo.upload_bytes(&obj, decoded_data).await.unwrap();
-        let res = self.s3_repo.generate_presigned_url(obj).await?;
-        Ok(res)
+    /// Скачивание файлов и папок одним ZIP-архивом без сжатия. Архив собирается на лету:
+    /// каждый файл расшифровывается при отправке, на диск ничего не пишется. Недоступные
+    /// на чтение, незагруженные и нечитаемые из хранилища объекты пропускаются и
+    /// перечисляются в `skipped.txt` внутри архива
+    pub async fn download_zip(
+        &self,
+        user_id: Id,
+        object_ids: Vec<Id>,
+    ) -> Result<FileResponse, ApiError> {
+        let mut roots: Vec<Object> = Vec::with_capacity(object_ids.len());
+        for id in object_ids {
+            if roots.iter().any(|root| root.id == id) {
+                continue;
+            }
+            let obj = self
+                .access_service
+                .authorize(user_id, id, Permission::Read)
+                .await?;
+            if obj.in_trash {
+                return Err(ObjectError::ObjectNotFound)?;
+            }
+            roots.push(obj);
+        }
+        let name = match roots.as_slice() {
+            [root] => format!("{}.zip", root.name),
+            _ => "download.zip".to_string(),
+        };
+
+        let mut paths = ArchivePaths::default();
+        let mut added = HashSet::new();
+        let mut members = Vec::new();
+        let mut skipped = Vec::new();
+        for root in &roots {
+            let (readable, unreadable) =
+                self.access_service.readable_subtree(user_id, root).await?;
+            let mut dirs: HashMap<Id, String> = HashMap::new();
+            for obj in readable {
+                if !added.insert(obj.id) {
+                    continue;
+                }
+                let parent = match obj.id == root.id {
+                    true => None,
+                    false => obj.parent_id.and_then(|id| dirs.get(&id)),
+                };
+                let is_file = matches!(obj.type_, ObjectType::File);
+                let path = paths.add(parent.map(String::as_str), &obj.name, is_file);
+                let reason = match obj.upload_status {
+                    _ if !is_file => None,
+                    Some(UploadStatus::Pending) => Some("upload is still in progress"),
+                    Some(UploadStatus::Failed) => Some("upload failed"),
+                    _ if obj.decode_key.is_none() => Some("file has no content"),
+                    _ => None,
+                };
+                match reason {
+                    Some(reason) => skipped.push(ZipSkip { path, reason }),
+                    None if is_file => members.push(ZipMember { path, obj }),
+                    None => {
+                        dirs.insert(obj.id, path.clone());
+                        members.push(ZipMember { path, obj });
+                    }
+                }
+            }
+            for obj in unreadable {
+                let name = zip_stream::sanitize_name(&obj.name);
+                let path = match obj.parent_id.and_then(|id| dirs.get(&id)) {
+                    Some(parent) => format!("{parent}/{name}"),
+                    None => name,
+                };
+                skipped.push(ZipSkip {
+                    path,
+                    reason: "access denied",
+                });
+            }
+        }
+
+        let (tx, rx) = mpsc::channel(ZIP_CHANNEL_CAPACITY);
+        let service = self.clone();
+        tokio::spawn(async move {
+            if service
+                .write_zip(members, skipped, paths, &tx)
+                .await
+                .is_err()
+            {
+                tracing::info!("ZIP download for user {} was interrupted", user_id);
+            }
+        });
+        let stream = stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        });
+        Ok(FileResponse::archive(name, Body::from_stream(stream)))
+    }
+
+    /// Отправка архива кусками в `tx`. Файл, который не удалось открыть, пропускается;
+    /// ошибка посреди файла обрывает архив, чтобы клиент не получил битые данные.
+    /// Ошибка означает, что клиент перестал читать ответ
+    async fn write_zip(
+        &self,
+        members: Vec<ZipMember>,
+        mut skipped: Vec<ZipSkip>,
+        mut paths: ArchivePaths,
+        tx: &ZipSender,
+    ) -> Result<(), mpsc::error::SendError<io::Result<Bytes>>> {
+        let mut zip = ZipWriter::new();
+        for ZipMember { path, obj } in members {
+            let modified = obj.updated_at.unwrap_or(obj.created_at);
+            if !matches!(obj.type_, ObjectType::File) {
+                tx.send(Ok(zip.add_dir(&path, modified))).await?;
+                continue;
+            }
+            let plaintext = match self.open_plaintext(&obj).await {
+                Ok(plaintext) => plaintext,
+                Err(err) => {
+                    tracing::error!("Failed to open object {} for ZIP: {}", obj.id, err);
+                    skipped.push(ZipSkip {
+                        path,
+                        reason: "file could not be read from storage",
+                    });
+                    continue;
+                }
+            };
+            let size = obj.size.unwrap_or(0) as u64;
+            tx.send(Ok(zip.start_file(&path, modified, size))).await?;
+
+            let mut plaintext = std::pin::pin!(plaintext);
+            let mut hasher = crc32fast::Hasher::new();
+            let mut written = 0;
+            while let Some(chunk) = plaintext.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(err) => return tx.send(Err(err)).await,
+                };
+                hasher.update(&chunk);
+                written += chunk.len() as u64;
+                tx.send(Ok(chunk)).await?;
+            }
+            tx.send(Ok(zip.finish_file(hasher.finalize(), written)))
+                .await?;
+        }
+
+        if !skipped.is_empty() {
+            let report: String = skipped
+                .iter()
+                .map(|skip| format!("{} - {}\n", skip.path, skip.reason))
+                .collect();
+            let path = paths.add(None, ZIP_SKIPPED_NAME, true);
+            let now = chrono::Utc::now().naive_utc();
+            let size = report.len() as u64;
+            tx.send(Ok(zip.start_file(&path, now, size))).await?;
+            tx.send(Ok(Bytes::from(report.clone()))).await?;
+            tx.send(Ok(zip.finish_file(crc32fast::hash(report.as_bytes()), size)))
+                .await?;
+        }
+        tx.send(Ok(zip.finish())).await
+    }
+
+    /// Расшифрованное содержимое файла целиком
+    async fn open_plaintext(
+        &self,
+        obj: &Object,
+    ) -> Result<impl Stream<Item = io::Result<Bytes>>, ApiError> {
+        let decode_key = obj
+            .decode_key
+            .as_deref()
+            .ok_or(ObjectError::InvalidDecodeKey)?;
+        let data_key = self
+            .keyring
+            .unwrap(decode_key)
+            .map_err(|_| ObjectError::InvalidDecodeKey)?;
+        let size = obj.size.unwrap_or(0) as u64;
+        let (body, prefix, decryptor) = self.open_blob(obj, &data_key, size, None).await?;
+        Ok(blob_stream::decrypt_stream(obj.id, body, prefix, decryptor))
+    }
+
+    /// Тело блоба, уже прочитанное из него начало и расшифровщик для выдачи `byte_range`.
+    /// Формат берется из блоба в БД. Заголовок сегментного блоба для полного скачивания
+    /// читается из того же потока, для диапазона - отдельным запросом
+    async fn open_blob(
+        &self,
+        obj: &Object,
+        data_key: &[u8; cipher::KEY_LENGTH],
+        size: u64,
+        byte_range: Option<ByteRange>,
+    ) -> Result<(StorageStream, Vec<u8>, BlobDecryptor), ApiError> {
+        let format_version = self.blob_format_version(obj).await?;
+        let byte_range = match byte_range {
+            Some(byte_range) => byte_range,
+            None => {
+                let mut body = self.storage_repo.get_stream(obj, None).await?;
+                let head = blob_stream::read_head(&mut body).await?;
+                let (prefix, decryptor) =
+                    blob_stream::open_full(head, data_key, size, format_version)
+                        .map_err(|err| Self::corrupted(obj, err))?;
+                return Ok((body, prefix, decryptor));
+            }
+        };
+
+        let header = match format_version {
+            BlobFormatVersion::Legacy => None,
+            BlobFormatVersion::Segmented => {
+                let header_range = ByteRange {
+                    start: 0,
+                    end: cipher::HEADER_LENGTH as u64 - 1,
+                };
+                let mut head_body = self
+                    .storage_repo
+                    .get_stream(obj, Some(header_range))
+                    .await?;
+                let head = blob_stream::read_head(&mut head_body).await?;
+                blob_stream::parse_header(&head, format_version)
+                    .map_err(|err| Self::corrupted(obj, err))?
+            }
+        };
+        match header {
+            Some(header) => {
+                let blob_cipher = BlobCipher::new(data_key, header, size);
+                let encrypted_range = blob_stream::encrypted_range(&blob_cipher, byte_range);
+                let body = self
+                    .storage_repo
+                    .get_stream(obj, Some(encrypted_range))
+                    .await?;
+                let reader = SegmentReader::new(blob_cipher, byte_range.start, byte_range.length());
+                Ok((body, Vec::new(), BlobDecryptor::Segmented(reader)))
+            }
+            None => {
+                let mut ctr = cipher::init_cipher(data_key);
+                ctr.seek(byte_range.start);
+                let body = self.storage_repo.get_stream(obj, Some(byte_range)).await?;
+                Ok((body, Vec::new(), BlobDecryptor::Ctr(ctr)))
+            }
+        }
+    }
+
+    /// Формат содержимого файла из его блоба
+    async fn blob_format_version(&self, obj: &Object) -> Result<BlobFormatVersion, ApiError> {
+        let Some(blob_id) = obj.blob_id else {
+            tracing::error!("Object {} has no blob", obj.id);
+            return Err(ObjectError::CorruptedBlob)?;
+        };
+        let mut conn = self.db_conn.get_pool().acquire().await?;
+        let blob = self.blob_repo.select_by_id(&mut conn, blob_id).await?;
+        Ok(blob.format_version)
+    }
+
+    fn corrupted(obj: &Object, err: CipherError) -> ObjectError {
+        tracing::error!("Object {} has unreadable blob header: {}", obj.id, err);
+        ObjectError::CorruptedBlob
     }
 
     pub async fn admin_get_object_list(
@@ -268,3 +1277,537 @@ impl ObjectService {
         Ok(objects_paginated)
     }
 }
+
+/// Удаление временного файла; отсутствие файла не ошибка
+async fn remove_tmp(path: &str) {
+    match fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => tracing::error!("Failed to remove {}: {}", path, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::user::CreateUserDto;
+    use axum::extract::FromRequest;
+    use axum::http::{header, Request};
+    use file_worker::storage::LocalStorage;
+    use sqlx::PgPool;
+
+    const BOUNDARY: &str = "flaxum-test-boundary";
+
+    async fn service(pool: PgPool) -> ObjectService {
+        let db_conn = Arc::new(Database::from_pool(pool));
+        let root = std::env::temp_dir().join(format!("flaxum-test-{}", Id::new_v4()));
+        let storage: Arc<dyn StorageBackend> = Arc::new(LocalStorage::init(root).await.unwrap());
+        let keyring = Arc::new(Keyring::from_hex(&"11".repeat(32), None).unwrap());
+        fs::create_dir_all("tmp").await.unwrap();
+        ObjectService::new(&db_conn, &storage, &keyring)
+    }
+
+    async fn create_user(service: &ObjectService, email: &str) -> Id {
+        service
+            .user_repo
+            .create_user(CreateUserDto {
+                email: email.to_string(),
+                password: "password".to_string(),
+            })
+            .await
+            .unwrap();
+        service
+            .user_repo
+            .select_by_email(email.to_string())
+            .await
+            .unwrap()
+            .id
+    }
+
+    /// Multipart-запрос с полями `path` и `file` для каждой пары путь - содержимое
+    async fn multipart(files: &[(&str, &str)]) -> Multipart {
+        let mut body = String::new();
+        for (path, content) in files {
+            body.push_str(&format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"path\"\r\n\r\n{path}\r\n"
+            ));
+            body.push_str(&format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"file\"\r\n\
+                Content-Type: text/plain\r\n\r\n{content}\r\n"
+            ));
+        }
+        body.push_str(&format!("--{BOUNDARY}--\r\n"));
+        let req = Request::builder()
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(Body::from(body))
+            .unwrap();
+        Multipart::from_request(req, &()).await.unwrap()
+    }
+
+    async fn upload(service: &ObjectService, user_id: Id, files: &[(&str, &str)]) {
+        let report = service
+            .upload_own_file(multipart(files).await, None, user_id, None)
+            .await
+            .unwrap();
+        assert_eq!(report.failed, 0);
+        for item in report.items {
+            let obj = item.object.unwrap();
+            let _ = fs::remove_file(format!("tmp/{}.{}", user_id, obj.id)).await;
+        }
+    }
+
+    /// Живые объекты пользователя: путь от корня и тип
+    async fn tree(pool: &PgPool, user_id: Id) -> Vec<(String, String)> {
+        let q = r#"
+        WITH RECURSIVE tree AS (
+            SELECT id, name::text AS path, type::text AS type FROM "Object"
+            WHERE owner_id = $1 AND parent_id IS NULL AND eliminated IS FALSE
+            UNION ALL
+            SELECT "Object".id, tree.path || '/' || "Object".name, "Object".type::text
+            FROM "Object"
+            JOIN tree ON "Object".parent_id = tree.id
+            WHERE "Object".eliminated IS FALSE
+        )
+        SELECT path, type FROM tree ORDER BY path, type
+        "#;
+        sqlx::query_as(q)
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    fn entries(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(path, type_)| (path.to_string(), type_.to_string()))
+            .collect()
+    }
+
+    async fn folder(
+        service: &ObjectService,
+        user_id: Id,
+        parent_id: Option<Id>,
+        name: &str,
+    ) -> Object {
+        service
+            .create_own_folder(ObjectCreateModel {
+                id: Id::new_v4(),
+                parent_id,
+                owner_id: user_id,
+                creator_id: user_id,
+                name: name.to_string(),
+                size: Some(0),
+                type_: ObjectType::Dir,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+    }
+
+    async fn bulk_move(
+        service: &ObjectService,
+        user_id: Id,
+        object_ids: Vec<Id>,
+        parent_id: Option<Id>,
+    ) -> BulkObjectReport {
+        service
+            .bulk(
+                user_id,
+                BulkObjectDto {
+                    object_ids,
+                    action: BulkObjectAction::Move,
+                    parent_id,
+                    access: None,
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn move_sees_earlier_moves_in_same_transaction(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let user_id = create_user(&service, "cycle@flaxum.test").await;
+        let a = folder(&service, user_id, None, "A").await;
+        let b = folder(&service, user_id, None, "B").await;
+
+        let mut tx = pool.begin().await.unwrap();
+        let a = service
+            .rename_and_move(&mut tx, user_id, a, "A".to_string(), Some(b.id))
+            .await
+            .unwrap();
+        let res = service
+            .rename_and_move(&mut tx, user_id, b, "B".to_string(), Some(a.id))
+            .await;
+        assert!(matches!(
+            res,
+            Err(ApiError::ObjectError(ObjectError::MoveIntoItself))
+        ));
+        tx.commit().await.unwrap();
+
+        assert_eq!(
+            tree(&pool, user_id).await,
+            entries(&[("B", "dir"), ("B/A", "dir")])
+        );
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn move_waits_only_for_moves_it_can_conflict_with(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let user_id = create_user(&service, "lock@flaxum.test").await;
+        let a = folder(&service, user_id, None, "A").await;
+        let b = folder(&service, user_id, None, "B").await;
+        let c = folder(&service, user_id, None, "C").await;
+        let d = folder(&service, user_id, None, "D").await;
+        let wait = std::time::Duration::from_millis(300);
+
+        let mut tx = pool.begin().await.unwrap();
+        service
+            .rename_and_move(&mut tx, user_id, a.clone(), "A".to_string(), Some(b.id))
+            .await
+            .unwrap();
+
+        // Другая часть дерева не ждет незавершенный перенос
+        let report =
+            tokio::time::timeout(wait, bulk_move(&service, user_id, vec![c.id], Some(d.id)))
+                .await
+                .unwrap();
+        assert_eq!(report.succeeded, 1);
+        // Встречный перенос ждет его и после фиксации видит цикл
+        let res =
+            tokio::time::timeout(wait, bulk_move(&service, user_id, vec![b.id], Some(a.id))).await;
+        assert!(res.is_err());
+        tx.commit().await.unwrap();
+        let report = bulk_move(&service, user_id, vec![b.id], Some(a.id)).await;
+        assert_eq!(report.failed, 1);
+
+        assert_eq!(
+            tree(&pool, user_id).await,
+            entries(&[("B", "dir"), ("B/A", "dir"), ("D", "dir"), ("D/C", "dir")])
+        );
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn bulk_move_rejects_cycles_and_duplicate_names(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let user_id = create_user(&service, "bulk@flaxum.test").await;
+        let a = folder(&service, user_id, None, "A").await;
+        let b = folder(&service, user_id, None, "B").await;
+
+        let report = bulk_move(&service, user_id, vec![a.id], Some(b.id)).await;
+        assert_eq!(report.succeeded, 1);
+        let report = bulk_move(&service, user_id, vec![b.id], Some(a.id)).await;
+        assert_eq!(report.failed, 1);
+
+        // Одинаковые имена из разных папок в одну папку назначения
+        let x1 = folder(&service, user_id, Some(a.id), "x").await;
+        let x2 = folder(&service, user_id, None, "x").await;
+        let c = folder(&service, user_id, None, "C").await;
+        let report = bulk_move(&service, user_id, vec![x1.id, x2.id], Some(c.id)).await;
+        assert_eq!((report.succeeded, report.failed), (1, 1));
+        assert!(!report.items[1].ok);
+
+        assert_eq!(
+            tree(&pool, user_id).await,
+            entries(&[
+                ("B", "dir"),
+                ("B/A", "dir"),
+                ("C", "dir"),
+                ("C/x", "dir"),
+                ("x", "dir"),
+            ])
+        );
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn upload_by_path_reuses_existing_folders(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let user_id = create_user(&service, "upload@flaxum.test").await;
+
+        // Файл с именем папки из пути не должен становиться ее родителем
+        upload(&service, user_id, &[("a", "not a folder")]).await;
+        upload(&service, user_id, &[("a/b/c.txt", "first")]).await;
+        upload(&service, user_id, &[("a/b/c.txt", "second")]).await;
+
+        assert_eq!(
+            tree(&pool, user_id).await,
+            entries(&[
+                ("a", "dir"),
+                ("a", "file"),
+                ("a/b", "dir"),
+                ("a/b/c.txt", "file"),
+                ("a/b/c.txt", "file"),
+            ])
+        );
+    }
+
+    async fn share(
+        pool: &PgPool,
+        service: &ObjectService,
+        user_id: Id,
+        obj: &Object,
+        access: UxOAccess,
+    ) {
+        let mut tx = pool.begin().await.unwrap();
+        service
+            .uxo_repo
+            .insert_uxo(&mut tx, user_id, obj.id, access)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    async fn delete(
+        service: &ObjectService,
+        user_id: Id,
+        obj: &Object,
+        hard_delete: bool,
+    ) -> Result<Object, ApiError> {
+        service
+            .delete_own_object(
+                user_id,
+                DeleteObjectDto {
+                    file_id: obj.id,
+                    delete_mark: true,
+                    hard_delete,
+                },
+            )
+            .await
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn delete_requires_access_to_whole_subtree(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let owner = create_user(&service, "owner@flaxum.test").await;
+        let guest = create_user(&service, "guest@flaxum.test").await;
+        let shared = folder(&service, owner, None, "shared").await;
+        let private = folder(&service, owner, Some(shared.id), "private").await;
+        folder(&service, owner, Some(private.id), "nested").await;
+        share(&pool, &service, guest, &shared, UxOAccess::owner()).await;
+        let read_only = UxOAccess {
+            can_read: true,
+            can_edit: false,
+            can_delete: false,
+        };
+        share(&pool, &service, guest, &private, read_only).await;
+
+        for hard_delete in [false, true] {
+            let res = delete(&service, guest, &shared, hard_delete).await;
+            assert!(matches!(
+                res,
+                Err(ApiError::ObjectError(ObjectError::AccessDenied))
+            ));
+        }
+        let report = service
+            .bulk(
+                guest,
+                BulkObjectDto {
+                    object_ids: vec![shared.id],
+                    action: BulkObjectAction::Eliminate,
+                    parent_id: None,
+                    access: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.items[0].status, Some(403));
+        let expected = entries(&[
+            ("shared", "dir"),
+            ("shared/private", "dir"),
+            ("shared/private/nested", "dir"),
+        ]);
+        assert_eq!(tree(&pool, owner).await, expected);
+        let obj = service.object_repo.select_by_id(shared.id).await.unwrap();
+        assert!(!obj.in_trash);
+
+        // Папку с запретом нельзя удалить и напрямую, владелец удаляет все
+        let private = delete(&service, guest, &private, false).await;
+        assert!(private.is_err());
+        let trashed = delete(&service, owner, &shared, false).await.unwrap();
+        assert!(trashed.in_trash);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn restore_rejects_taken_name(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let user_id = create_user(&service, "restore@flaxum.test").await;
+        let parent = folder(&service, user_id, None, "parent").await;
+        let old = folder(&service, user_id, Some(parent.id), "d").await;
+        let old = delete(&service, user_id, &old, false).await.unwrap();
+        folder(&service, user_id, Some(parent.id), "d").await;
+
+        let restore = DeleteObjectDto {
+            file_id: old.id,
+            delete_mark: false,
+            hard_delete: false,
+        };
+        let res = service.delete_own_object(user_id, restore).await;
+        assert!(matches!(
+            res,
+            Err(ApiError::ObjectError(ObjectError::NameConflict))
+        ));
+
+        // Папка в корзине: восстановление в корень, где имя свободно
+        delete(&service, user_id, &parent, false).await.unwrap();
+        let restore = DeleteObjectDto {
+            file_id: old.id,
+            delete_mark: false,
+            hard_delete: false,
+        };
+        let restored = service.delete_own_object(user_id, restore).await.unwrap();
+        assert_eq!(restored.parent_id, None);
+        assert_eq!(
+            tree(&pool, user_id).await,
+            entries(&[("d", "dir"), ("parent", "dir"), ("parent/d", "dir")])
+        );
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn failed_registration_removes_tmp_file(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let user_id = create_user(&service, "quota@flaxum.test").await;
+        sqlx::query(r#"UPDATE "User" SET storage_quota = 1 WHERE id = $1"#)
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut obj_constructor = ObjectCreateModel::default();
+        obj_constructor.id = Id::new_v4();
+        obj_constructor.owner_id = user_id;
+        obj_constructor.creator_id = user_id;
+        obj_constructor.name = "a.txt".to_string();
+        obj_constructor.size = Some(5);
+        obj_constructor.type_ = ObjectType::File;
+        obj_constructor.hash_sha256 = Some(hex::encode(Sha256::digest(b"hello")));
+        let tmp_path = format!("tmp/{}.{}", user_id, obj_constructor.id);
+        fs::write(&tmp_path, "hello").await.unwrap();
+
+        let res = service.store_uploaded_file(obj_constructor).await;
+        assert!(matches!(
+            res,
+            Err(ApiError::UserError(UserError::StorageQuotaExceeded))
+        ));
+        assert!(!fs::try_exists(&tmp_path).await.unwrap());
+    }
+
+    /// Итоговые права `user_id` на объект
+    async fn access(pool: &PgPool, service: &ObjectService, user_id: Id, obj: &Object) -> bool {
+        let mut conn = pool.acquire().await.unwrap();
+        service
+            .access_service
+            .effective_access_tx(&mut conn, user_id, obj)
+            .await
+            .unwrap()
+            .is_some_and(|access| access.allows(Permission::Read))
+    }
+
+    /// Имена объектов общего списка `user_id` в папке `parent_id`
+    async fn shared_names(
+        service: &ObjectService,
+        user_id: Id,
+        parent_id: Option<Id>,
+    ) -> Vec<String> {
+        let user = service.user_repo.select_by_id(user_id).await.unwrap();
+        let list = service
+            .get_shared_list(
+                Pagination {
+                    limit: 50,
+                    offset: 0,
+                },
+                user,
+                GetObjectListDto { parent_id },
+            )
+            .await
+            .unwrap();
+        let mut names: Vec<String> = serde_json::to_value(list).unwrap()["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn read() -> UxOAccess {
+        UxOAccess {
+            can_read: true,
+            can_edit: false,
+            can_delete: false,
+        }
+    }
+
+    fn deny() -> UxOAccess {
+        UxOAccess {
+            can_read: false,
+            can_edit: false,
+            can_delete: false,
+        }
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn access_is_inherited_from_nearest_folder(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let owner = create_user(&service, "owner@flaxum.test").await;
+        let guest = create_user(&service, "guest@flaxum.test").await;
+        let shared = folder(&service, owner, None, "shared").await;
+        let nested = folder(&service, owner, Some(shared.id), "nested").await;
+        let file = folder(&service, owner, Some(nested.id), "file").await;
+        let denied = folder(&service, owner, Some(shared.id), "denied").await;
+        let hidden = folder(&service, owner, Some(denied.id), "hidden").await;
+        let granted = folder(&service, owner, Some(hidden.id), "granted").await;
+        share(&pool, &service, guest, &shared, read()).await;
+        share(&pool, &service, guest, &denied, deny()).await;
+        share(&pool, &service, guest, &granted, read()).await;
+
+        // Файл в подпапке наследует чтение от общей папки
+        assert!(access(&pool, &service, guest, &file).await);
+        // Запрет на дочерней папке перекрывает доступ родителя
+        assert!(!access(&pool, &service, guest, &denied).await);
+        assert!(!access(&pool, &service, guest, &hidden).await);
+        // Доступ на объекте внутри запрещенной папки перекрывает запрет
+        assert!(access(&pool, &service, guest, &granted).await);
+        // Новый объект в общей папке наследует доступ сразу
+        let later = folder(&service, owner, Some(nested.id), "later").await;
+        assert!(access(&pool, &service, guest, &later).await);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn shared_list_shows_inherited_objects_once(pool: PgPool) {
+        let service = service(pool.clone()).await;
+        let owner = create_user(&service, "owner@flaxum.test").await;
+        let guest = create_user(&service, "guest@flaxum.test").await;
+        let shared = folder(&service, owner, None, "shared").await;
+        let child = folder(&service, owner, Some(shared.id), "child").await;
+        let denied = folder(&service, owner, Some(shared.id), "denied").await;
+        let granted = folder(&service, owner, Some(denied.id), "granted").await;
+        share(&pool, &service, guest, &shared, read()).await;
+        share(&pool, &service, guest, &child, read()).await;
+        share(&pool, &service, guest, &denied, deny()).await;
+        share(&pool, &service, guest, &granted, read()).await;
+
+        // Дочерняя папка со своей записью видна только в общей папке,
+        // объект внутри запрещенной папки - в корне
+        assert_eq!(
+            shared_names(&service, guest, None).await,
+            vec!["granted", "shared"]
+        );
+        assert_eq!(
+            shared_names(&service, guest, Some(shared.id)).await,
+            vec!["child"]
+        );
+    }
+}
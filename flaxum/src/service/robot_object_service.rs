@@ -1,11 +1,11 @@
 use crate::config::database::Database;
 use crate::repository::robot_object_repository::{RobotObjectRepository, RobotObjectRepositoryTrait};
-use crate::repository::s3_repository::{S3Repository, S3RepositoryTrait};
+use crate::repository::storage_repository::{StorageRepository, StorageRepositoryTrait};
 
 use std::sync::Arc;
 
 use amqprs::connection::Connection as RMQConn;
-use aws_sdk_s3::Client as S3Client;
+use file_worker::storage::StorageBackend;
 
 
 
@@ -14,11 +14,17 @@ pub struct RobotObjectService {
     db_conn: Arc<Database>,
     rmq_conn: Arc<RMQConn>,
     robot_object_repo: RobotObjectRepository,
-    s3_repo: S3Repository,
+    storage_repo: StorageRepository,
 }
 
+/// Файлы роботов хранятся в `RobotObject` и не входят в квоту
+/// и `storage_size` создателя робота
 pub trait RobotObjectServiceTrait{
-    fn new(db_conn: &Arc<Database>, s3_conn: &Arc<S3Client>, rmq_conn: &Arc<RMQConn>) -> Self;
+    fn new(
+        db_conn: &Arc<Database>,
+        storage: &Arc<dyn StorageBackend>,
+        rmq_conn: &Arc<RMQConn>,
+    ) -> Self;
 
     async fn upload_object();
     async fn download_object();
@@ -28,12 +34,16 @@ pub trait RobotObjectServiceTrait{
 
 
 impl RobotObjectServiceTrait for RobotObjectService {
-    fn new(db_conn: &Arc<Database>, s3_conn: &Arc<S3Client>, rmq_conn: &Arc<RMQConn>) -> Self {
+    fn new(
+        db_conn: &Arc<Database>,
+        storage: &Arc<dyn StorageBackend>,
+        rmq_conn: &Arc<RMQConn>,
+    ) -> Self {
         Self {
             db_conn: Arc::clone(db_conn),
             rmq_conn: Arc::clone(rmq_conn),
             robot_object_repo: RobotObjectRepository::new(db_conn),
-            s3_repo: S3Repository::new(s3_conn),
+            storage_repo: StorageRepository::new(storage),
         }
     }
     async fn upload_object(){}
@@ -0,0 +1,230 @@
+use std::io;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::database::Database;
+use crate::config::SCRUB_REVERIFY_DAYS;
+use crate::entity::pagination::Pagination;
+use crate::entity::storage_verification::{
+    ScrubCandidate, ScrubReport, StorageVerificationCreateModel, StorageVerificationReport,
+    VerificationStatus,
+};
+use crate::error::api_error::ApiError;
+use crate::repository::storage_repository::{StorageRepository, StorageRepositoryTrait};
+use crate::repository::storage_verification_repository::{
+    StorageVerificationRepository, StorageVerificationRepositoryTrait,
+};
+use crate::utils::blob_stream;
+
+use chrono::Utc;
+use file_worker::cipher::Keyring;
+use file_worker::storage::{StorageBackend, StorageError};
+use futures::StreamExt;
+use sha2::{digest::Digest, Sha256};
+
+/// Сколько единиц содержимого проверяется за один проход фоновой задачи
+pub const SCRUB_BATCH_SIZE: i64 = 100;
+
+/// Проверка содержимого в хранилище: расшифровка с проверкой тегов и сверка `hash_sha256`
+#[derive(Clone)]
+pub struct ScrubService {
+    verification_repo: StorageVerificationRepository,
+    storage_repo: StorageRepository,
+    keyring: Arc<Keyring>,
+    rate_bytes_per_sec: u64,
+}
+
+/// Почему проверка не дала хэш содержимого
+struct ScrubFailure {
+    status: VerificationStatus,
+    error: String,
+}
+
+impl ScrubFailure {
+    fn new(status: VerificationStatus, error: impl ToString) -> Self {
+        Self {
+            status,
+            error: error.to_string(),
+        }
+    }
+}
+
+impl ScrubService {
+    pub fn new(
+        db_conn: &Arc<Database>,
+        storage: &Arc<dyn StorageBackend>,
+        keyring: &Arc<Keyring>,
+        rate_bytes_per_sec: u64,
+    ) -> Self {
+        Self {
+            verification_repo: StorageVerificationRepository::new(db_conn),
+            storage_repo: StorageRepository::new(storage),
+            keyring: Arc::clone(keyring),
+            rate_bytes_per_sec,
+        }
+    }
+
+    /// Проверка до `limit` еще не проверенных или давно проверенных блобов и файлов роботов.
+    /// Чтение из хранилища ограничено `rate_bytes_per_sec` на весь проход
+    pub async fn run_batch(&self, limit: i64) -> Result<ScrubReport, ApiError> {
+        let verified_before =
+            (Utc::now() - chrono::Duration::days(SCRUB_REVERIFY_DAYS)).naive_utc();
+        let candidates = self
+            .verification_repo
+            .select_candidates(verified_before, limit)
+            .await?;
+
+        let mut report = ScrubReport::default();
+        let mut limiter = RateLimiter::new(self.rate_bytes_per_sec);
+        for candidate in candidates {
+            let result = self.verify(&candidate, &mut limiter).await;
+            if result.status != VerificationStatus::Ok {
+                tracing::warn!(
+                    "Integrity check of {:?} {} failed: {:?} {}",
+                    candidate.target_type,
+                    candidate.id,
+                    result.status,
+                    result.error.as_deref().unwrap_or("hash mismatch")
+                );
+            }
+            report.add(result.status);
+            self.verification_repo.upsert(result).await?;
+        }
+        Ok(report)
+    }
+
+    pub async fn get_report(
+        &self,
+        pagination: Pagination,
+        include_ok: bool,
+    ) -> Result<StorageVerificationReport, ApiError> {
+        let (limit, offset) = (pagination.limit, pagination.offset);
+        let summary = self.verification_repo.select_summary().await?;
+        let (items, total) = self
+            .verification_repo
+            .select_list(pagination, include_ok)
+            .await?;
+        Ok(StorageVerificationReport {
+            summary,
+            items,
+            limit,
+            offset,
+            total,
+        })
+    }
+
+    async fn verify(
+        &self,
+        candidate: &ScrubCandidate,
+        limiter: &mut RateLimiter,
+    ) -> StorageVerificationCreateModel {
+        let expected_sha256 = candidate
+            .hash_sha256
+            .as_ref()
+            .map(|hash| hash.trim().to_string());
+        let (status, actual_sha256, error) = match self.hash_content(candidate, limiter).await {
+            Ok(actual) => {
+                let status = match &expected_sha256 {
+                    Some(expected) if *expected != actual => VerificationStatus::Mismatch,
+                    _ => VerificationStatus::Ok,
+                };
+                (status, Some(actual), None)
+            }
+            Err(failure) => (failure.status, None, Some(failure.error)),
+        };
+        StorageVerificationCreateModel {
+            target_type: candidate.target_type,
+            target_id: candidate.id,
+            storage_key: candidate.storage_key(),
+            status,
+            expected_sha256,
+            actual_sha256,
+            error,
+        }
+    }
+
+    /// SHA256 расшифрованного содержимого. Ошибка проверки сегмента - `mismatch`
+    async fn hash_content(
+        &self,
+        candidate: &ScrubCandidate,
+        limiter: &mut RateLimiter,
+    ) -> Result<String, ScrubFailure> {
+        let decode_key = candidate
+            .decode_key
+            .as_deref()
+            .ok_or_else(|| ScrubFailure::new(VerificationStatus::Error, "decode key is missing"))?;
+        let data_key = self
+            .keyring
+            .unwrap(decode_key)
+            .map_err(|err| ScrubFailure::new(VerificationStatus::Error, err))?;
+
+        let mut body = match self
+            .storage_repo
+            .get_key_stream(&candidate.storage_key())
+            .await
+        {
+            Ok(body) => body,
+            Err(err @ StorageError::NotFound(_)) => {
+                return Err(ScrubFailure::new(VerificationStatus::Missing, err))
+            }
+            Err(err) => return Err(ScrubFailure::new(VerificationStatus::Error, err)),
+        };
+        let head = blob_stream::read_head(&mut body)
+            .await
+            .map_err(|err| ScrubFailure::new(VerificationStatus::Error, err))?;
+        let (prefix, decryptor) = blob_stream::open_full(
+            head,
+            &data_key,
+            candidate.size as u64,
+            candidate.format_version,
+        )
+        .map_err(|err| ScrubFailure::new(VerificationStatus::Mismatch, err))?;
+
+        let mut stream = pin!(blob_stream::decrypt_stream(
+            candidate.id,
+            body,
+            prefix,
+            decryptor
+        ));
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|err| match err.kind() {
+                io::ErrorKind::InvalidData => ScrubFailure::new(VerificationStatus::Mismatch, err),
+                _ => ScrubFailure::new(VerificationStatus::Error, err),
+            })?;
+            hasher.update(&chunk);
+            limiter.consume(chunk.len()).await;
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+/// Ограничение средней скорости чтения; 0 - без ограничения
+struct RateLimiter {
+    bytes_per_sec: u64,
+    started: Instant,
+    bytes: u64,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    async fn consume(&mut self, bytes: usize) {
+        if self.bytes_per_sec == 0 {
+            return;
+        }
+        self.bytes += bytes as u64;
+        let expected = Duration::from_secs_f64(self.bytes as f64 / self.bytes_per_sec as f64);
+        let elapsed = self.started.elapsed();
+        if expected > elapsed {
+            tokio::time::sleep(expected - elapsed).await;
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use std::sync::Arc;
+
+use crate::config::database::Database;
+use crate::dto::object::GetObjectListDto;
+use crate::dto::share_link::{CreateShareLinkDto, GetShareLinkListDto};
+use crate::entity::object::{Object, ObjectType, Permission, PublicObject, PublicObjectsPaginated};
+use crate::entity::pagination::Pagination;
+use crate::entity::share_link::{
+    PublicShareLink, ShareLink, ShareLinkCreateModel, ShareLinksPaginated,
+};
+use crate::error::api_error::ApiError;
+use crate::error::backend_error::BackendError;
+use crate::error::object_error::ObjectError;
+use crate::error::share_error::ShareError;
+use crate::repository::object_repository::{ObjectRepository, ObjectRepositoryTrait};
+use crate::repository::share_link_repository::{ShareLinkRepository, ShareLinkRepositoryTrait};
+use crate::response::file_response::FileResponse;
+use crate::scalar::Id;
+use crate::service::access_service::AccessService;
+use crate::service::object_service::ObjectService;
+use crate::utils::crypto;
+
+use axum::extract::Multipart;
+use axum_extra::headers::{IfRange, Range};
+use chrono::Utc;
+use file_worker::cipher::Keyring;
+use file_worker::storage::StorageBackend;
+use rand::Rng;
+
+const TOKEN_LENGTH_BYTES: usize = 24;
+
+/// Публичные ссылки: управление владельцем и анонимный доступ по токену
+#[derive(Clone)]
+pub struct ShareLinkService {
+    share_link_repo: ShareLinkRepository,
+    object_repo: ObjectRepository,
+    object_service: ObjectService,
+    access_service: AccessService,
+}
+
+impl ShareLinkService {
+    pub fn new(
+        db_conn: &Arc<Database>,
+        storage: &Arc<dyn StorageBackend>,
+        keyring: &Arc<Keyring>,
+    ) -> Self {
+        Self {
+            share_link_repo: ShareLinkRepository::new(db_conn),
+            object_repo: ObjectRepository::new(db_conn),
+            object_service: ObjectService::new(db_conn, storage, keyring),
+            access_service: AccessService::new(db_conn),
+        }
+    }
+
+    fn generate_token() -> String {
+        let mut rng = rand::rng();
+        let token: Vec<u8> = (0..TOKEN_LENGTH_BYTES).map(|_| rng.random()).collect();
+        hex::encode(token)
+    }
+
+    /// Создать ссылку может только владелец объекта
+    pub async fn create_link(
+        &self,
+        user_id: Id,
+        object_id: Id,
+        dto: CreateShareLinkDto,
+    ) -> Result<ShareLink, ApiError> {
+        let obj = self
+            .access_service
+            .authorize(user_id, object_id, Permission::Read)
+            .await?;
+        if obj.owner_id != user_id {
+            return Err(ObjectError::AccessDenied)?;
+        }
+        if dto
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now().naive_utc())
+        {
+            return Err(ShareError::InvalidExpiry)?;
+        }
+        let password_hash = match dto.password {
+            Some(password) => Some(
+                crypto::hash(password)
+                    .await
+                    .map_err(|e| BackendError::InternalError(e.to_string()))?,
+            ),
+            None => None,
+        };
+        let create_model = ShareLinkCreateModel {
+            id: Id::new_v4(),
+            object_id,
+            owner_id: user_id,
+            token: Self::generate_token(),
+            password_hash,
+            expires_at: dto.expires_at,
+            max_downloads: dto.max_downloads,
+            read_only: dto.read_only,
+        };
+        let link = self.share_link_repo.insert(create_model).await?;
+        Ok(link)
+    }
+
+    pub async fn get_own_list(
+        &self,
+        user_id: Id,
+        pagination: Pagination,
+        dto: GetShareLinkListDto,
+    ) -> Result<ShareLinksPaginated, ApiError> {
+        let res = self
+            .share_link_repo
+            .select_own_list(pagination, user_id, dto.object_id)
+            .await?;
+        Ok(res)
+    }
+
+    pub async fn revoke_link(&self, user_id: Id, link_id: Id) -> Result<(), ApiError> {
+        self.share_link_repo
+            .delete(link_id, user_id)
+            .await?
+            .ok_or(ShareError::LinkNotFound)?;
+        Ok(())
+    }
+
+    /// Проверка токена, срока действия и пароля; возвращает ссылку и корневой объект
+    async fn resolve(
+        &self,
+        token: &str,
+        password: Option<String>,
+    ) -> Result<(ShareLink, Object), ApiError> {
+        let link = self
+            .share_link_repo
+            .select_by_token(token)
+            .await?
+            .ok_or(ShareError::LinkNotFound)?;
+        if link.is_expired(Utc::now().naive_utc()) {
+            return Err(ShareError::LinkExpired)?;
+        }
+        if let Some(password_hash) = &link.password_hash {
+            let password = password.ok_or(ShareError::PasswordRequired)?;
+            let is_valid = crypto::verify(password, password_hash.clone())
+                .await
+                .map_err(|e| BackendError::InternalError(e.to_string()))?;
+            if !is_valid {
+                return Err(ShareError::InvalidPassword)?;
+            }
+        }
+        let obj = match self.object_repo.select_by_id(link.object_id).await {
+            Ok(obj) if !obj.in_trash => obj,
+            Ok(_) | Err(sqlx::Error::RowNotFound) => return Err(ShareError::LinkNotFound)?,
+            Err(e) => return Err(e)?,
+        };
+        Ok((link, obj))
+    }
+
+    /// Объект внутри общей папки, по умолчанию - сам корень ссылки
+    async fn resolve_object(
+        &self,
+        root: Object,
+        object_id: Option<Id>,
+    ) -> Result<Object, ApiError> {
+        let object_id = match object_id {
+            Some(object_id) if object_id != root.id => object_id,
+            _ => return Ok(root),
+        };
+        if !self.object_repo.is_in_subtree(object_id, root.id).await? {
+            return Err(ObjectError::ObjectNotFound)?;
+        }
+        let obj = self.object_repo.select_by_id(object_id).await?;
+        Ok(obj)
+    }
+
+    pub async fn get_public_info(
+        &self,
+        token: &str,
+        password: Option<String>,
+    ) -> Result<PublicShareLink, ApiError> {
+        let (link, obj) = self.resolve(token, password).await?;
+        Ok(PublicShareLink {
+            read_only: link.read_only,
+            expires_at: link.expires_at,
+            max_downloads: link.max_downloads,
+            download_count: link.download_count,
+            object: PublicObject::from(obj),
+        })
+    }
+
+    pub async fn get_public_list(
+        &self,
+        token: &str,
+        password: Option<String>,
+        pagination: Pagination,
+        dto: GetObjectListDto,
+    ) -> Result<PublicObjectsPaginated, ApiError> {
+        let (_, root) = self.resolve(token, password).await?;
+        let parent = self.resolve_object(root, dto.parent_id).await?;
+        if !matches!(parent.type_, ObjectType::Dir) {
+            return Err(ObjectError::ParentNotAFolder)?;
+        }
+        let res = self
+            .object_repo
+            .select_children(pagination, parent.id)
+            .await?;
+        Ok(PublicObjectsPaginated::from(res))
+    }
+
+    /// Скачивание по ссылке. Лимит расходует запрос, отдающий файл с начала:
+    /// целиком или диапазоном с нулевого байта. Докачка продолжения лимит не расходует
+    pub async fn download_public_file(
+        &self,
+        token: &str,
+        password: Option<String>,
+        file_id: Option<Id>,
+        range: Option<Range>,
+        if_range: Option<IfRange>,
+    ) -> Result<FileResponse, ApiError> {
+        let (link, root) = self.resolve(token, password).await?;
+        let obj = self.resolve_object(root, file_id).await?;
+        if !matches!(obj.type_, ObjectType::File) {
+            return Err(ObjectError::NotAFile)?;
+        }
+        let res = self
+            .object_service
+            .stream_file(obj, range, if_range)
+            .await?;
+        if res.starts_at_beginning() {
+            self.share_link_repo
+                .increment_download_count(link.id)
+                .await?
+                .ok_or(ShareError::DownloadLimitReached)?;
+        }
+        Ok(res)
+    }
+
+    /// Загрузка в общую папку по ссылке без флага `read_only`;
+    /// файл принадлежит владельцу ссылки
+    pub async fn upload_public_file(
+        &self,
+        token: &str,
+        password: Option<String>,
+        parent_id: Option<Id>,
+        multipart: Multipart,
+        content_length: Option<u64>,
+    ) -> Result<PublicObject, ApiError> {
+        let (link, root) = self.resolve(token, password).await?;
+        if link.read_only {
+            return Err(ShareError::ReadOnly)?;
+        }
+        let parent = self.resolve_object(root, parent_id).await?;
+        if !matches!(parent.type_, ObjectType::Dir) {
+            return Err(ObjectError::ParentNotAFolder)?;
+        }
+        let obj = self
+            .object_service
+            .receive_file(multipart, Some(parent.id), link.owner_id, content_length)
+            .await?;
+        Ok(PublicObject::from(obj))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::database::DatabaseTrait;
+    use crate::dto::user::CreateUserDto;
+    use crate::entity::object::ObjectCreateModel;
+    use crate::repository::user_repository::{UserRepository, UserRepositoryTrait};
+    use axum::http::HeaderValue;
+    use axum_extra::headers::Header;
+    use bytes::Bytes;
+    use file_worker::cipher::{BlobCipher, BlobHeader};
+    use file_worker::storage::LocalStorage;
+    use sha2::{Digest, Sha256};
+    use sqlx::PgPool;
+
+    const CONTENT: &[u8] = b"hello, world";
+
+    /// Сервис и загруженный в хранилище файл с содержимым `CONTENT`
+    async fn stored_file(pool: PgPool) -> (ShareLinkService, Object) {
+        let db_conn = Arc::new(Database::from_pool(pool.clone()));
+        let root = std::env::temp_dir().join(format!("flaxum-test-{}", Id::new_v4()));
+        let storage: Arc<dyn StorageBackend> = Arc::new(LocalStorage::init(root).await.unwrap());
+        let keyring = Arc::new(Keyring::from_hex(&"11".repeat(32), None).unwrap());
+        let service = ShareLinkService::new(&db_conn, &storage, &keyring);
+
+        let user_repo = UserRepository::new(&db_conn);
+        user_repo
+            .create_user(CreateUserDto {
+                email: "link@flaxum.test".to_string(),
+                password: "password".to_string(),
+            })
+            .await
+            .unwrap();
+        let user_id = user_repo
+            .select_by_email("link@flaxum.test".to_string())
+            .await
+            .unwrap()
+            .id;
+
+        let obj_constructor = ObjectCreateModel {
+            id: Id::new_v4(),
+            owner_id: user_id,
+            creator_id: user_id,
+            name: "a.txt".to_string(),
+            size: Some(CONTENT.len() as i64),
+            type_: ObjectType::File,
+            hash_sha256: Some(hex::encode(Sha256::digest(CONTENT))),
+            ..Default::default()
+        };
+        tokio::fs::create_dir_all("tmp").await.unwrap();
+        let tmp_path = format!("tmp/{}.{}", user_id, obj_constructor.id);
+        tokio::fs::write(&tmp_path, CONTENT).await.unwrap();
+        let obj = service
+            .object_service
+            .store_uploaded_file(obj_constructor)
+            .await
+            .unwrap();
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+
+        // То, что сделал бы file_worker: шифрование сегментами и статус `stored`
+        let data_key = keyring.unwrap(obj.decode_key.as_ref().unwrap()).unwrap();
+        let header = BlobHeader::generate();
+        let mut blob = header.to_bytes().to_vec();
+        let blob_cipher = BlobCipher::new(&data_key, header, CONTENT.len() as u64);
+        blob.extend(blob_cipher.encrypt_segment(0, CONTENT).unwrap());
+        storage
+            .put(&obj.storage_key(), Bytes::from(blob))
+            .await
+            .unwrap();
+        for table in ["Object", "Blob"] {
+            sqlx::query(&format!(
+                r#"UPDATE "{table}" SET upload_status = 'stored' WHERE id = $1"#
+            ))
+            .bind(obj.id)
+            .execute(db_conn.get_pool())
+            .await
+            .unwrap();
+        }
+        let obj = service.object_repo.select_by_id(obj.id).await.unwrap();
+        (service, obj)
+    }
+
+    async fn download(
+        service: &ShareLinkService,
+        link: &ShareLink,
+        range: Option<&str>,
+    ) -> Result<FileResponse, ApiError> {
+        let range = range.map(|range| {
+            let value = HeaderValue::from_str(range).unwrap();
+            Range::decode(&mut std::iter::once(&value)).unwrap()
+        });
+        service
+            .download_public_file(&link.token, None, None, range, None)
+            .await
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn only_downloads_from_start_count_against_limit(pool: PgPool) {
+        let (service, obj) = stored_file(pool).await;
+        let link = service
+            .create_link(
+                obj.owner_id,
+                obj.id,
+                CreateShareLinkDto {
+                    expires_at: None,
+                    password: None,
+                    max_downloads: Some(2),
+                    read_only: true,
+                },
+            )
+            .await
+            .unwrap();
+        let download_count = || async {
+            service
+                .get_public_info(&link.token, None)
+                .await
+                .unwrap()
+                .download_count
+        };
+
+        assert!(download(&service, &link, None).await.is_ok());
+        assert_eq!(download_count().await, 1);
+        // Продолжения и неудовлетворимые диапазоны лимит не расходуют
+        for range in ["bytes=5-", "bytes=6-9", "bytes=-3", "bytes=100-"] {
+            assert!(download(&service, &link, Some(range)).await.is_ok());
+        }
+        assert_eq!(download_count().await, 1);
+        assert!(download(&service, &link, Some("bytes=0-4")).await.is_ok());
+        assert_eq!(download_count().await, 2);
+
+        assert!(matches!(
+            download(&service, &link, None).await,
+            Err(ApiError::ShareError(ShareError::DownloadLimitReached))
+        ));
+        assert!(download(&service, &link, Some("bytes=5-")).await.is_ok());
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::config::database::{Database, DatabaseTrait};
+use crate::config::{STORAGE_GC_GRACE_HOURS, TMP_DIR};
+use crate::entity::object::UploadStatus;
+use crate::entity::storage_gc::{
+    OrphanStorageObject, StaleTempFile, StaleUpload, StorageGcReport, StoredContent,
+};
+use crate::entity::storage_verification::ScrubTarget;
+use crate::error::api_error::ApiError;
+use crate::repository::blob_repository::{BlobRepository, BlobRepositoryTrait};
+use crate::repository::robot_object_repository::{
+    RobotObjectRepository, RobotObjectRepositoryTrait,
+};
+use crate::repository::storage_gc_repository::{StorageGcRepository, StorageGcRepositoryTrait};
+use crate::repository::storage_repository::{StorageRepository, StorageRepositoryTrait};
+use crate::scalar::Id;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use file_worker::storage::{StorageBackend, StoredObject};
+use tokio::fs;
+
+/// Сколько id проверяется в БД одним запросом
+const GC_BATCH_SIZE: usize = 1000;
+
+/// Сверка временных файлов и хранилища с БД: временные файлы, которые никто не дочитает,
+/// объекты хранилища без строки в БД, загруженное по данным БД, но отсутствующее содержимое
+/// и брошенные незавершенные загрузки
+#[derive(Clone)]
+pub struct StorageGcService {
+    db_conn: Arc<Database>,
+    gc_repo: StorageGcRepository,
+    blob_repo: BlobRepository,
+    robot_object_repo: RobotObjectRepository,
+    storage_repo: StorageRepository,
+}
+
+impl StorageGcService {
+    pub fn new(db_conn: &Arc<Database>, storage: &Arc<dyn StorageBackend>) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+            gc_repo: StorageGcRepository::new(db_conn),
+            blob_repo: BlobRepository::new(db_conn),
+            robot_object_repo: RobotObjectRepository::new(db_conn),
+            storage_repo: StorageRepository::new(storage),
+        }
+    }
+
+    /// Отчет о найденном; с `cleanup` временные файлы и лишние объекты удаляются,
+    /// загрузки отменяются, а отсутствующее содержимое получает статус `failed`
+    pub async fn run(&self, cleanup: bool) -> Result<StorageGcReport, ApiError> {
+        let stale_before = Utc::now() - Duration::hours(STORAGE_GC_GRACE_HOURS);
+        let mut report = StorageGcReport {
+            cleanup,
+            stale_before: stale_before.naive_utc(),
+            temp_files: Vec::new(),
+            orphan_objects: Vec::new(),
+            missing_content: Vec::new(),
+            stale_uploads: Vec::new(),
+            reclaimed_size: 0,
+            failed: 0,
+        };
+
+        self.collect_temp_files(stale_before, &mut report).await?;
+        let objects = self.storage_repo.list_all().await?;
+        self.collect_orphan_objects(&objects, stale_before, &mut report)
+            .await?;
+        self.collect_missing_content(&objects, stale_before, &mut report)
+            .await?;
+        self.collect_stale_uploads(stale_before, &mut report)
+            .await?;
+        Ok(report)
+    }
+
+    async fn collect_temp_files(
+        &self,
+        stale_before: DateTime<Utc>,
+        report: &mut StorageGcReport,
+    ) -> Result<(), ApiError> {
+        let mut entries = match fs::read_dir(TMP_DIR).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err)?,
+        };
+        let mut candidates = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let modified = metadata.modified().ok();
+            if !metadata.is_file() || Self::is_recent(modified, stale_before) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            candidates.push((name, metadata.len(), modified));
+        }
+
+        let ids: Vec<Id> = candidates
+            .iter()
+            .filter_map(|(name, _, _)| Self::tmp_file_id(name))
+            .collect();
+        let mut in_use = HashSet::new();
+        for chunk in ids.chunks(GC_BATCH_SIZE) {
+            in_use.extend(self.gc_repo.select_tmp_in_use(chunk).await?);
+        }
+
+        for (name, size, modified) in candidates {
+            if Self::tmp_file_id(&name).is_some_and(|id| in_use.contains(&id)) {
+                continue;
+            }
+            if report.cleanup {
+                match fs::remove_file(Path::new(TMP_DIR).join(&name)).await {
+                    Ok(()) => report.reclaimed_size += size as i64,
+                    Err(err) => {
+                        tracing::error!("Failed to remove temp file {}: {}", name, err);
+                        report.failed += 1;
+                    }
+                }
+            }
+            report.temp_files.push(StaleTempFile {
+                name,
+                size: size as i64,
+                modified_at: modified.map(Self::naive),
+            });
+        }
+        Ok(())
+    }
+
+    async fn collect_orphan_objects(
+        &self,
+        objects: &[StoredObject],
+        stale_before: DateTime<Utc>,
+        report: &mut StorageGcReport,
+    ) -> Result<(), ApiError> {
+        let candidates: Vec<(&StoredObject, Id)> = objects
+            .iter()
+            .filter(|object| !Self::is_recent(object.last_modified, stale_before))
+            .filter_map(|object| Some((object, Self::storage_key_id(&object.key)?)))
+            .collect();
+        let ids: Vec<Id> = candidates.iter().map(|(_, id)| *id).collect();
+        let mut referenced = HashSet::new();
+        for chunk in ids.chunks(GC_BATCH_SIZE) {
+            referenced.extend(self.gc_repo.select_referenced(chunk).await?);
+        }
+
+        for (object, id) in candidates {
+            if referenced.contains(&id) {
+                continue;
+            }
+            if report.cleanup {
+                match self.storage_repo.delete_key(&object.key).await {
+                    Ok(()) => report.reclaimed_size += object.size as i64,
+                    Err(err) => {
+                        tracing::error!("Failed to delete orphan {}: {}", object.key, err);
+                        report.failed += 1;
+                    }
+                }
+            }
+            report.orphan_objects.push(OrphanStorageObject {
+                key: object.key.clone(),
+                size: object.size as i64,
+                last_modified: object.last_modified.map(Self::naive),
+            });
+        }
+        Ok(())
+    }
+
+    async fn collect_missing_content(
+        &self,
+        objects: &[StoredObject],
+        stale_before: DateTime<Utc>,
+        report: &mut StorageGcReport,
+    ) -> Result<(), ApiError> {
+        let keys: HashSet<&str> = objects.iter().map(|object| object.key.as_str()).collect();
+        let mut after = None;
+        loop {
+            let batch = self
+                .gc_repo
+                .select_stored(stale_before.naive_utc(), after, GC_BATCH_SIZE as i64)
+                .await?;
+            let Some(last) = batch.last() else {
+                break;
+            };
+            after = Some(last.id);
+
+            for content in batch {
+                let storage_key = content.storage_key();
+                // Список хранилища мог устареть за время обхода, поэтому ключ проверяется еще раз
+                if keys.contains(storage_key.as_str())
+                    || self.storage_repo.exists(&storage_key).await?
+                {
+                    continue;
+                }
+                if report.cleanup {
+                    if let Err(err) = self.mark_failed(&content).await {
+                        tracing::error!("Failed to mark {} as failed: {}", storage_key, err);
+                        report.failed += 1;
+                    }
+                }
+                report.missing_content.push(content);
+            }
+        }
+        Ok(())
+    }
+
+    async fn collect_stale_uploads(
+        &self,
+        stale_before: DateTime<Utc>,
+        report: &mut StorageGcReport,
+    ) -> Result<(), ApiError> {
+        for upload in self.storage_repo.list_uploads().await? {
+            if Self::is_recent(upload.initiated, stale_before) {
+                continue;
+            }
+            if report.cleanup {
+                if let Err(err) = self.storage_repo.abort_upload(&upload).await {
+                    tracing::error!("Failed to abort upload of {}: {}", upload.key, err);
+                    report.failed += 1;
+                }
+            }
+            report.stale_uploads.push(StaleUpload {
+                initiated_at: upload.initiated.map(Self::naive),
+                key: upload.key,
+                upload_id: upload.upload_id,
+            });
+        }
+        Ok(())
+    }
+
+    async fn mark_failed(&self, content: &StoredContent) -> Result<(), ApiError> {
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        match content.target_type {
+            ScrubTarget::Blob => {
+                self.blob_repo
+                    .set_upload_status(&mut tx, content.id, UploadStatus::Failed)
+                    .await?
+            }
+            ScrubTarget::RobotObject => {
+                self.robot_object_repo
+                    .set_upload_status(&mut tx, content.id, UploadStatus::Failed)
+                    .await?
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Время изменения неизвестно - файл считается старым
+    fn is_recent(time: Option<SystemTime>, stale_before: DateTime<Utc>) -> bool {
+        time.is_some_and(|time| DateTime::<Utc>::from(time) >= stale_before)
+    }
+
+    fn naive(time: SystemTime) -> NaiveDateTime {
+        DateTime::<Utc>::from(time).naive_utc()
+    }
+
+    /// Id из имени временного файла `{owner_id}.{id}`; файлы с другими именами
+    /// (например `.enc` прежних версий file_worker) id не имеют и всегда считаются лишними
+    fn tmp_file_id(name: &str) -> Option<Id> {
+        let (owner_id, id) = name.split_once('.')?;
+        Id::parse_str(owner_id).ok()?;
+        Id::parse_str(id).ok()
+    }
+
+    /// Id блоба или файла робота из ключа `{owner_id}/{id}`
+    fn storage_key_id(key: &str) -> Option<Id> {
+        let (owner_id, id) = key.split_once('/')?;
+        Id::parse_str(owner_id).ok()?;
+        Id::parse_str(id).ok()
+    }
+}
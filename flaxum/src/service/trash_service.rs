@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use crate::config::database::{Database, DatabaseTrait};
+use crate::entity::trash::TrashPurgeReport;
+use crate::error::api_error::ApiError;
+use crate::repository::blob_repository::{BlobRepository, BlobRepositoryTrait};
+use crate::repository::object_repository::{ObjectRepository, ObjectRepositoryTrait};
+use crate::repository::storage_repository::{StorageRepository, StorageRepositoryTrait};
+use crate::repository::user_repository::{UserRepository, UserRepositoryTrait};
+use crate::scalar::Id;
+
+use chrono::{Duration, Utc};
+use file_worker::storage::StorageBackend;
+
+/// Сколько корней корзины обрабатывается за один проход очистки
+const PURGE_BATCH_SIZE: i64 = 500;
+
+/// Очистка корзины от объектов старше срока хранения
+#[derive(Clone)]
+pub struct TrashService {
+    db_conn: Arc<Database>,
+    object_repo: ObjectRepository,
+    user_repo: UserRepository,
+    blob_repo: BlobRepository,
+    storage_repo: StorageRepository,
+    retention_days: i64,
+}
+
+impl TrashService {
+    pub fn new(
+        db_conn: &Arc<Database>,
+        storage: &Arc<dyn StorageBackend>,
+        retention_days: i64,
+    ) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+            object_repo: ObjectRepository::new(db_conn),
+            user_repo: UserRepository::new(db_conn),
+            blob_repo: BlobRepository::new(db_conn),
+            storage_repo: StorageRepository::new(storage),
+            retention_days,
+        }
+    }
+
+    /// Окончательное удаление просроченных объектов корзины вместе с поддеревьями.
+    /// При `dry_run` только возвращает то, что было бы удалено
+    pub async fn purge_expired(&self, dry_run: bool) -> Result<TrashPurgeReport, ApiError> {
+        let expired_before = (Utc::now() - Duration::days(self.retention_days)).naive_utc();
+        let items = self
+            .object_repo
+            .select_expired_trash(expired_before, PURGE_BATCH_SIZE)
+            .await?;
+
+        if !dry_run {
+            for item in &items {
+                let mut tx = self.db_conn.get_pool().begin().await?;
+                let eliminated = self
+                    .object_repo
+                    .mark_as_eliminated(&mut tx, item.id)
+                    .await?;
+                let blob_ids: Vec<Id> = eliminated.iter().filter_map(|obj| obj.blob_id).collect();
+                let unreferenced = self.blob_repo.release(&mut tx, &blob_ids).await?;
+                let mut owner_ids: Vec<Id> = eliminated.iter().map(|obj| obj.owner_id).collect();
+                owner_ids.sort();
+                owner_ids.dedup();
+                self.user_repo
+                    .recalculate_storage_size(&mut tx, &owner_ids)
+                    .await?;
+                tx.commit().await?;
+                self.storage_repo.delete_blobs(&unreferenced).await;
+                tracing::info!(
+                    "Purged trash object {} '{}' of user {} trashed at {}: {} objects, {} bytes",
+                    item.id,
+                    item.name,
+                    item.owner_id,
+                    item.trashed_at,
+                    eliminated.len(),
+                    item.total_size,
+                );
+            }
+        }
+
+        Ok(TrashPurgeReport {
+            dry_run,
+            expired_before,
+            object_count: items.iter().map(|item| item.object_count).sum(),
+            total_size: items.iter().map(|item| item.total_size).sum(),
+            items,
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use crate::config::database::{Database, DatabaseTrait};
+use crate::config::rabbitmq::{ROUTING_KEY_EVENT_UPLOAD_ROBOT, ROUTING_KEY_EVENT_UPLOAD_USER};
+use crate::entity::object::UploadStatus;
+use crate::entity::pagination::Pagination;
+use crate::entity::upload_dead_letter::{UploadDeadLetter, UploadDeadLettersPaginated};
+use crate::entity::upload_outbox::UploadOutboxCreateModel;
+use crate::error::api_error::ApiError;
+use crate::error::upload_error::UploadError;
+use crate::repository::blob_repository::{BlobRepository, BlobRepositoryTrait};
+use crate::repository::robot_object_repository::{
+    RobotObjectRepository, RobotObjectRepositoryTrait,
+};
+use crate::repository::upload_dead_letter_repository::{
+    UploadDeadLetterRepository, UploadDeadLetterRepositoryTrait,
+};
+use crate::repository::upload_outbox_repository::{
+    UploadOutboxRepository, UploadOutboxRepositoryTrait,
+};
+use crate::scalar::Id;
+
+/// Просмотр и повторная отправка событий загрузки, отложенных file_worker
+#[derive(Clone)]
+pub struct UploadDeadLetterService {
+    dead_letter_repo: UploadDeadLetterRepository,
+    blob_repo: BlobRepository,
+    robot_object_repo: RobotObjectRepository,
+    outbox_repo: UploadOutboxRepository,
+    db_conn: Arc<Database>,
+}
+
+impl UploadDeadLetterService {
+    pub fn new(db_conn: &Arc<Database>) -> Self {
+        Self {
+            dead_letter_repo: UploadDeadLetterRepository::new(db_conn),
+            blob_repo: BlobRepository::new(db_conn),
+            robot_object_repo: RobotObjectRepository::new(db_conn),
+            outbox_repo: UploadOutboxRepository::new(db_conn),
+            db_conn: Arc::clone(db_conn),
+        }
+    }
+
+    pub async fn get_list(
+        &self,
+        pagination: Pagination,
+        include_replayed: bool,
+    ) -> Result<UploadDeadLettersPaginated, ApiError> {
+        let res = self
+            .dead_letter_repo
+            .select_list(pagination, include_replayed)
+            .await?;
+        Ok(res)
+    }
+
+    /// Повторная отправка события через outbox с возвратом файла в статус `pending`
+    pub async fn replay(&self, id: Id) -> Result<UploadDeadLetter, ApiError> {
+        let dead_letter = self
+            .dead_letter_repo
+            .select_by_id(id)
+            .await?
+            .ok_or(UploadError::DeadLetterNotFound)?;
+
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        let dead_letter = self
+            .dead_letter_repo
+            .mark_replayed(&mut tx, dead_letter.id)
+            .await?
+            .ok_or(UploadError::DeadLetterReplayed)?;
+        if let Some(object_id) = dead_letter.object_id {
+            match dead_letter.routing_key.as_str() {
+                ROUTING_KEY_EVENT_UPLOAD_USER => {
+                    self.blob_repo
+                        .set_upload_status(&mut tx, object_id, UploadStatus::Pending)
+                        .await?
+                }
+                ROUTING_KEY_EVENT_UPLOAD_ROBOT => {
+                    self.robot_object_repo
+                        .set_upload_status(&mut tx, object_id, UploadStatus::Pending)
+                        .await?
+                }
+                _ => {}
+            }
+        }
+
+        let message = UploadOutboxCreateModel {
+            id: Id::new_v4(),
+            routing_key: dead_letter.routing_key.clone(),
+            payload: dead_letter.payload.clone(),
+        };
+        self.outbox_repo.insert(&mut tx, message).await?;
+        tx.commit().await?;
+
+        tracing::info!(
+            "replayed dead-lettered upload {} ({:?})",
+            dead_letter.id,
+            dead_letter.object_id
+        );
+        Ok(dead_letter)
+    }
+}
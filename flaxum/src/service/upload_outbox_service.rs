@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use crate::config::database::{Database, DatabaseTrait};
+use crate::config::rabbitmq::ConfirmedPublisher;
+use crate::error::api_error::ApiError;
+use crate::error::backend_error::BackendError;
+use crate::repository::upload_outbox_repository::{
+    UploadOutboxRepository, UploadOutboxRepositoryTrait,
+};
+
+use amqprs::connection::Connection as RMQConn;
+use chrono::{Duration, Utc};
+
+/// Сколько событий публикуется за один проход relay
+const RELAY_BATCH_SIZE: i64 = 100;
+/// Сколько дней хранятся уже отправленные события
+const SENT_RETENTION_DAYS: i64 = 7;
+
+/// Публикация событий загрузки из `UploadOutbox` с подтверждением брокера
+#[derive(Clone)]
+pub struct UploadOutboxService {
+    outbox_repo: UploadOutboxRepository,
+    db_conn: Arc<Database>,
+    rmq_conn: Arc<RMQConn>,
+}
+
+impl UploadOutboxService {
+    pub fn new(db_conn: &Arc<Database>, rmq_conn: &Arc<RMQConn>) -> Self {
+        Self {
+            outbox_repo: UploadOutboxRepository::new(db_conn),
+            db_conn: Arc::clone(db_conn),
+            rmq_conn: Arc::clone(rmq_conn),
+        }
+    }
+
+    /// Публикация очередной пачки неотправленных событий по порядку записи.
+    /// Событие помечается отправленным только после подтверждения брокера;
+    /// при ошибке проход прерывается, чтобы не нарушать порядок, и событие
+    /// повторяется следующим проходом. Возвращает число отправленных событий
+    pub async fn relay_pending(&self) -> Result<usize, ApiError> {
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        let pending = self
+            .outbox_repo
+            .select_pending(&mut tx, RELAY_BATCH_SIZE)
+            .await?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let mut publisher = ConfirmedPublisher::open(&self.rmq_conn)
+            .await
+            .map_err(|err| BackendError::InternalError(err.to_string()))?;
+        let mut sent = 0;
+        for message in pending {
+            match publisher
+                .publish(&message.routing_key, message.payload.into_bytes())
+                .await
+            {
+                Ok(()) => {
+                    self.outbox_repo.mark_sent(&mut tx, message.id).await?;
+                    sent += 1;
+                }
+                Err(err) => {
+                    tracing::warn!("Failed to publish outbox message {}: {}", message.id, err);
+                    self.outbox_repo
+                        .record_failure(&mut tx, message.id, &err.to_string())
+                        .await?;
+                    break;
+                }
+            }
+        }
+        publisher.close().await;
+        tx.commit().await?;
+        Ok(sent)
+    }
+
+    /// Удаление отправленных событий старше `SENT_RETENTION_DAYS`
+    pub async fn delete_sent(&self) -> Result<u64, ApiError> {
+        let sent_before = Utc::now().naive_utc() - Duration::days(SENT_RETENTION_DAYS);
+        let res = self.outbox_repo.delete_sent_before(sent_before).await?;
+        Ok(res)
+    }
+}
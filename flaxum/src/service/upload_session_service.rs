@@ -0,0 +1,563 @@
+use std::io::SeekFrom;
+use std::sync::Arc;
+
+use crate::config::database::{Database, DatabaseTrait};
+use crate::config::{UPLOAD_CHUNK_LEASE_SECONDS, UPLOAD_SESSION_TTL_HOURS};
+use crate::dto::upload_session::CreateUploadSessionDto;
+use crate::entity::object::{Object, ObjectCreateModel, ObjectType};
+use crate::entity::upload_session::{UploadSession, UploadSessionCreateModel};
+use crate::error::api_error::ApiError;
+use crate::error::backend_error::BackendError;
+use crate::error::upload_error::UploadError;
+use crate::repository::upload_session_repository::{
+    UploadSessionRepository, UploadSessionRepositoryTrait,
+};
+use crate::scalar::Id;
+use crate::service::access_service::AccessService;
+use crate::service::object_service::ObjectService;
+
+use axum::body::Body;
+use chrono::{Duration, NaiveDateTime, Utc};
+use file_worker::cipher::Keyring;
+use file_worker::storage::StorageBackend;
+use futures::StreamExt;
+use sha2::{digest::Digest, Sha256};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+const HASH_BUFFER_SIZE: usize = 1024 * 1024 * 8;
+
+/// Право одного запроса писать в файл сессии до `until`; продлевается после `renew_at`
+struct Lease {
+    writer_id: Id,
+    until: NaiveDateTime,
+    renew_at: NaiveDateTime,
+}
+
+impl Lease {
+    fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            writer_id: Id::new_v4(),
+            until: (now + Duration::seconds(UPLOAD_CHUNK_LEASE_SECONDS)).naive_utc(),
+            renew_at: (now + Duration::seconds(UPLOAD_CHUNK_LEASE_SECONDS / 2)).naive_utc(),
+        }
+    }
+}
+
+/// Возобновляемая загрузка: сессия, дозапись кусков по смещению, завершение
+#[derive(Clone)]
+pub struct UploadSessionService {
+    db_conn: Arc<Database>,
+    upload_session_repo: UploadSessionRepository,
+    object_service: ObjectService,
+    access_service: AccessService,
+}
+
+impl UploadSessionService {
+    pub fn new(
+        db_conn: &Arc<Database>,
+        storage: &Arc<dyn StorageBackend>,
+        keyring: &Arc<Keyring>,
+    ) -> Self {
+        Self {
+            db_conn: Arc::clone(db_conn),
+            upload_session_repo: UploadSessionRepository::new(db_conn),
+            object_service: ObjectService::new(db_conn, storage, keyring),
+            access_service: AccessService::new(db_conn),
+        }
+    }
+
+    fn expires_at() -> NaiveDateTime {
+        (Utc::now() + Duration::hours(UPLOAD_SESSION_TTL_HOURS)).naive_utc()
+    }
+
+    pub async fn create_session(
+        &self,
+        dto: CreateUploadSessionDto,
+        user_id: Id,
+    ) -> Result<UploadSession, ApiError> {
+        self.access_service
+            .authorize_parent(user_id, dto.parent_id)
+            .await?;
+        self.object_service
+            .available_storage(user_id, dto.size)
+            .await?;
+        let create_model = UploadSessionCreateModel {
+            id: Id::new_v4(),
+            user_id,
+            parent_id: dto.parent_id,
+            name: dto.name,
+            mimetype: dto.mimetype,
+            size: dto.size,
+            expires_at: Self::expires_at(),
+        };
+        fs::File::create(format!("tmp/{}.{}", user_id, create_model.id)).await?;
+        let session = self.upload_session_repo.insert(create_model).await?;
+        Ok(session)
+    }
+
+    pub async fn get_session(&self, id: Id, user_id: Id) -> Result<UploadSession, ApiError> {
+        let session = self
+            .upload_session_repo
+            .select_by_id(id, user_id)
+            .await?
+            .ok_or(UploadError::SessionNotFound)?;
+        Ok(session)
+    }
+
+    /// Дозапись куска с позиции `offset`. Если соединение оборвалось посреди куска,
+    /// принятые байты сохраняются и загрузку можно продолжить с нового смещения.
+    /// Тело пишется без открытой транзакции: запрос берет право записи в сессию,
+    /// параллельный запрос в ту же сессию получает 409
+    pub async fn append_chunk(
+        &self,
+        id: Id,
+        user_id: Id,
+        offset: i64,
+        body: Body,
+    ) -> Result<UploadSession, ApiError> {
+        let (session, mut lease) = self.claim(id, user_id, offset).await?;
+        let res = self.write_chunk(&session, &mut lease, offset, body).await;
+        let (written, stream_error) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                self.release(&session, &lease).await;
+                return Err(err);
+            }
+        };
+
+        let session = self
+            .upload_session_repo
+            .update_offset(
+                id,
+                lease.writer_id,
+                offset,
+                offset + written,
+                Self::expires_at(),
+            )
+            .await?
+            .ok_or(UploadError::SessionBusy)?;
+
+        match stream_error {
+            Some(err) => Err(BackendError::InternalError(format!(
+                "Failed to read upload chunk: {}",
+                err
+            )))?,
+            None => Ok(session),
+        }
+    }
+
+    /// Запись тела в файл сессии; число записанных байт и ошибка чтения тела, если оно оборвалось
+    async fn write_chunk(
+        &self,
+        session: &UploadSession,
+        lease: &mut Lease,
+        offset: i64,
+        body: Body,
+    ) -> Result<(i64, Option<axum::Error>), ApiError> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(session.tmp_path())
+            .await?;
+        // Отбрасываем хвост неудачной предыдущей записи
+        file.set_len(offset as u64).await?;
+        file.seek(SeekFrom::Start(offset as u64)).await?;
+
+        let remaining = session.size - offset;
+        let mut written: i64 = 0;
+        let mut stream = body.into_data_stream();
+        let mut stream_error = None;
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    if written + chunk.len() as i64 > remaining {
+                        return Err(UploadError::SizeExceeded)?;
+                    }
+                    self.renew(session, lease).await?;
+                    file.write_all(&chunk).await?;
+                    written += chunk.len() as i64;
+                }
+                Err(err) => {
+                    stream_error = Some(err);
+                    break;
+                }
+            }
+        }
+        file.flush().await?;
+        Ok((written, stream_error))
+    }
+
+    /// Право записи в сессию с позиции `offset`
+    async fn claim(
+        &self,
+        id: Id,
+        user_id: Id,
+        offset: i64,
+    ) -> Result<(UploadSession, Lease), ApiError> {
+        let lease = Lease::new();
+        let claimed = self
+            .upload_session_repo
+            .claim(id, user_id, offset, lease.writer_id, lease.until)
+            .await?;
+        if let Some(session) = claimed {
+            return Ok((session, lease));
+        }
+        let session = self.get_session(id, user_id).await?;
+        match session.upload_offset == offset {
+            true => Err(UploadError::SessionBusy)?,
+            false => Err(UploadError::OffsetMismatch(session.upload_offset))?,
+        }
+    }
+
+    /// Продление права записи, когда прошла половина срока. Если его уже забрал другой
+    /// запрос, писать в файл дальше нельзя
+    async fn renew(&self, session: &UploadSession, lease: &mut Lease) -> Result<(), ApiError> {
+        if Utc::now().naive_utc() < lease.renew_at {
+            return Ok(());
+        }
+        let renewed = Lease {
+            writer_id: lease.writer_id,
+            ..Lease::new()
+        };
+        let kept = self
+            .upload_session_repo
+            .renew_lease(session.id, renewed.writer_id, renewed.until)
+            .await?;
+        if !kept {
+            return Err(UploadError::SessionBusy)?;
+        }
+        *lease = renewed;
+        Ok(())
+    }
+
+    async fn release(&self, session: &UploadSession, lease: &Lease) {
+        let res = self
+            .upload_session_repo
+            .release_lease(session.id, lease.writer_id)
+            .await;
+        if let Err(err) = res {
+            tracing::warn!("Failed to release upload session {}: {}", session.id, err);
+        }
+    }
+
+    /// Завершение: собранный файл проходит тот же путь, что и обычный `/upload`.
+    /// Пока считается хеш, сессия занята, как при дозаписи; удаление сессии
+    /// и регистрация файла - одна транзакция. Если файл зарегистрировать не удалось,
+    /// сессия и ее временный файл удаляются
+    pub async fn finish(&self, id: Id, user_id: Id) -> Result<Object, ApiError> {
+        let session = self.get_session(id, user_id).await?;
+        if !session.is_complete() {
+            return Err(UploadError::Incomplete(session.upload_offset, session.size))?;
+        }
+        let (session, mut lease) = self.claim(id, user_id, session.size).await?;
+        let obj_constructor = match self.hash(&session, &mut lease).await {
+            Ok(obj_constructor) => obj_constructor,
+            Err(err) => {
+                self.release(&session, &lease).await;
+                return Err(err);
+            }
+        };
+        let res = self.register(&session, &lease, obj_constructor).await;
+        if res.is_err() {
+            self.discard(&session, &lease).await;
+        }
+        res
+    }
+
+    /// Хеш собранного файла и модель объекта для него
+    async fn hash(
+        &self,
+        session: &UploadSession,
+        lease: &mut Lease,
+    ) -> Result<ObjectCreateModel, ApiError> {
+        let mut file = fs::File::open(session.tmp_path()).await?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            self.renew(session, lease).await?;
+            hasher.update(&buffer[..read]);
+        }
+        let hash_sha256 = hex::encode(hasher.finalize());
+
+        Ok(ObjectCreateModel {
+            id: session.id,
+            parent_id: session.parent_id,
+            owner_id: session.user_id,
+            creator_id: session.user_id,
+            name: session.name.clone(),
+            size: Some(session.size),
+            type_: ObjectType::File,
+            mimetype: Some(
+                session
+                    .mimetype
+                    .clone()
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+            ),
+            upload_s3: Some(false),
+            decode_key: None,
+            hash_sha256: Some(hash_sha256),
+            blob_id: None,
+            upload_status: None,
+        })
+    }
+
+    async fn register(
+        &self,
+        session: &UploadSession,
+        lease: &Lease,
+        obj_constructor: ObjectCreateModel,
+    ) -> Result<Object, ApiError> {
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        self.upload_session_repo
+            .delete_claimed(&mut tx, session.id, lease.writer_id)
+            .await?
+            .ok_or(UploadError::SessionBusy)?;
+        self.access_service
+            .authorize_parent_tx(&mut tx, session.user_id, session.parent_id)
+            .await?;
+        let (new_obj, deduplicated) = self
+            .object_service
+            .store_uploaded_file_tx(&mut tx, obj_constructor)
+            .await?;
+        tx.commit().await?;
+        if deduplicated {
+            self.object_service.remove_uploaded_tmp(&new_obj).await;
+        }
+        Ok(new_obj)
+    }
+
+    /// Удаление сессии, которую держит `lease`, вместе с временным файлом.
+    /// Если аренда уже потеряна, сессию и файл не трогаем
+    async fn discard(&self, session: &UploadSession, lease: &Lease) {
+        let res = async {
+            let mut tx = self.db_conn.get_pool().begin().await?;
+            let deleted = self
+                .upload_session_repo
+                .delete_claimed(&mut tx, session.id, lease.writer_id)
+                .await?;
+            tx.commit().await?;
+            Ok::<_, ApiError>(deleted.is_some())
+        }
+        .await;
+        match res {
+            Ok(true) => Self::remove_tmp_file(session).await,
+            Ok(false) => {}
+            Err(err) => {
+                tracing::warn!("Failed to discard upload session {}: {}", session.id, err)
+            }
+        }
+    }
+
+    pub async fn abort(&self, id: Id, user_id: Id) -> Result<(), ApiError> {
+        let session = self.get_session(id, user_id).await?;
+        self.upload_session_repo.delete(session.id).await?;
+        Self::remove_tmp_file(&session).await;
+        Ok(())
+    }
+
+    /// Удаление просроченных незавершенных сессий вместе с их временными файлами
+    pub async fn remove_expired(&self) -> Result<usize, ApiError> {
+        let expired = self.upload_session_repo.delete_expired().await?;
+        for session in expired.iter() {
+            Self::remove_tmp_file(session).await;
+        }
+        Ok(expired.len())
+    }
+
+    async fn remove_tmp_file(session: &UploadSession) {
+        if let Err(err) = fs::remove_file(session.tmp_path()).await {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Failed to remove {}: {}", session.tmp_path(), err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::user::CreateUserDto;
+    use crate::error::user_error::UserError;
+    use crate::repository::user_repository::{UserRepository, UserRepositoryTrait};
+    use bytes::Bytes;
+    use file_worker::storage::LocalStorage;
+    use sqlx::postgres::PgPoolOptions;
+    use sqlx::PgPool;
+
+    async fn service(pool: PgPool) -> (UploadSessionService, UserRepository) {
+        let db_conn = Arc::new(Database::from_pool(pool));
+        let root = std::env::temp_dir().join(format!("flaxum-test-{}", Id::new_v4()));
+        let storage: Arc<dyn StorageBackend> = Arc::new(LocalStorage::init(root).await.unwrap());
+        let keyring = Arc::new(Keyring::from_hex(&"11".repeat(32), None).unwrap());
+        fs::create_dir_all("tmp").await.unwrap();
+        (
+            UploadSessionService::new(&db_conn, &storage, &keyring),
+            UserRepository::new(&db_conn),
+        )
+    }
+
+    /// Пользователь и его сессия на `size` байт в корне
+    async fn session(
+        service: &UploadSessionService,
+        user_repo: &UserRepository,
+        size: i64,
+    ) -> UploadSession {
+        user_repo
+            .create_user(CreateUserDto {
+                email: "owner@test.com".to_string(),
+                password: "password".to_string(),
+            })
+            .await
+            .unwrap();
+        let user_id = user_repo
+            .select_by_email("owner@test.com".to_string())
+            .await
+            .unwrap()
+            .id;
+        service
+            .create_session(
+                CreateUploadSessionDto {
+                    name: "a.txt".to_string(),
+                    size,
+                    mimetype: None,
+                    parent_id: None,
+                },
+                user_id,
+            )
+            .await
+            .unwrap()
+    }
+
+    /// Тело запроса из двух частей с паузой между ними
+    fn slow_body(content: &'static str) -> Body {
+        let (head, tail) = content.split_at(2);
+        let stream = futures::stream::iter([head, tail]).then(|part| async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            Ok::<_, std::io::Error>(Bytes::from_static(part.as_bytes()))
+        });
+        Body::from_stream(stream)
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn concurrent_chunks_at_same_offset(pool: PgPool) {
+        let (service, user_repo) = service(pool).await;
+        let session = session(&service, &user_repo, 5).await;
+        let user_id = session.user_id;
+
+        let (first, second) = tokio::join!(
+            service.append_chunk(session.id, user_id, 0, slow_body("hello")),
+            service.append_chunk(session.id, user_id, 0, slow_body("world")),
+        );
+        let content = fs::read_to_string(session.tmp_path()).await.unwrap();
+        let _ = fs::remove_file(session.tmp_path()).await;
+
+        let (accepted, rejected, expected) = match first {
+            Ok(accepted) => (accepted, second, "hello"),
+            Err(_) => (second.unwrap(), first, "world"),
+        };
+        assert_eq!(accepted.upload_offset, 5);
+        assert!(matches!(
+            rejected,
+            Err(ApiError::UploadError(UploadError::SessionBusy))
+        ));
+        assert_eq!(content, expected);
+
+        // Право записи снято: следующий кусок с прежним смещением - уже не то смещение
+        let res = service
+            .append_chunk(session.id, user_id, 0, Body::from("x"))
+            .await;
+        assert!(matches!(
+            res,
+            Err(ApiError::UploadError(UploadError::OffsetMismatch(5)))
+        ));
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn chunk_upload_does_not_hold_connection(pool: PgPool) {
+        // Пул на одно соединение: пока тело идет, БД нужна другим запросам
+        let options = pool.connect_options().as_ref().clone();
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        let (service, user_repo) = service(pool).await;
+        let session = session(&service, &user_repo, 5).await;
+        let user_id = session.user_id;
+
+        let append = service.append_chunk(session.id, user_id, 0, slow_body("hello"));
+        let read = async {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            let timeout = std::time::Duration::from_millis(100);
+            tokio::time::timeout(timeout, service.get_session(session.id, user_id)).await
+        };
+        let (appended, read) = tokio::join!(append, read);
+        let _ = fs::remove_file(session.tmp_path()).await;
+        assert_eq!(appended.unwrap().upload_offset, 5);
+        assert_eq!(read.unwrap().unwrap().upload_offset, 0);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn finish_registers_file_and_removes_session(pool: PgPool) {
+        let (service, user_repo) = service(pool).await;
+        let session = session(&service, &user_repo, 5).await;
+        let user_id = session.user_id;
+
+        let res = service.finish(session.id, user_id).await;
+        assert!(matches!(
+            res,
+            Err(ApiError::UploadError(UploadError::Incomplete(0, 5)))
+        ));
+        service
+            .append_chunk(session.id, user_id, 0, Body::from("hello"))
+            .await
+            .unwrap();
+        let obj = service.finish(session.id, user_id).await.unwrap();
+        let _ = fs::remove_file(session.tmp_path()).await;
+        assert_eq!(obj.id, session.id);
+        assert_eq!(obj.size, Some(5));
+        assert!(matches!(
+            service.get_session(session.id, user_id).await,
+            Err(ApiError::UploadError(UploadError::SessionNotFound))
+        ));
+        assert!(service.finish(session.id, user_id).await.is_err());
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn failed_finish_removes_session_and_tmp_file(pool: PgPool) {
+        let (service, user_repo) = service(pool.clone()).await;
+        let session = session(&service, &user_repo, 5).await;
+        let user_id = session.user_id;
+        service
+            .append_chunk(session.id, user_id, 0, Body::from("hello"))
+            .await
+            .unwrap();
+        sqlx::query(r#"UPDATE "User" SET storage_quota = 1 WHERE id = $1"#)
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let res = service.finish(session.id, user_id).await;
+        assert!(matches!(
+            res,
+            Err(ApiError::UserError(UserError::StorageQuotaExceeded))
+        ));
+        assert!(matches!(
+            service.get_session(session.id, user_id).await,
+            Err(ApiError::UploadError(UploadError::SessionNotFound))
+        ));
+        assert!(!fs::try_exists(session.tmp_path()).await.unwrap());
+    }
+}
@@ -1,10 +1,10 @@
 use crate::config::database::Database;
 use crate::dto::user::{
-    AdminCreateUserDto, AdminCreateUserOut, ChangePasswordDto, CreateUserDto, CreateUserOut,
-    UpdateUserMeDto,
+    AdminCreateUserDto, AdminCreateUserOut, AdminSetStorageQuotaDto, ChangePasswordDto,
+    CreateUserDto, CreateUserOut, UpdateUserMeDto,
 };
 use crate::entity::pagination::Pagination;
-use crate::entity::user::{AdminUsersPaginated, PublicUser, User};
+use crate::entity::user::{AdminUser, AdminUsersPaginated, PublicUser, StorageUsage, User};
 use crate::error::api_error::ApiError;
 use crate::error::user_error::UserError;
 use crate::repository::user_repository::{UserRepository, UserRepositoryTrait};
@@ -110,4 +110,24 @@ impl UserService {
             .update_password(hash_password, user_id)
             .await?)
     }
+
+    pub async fn admin_set_storage_quota(
+        &self,
+        payload: AdminSetStorageQuotaDto,
+    ) -> Result<AdminUser, ApiError> {
+        let user = self
+            .user_repo
+            .update_storage_quota(payload.id, payload.storage_quota)
+            .await
+            .map_err(|err| match err {
+                sqlx::Error::RowNotFound => ApiError::from(UserError::UserNotFound),
+                err => ApiError::from(err),
+            })?;
+        Ok(user)
+    }
+
+    pub async fn get_storage_usage(&self, id: Id) -> Result<StorageUsage, ApiError> {
+        let usage = self.user_repo.select_storage_usage(id).await?;
+        Ok(usage)
+    }
 }
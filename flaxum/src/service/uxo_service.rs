@@ -1,38 +1,75 @@
 use std::sync::Arc;
 
-use crate::config::database::Database;
+use crate::config::database::{Database, DatabaseTrait};
 use crate::dto::uxo::{DeleteAccessDto, DeleteAccessDtoIn, GiveAccessDto};
-use crate::entity::object::{GetUxoListOut, PublicUserXObject};
+use crate::entity::object::{GetUxoListOut, Permission, PublicUserXObject, UxOAccess};
 use crate::error::api_error::ApiError;
 use crate::error::backend_error::BackendError;
+use crate::error::user_error::UserError;
+use crate::repository::user_repository::{UserRepository, UserRepositoryTrait};
 use crate::repository::uxo_repository::{UxoRepository, UxoRepositoryTrait};
 use crate::scalar::Id;
+use crate::service::access_service::AccessService;
 
 // todo: add trait
 #[derive(Clone)]
 pub struct UxoService {
+    db_conn: Arc<Database>,
     uxo_repo: UxoRepository,
+    user_repo: UserRepository,
+    access_service: AccessService,
 }
 
 impl UxoService {
     pub fn new(db_conn: &Arc<Database>) -> Self {
         Self {
+            db_conn: Arc::clone(db_conn),
             uxo_repo: UxoRepository::new(db_conn),
+            user_repo: UserRepository::new(db_conn),
+            access_service: AccessService::new(db_conn),
         }
     }
 
-    pub async fn get_object_uxo_list(&self, obj_id: Id) -> Result<GetUxoListOut, ApiError> {
+    pub async fn get_object_uxo_list(
+        &self,
+        user_id: Id,
+        obj_id: Id,
+    ) -> Result<GetUxoListOut, ApiError> {
+        self.access_service
+            .authorize(user_id, obj_id, Permission::Read)
+            .await?;
         let res = self.uxo_repo.select_object_uxo_list(obj_id).await?;
         let res = GetUxoListOut { items: res };
         Ok(res)
     }
 
+    /// Выдать доступ может пользователь с правом редактирования,
+    /// и только в пределах собственных прав
     pub async fn give_access_by_email(
         &self,
+        user_id: Id,
         obj_id: Id,
         dto: GiveAccessDto,
     ) -> Result<PublicUserXObject, ApiError> {
-        let res = self.uxo_repo.insert_access_by_email(obj_id, dto).await?;
+        let granted = UxOAccess {
+            can_read: dto.can_read,
+            can_edit: dto.can_edit,
+            can_delete: dto.can_delete,
+        };
+        let recipient = self
+            .user_repo
+            .select_by_email(dto.recipient_email)
+            .await
+            .ok_or(UserError::UserNotFound)?;
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        self.access_service
+            .authorize_grant_tx(&mut tx, user_id, obj_id, recipient.id, &granted)
+            .await?;
+        let res = self
+            .uxo_repo
+            .upsert_access(&mut tx, recipient.id, obj_id, granted)
+            .await?;
+        tx.commit().await?;
         Ok(res)
     }
 
@@ -45,11 +82,155 @@ impl UxoService {
         if owner_id == dto.recipient_id {
             return Err(BackendError::CloseAccessYourSelf)?;
         }
+        let mut tx = self.db_conn.get_pool().begin().await?;
+        self.access_service
+            .authorize_revoke_tx(&mut tx, owner_id, obj_id, dto.recipient_id)
+            .await?;
         let dto = DeleteAccessDto {
             obj_id,
             recipient_id: dto.recipient_id,
         };
-        self.uxo_repo.delete_access_by_user_id(dto).await?;
+        self.uxo_repo.delete_access_by_user_id(&mut tx, dto).await?;
+        tx.commit().await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::user::CreateUserDto;
+    use crate::entity::object::{ObjectCreateModel, ObjectType};
+    use crate::repository::object_repository::{ObjectRepository, ObjectRepositoryTrait};
+    use sqlx::PgPool;
+
+    async fn create_user(user_repo: &UserRepository, email: &str) -> Id {
+        user_repo
+            .create_user(CreateUserDto {
+                email: email.to_string(),
+                password: "password".to_string(),
+            })
+            .await
+            .unwrap();
+        user_repo
+            .select_by_email(email.to_string())
+            .await
+            .unwrap()
+            .id
+    }
+
+    fn access(can_read: bool, can_edit: bool, can_delete: bool, email: &str) -> GiveAccessDto {
+        GiveAccessDto {
+            can_read,
+            can_edit,
+            can_delete,
+            recipient_email: email.to_string(),
+        }
+    }
+
+    fn status(res: Result<impl Sized, ApiError>) -> Option<u16> {
+        use axum::response::IntoResponse;
+        res.err().map(|err| err.into_response().status().as_u16())
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "нужен Postgres, DATABASE_URL"]
+    async fn grant_and_revoke_stay_within_own_access(pool: PgPool) {
+        let db_conn = Arc::new(Database::from_pool(pool));
+        let service = UxoService::new(&db_conn);
+        let owner = create_user(&service.user_repo, "owner@test.com").await;
+        let editor = create_user(&service.user_repo, "editor@test.com").await;
+        let other = create_user(&service.user_repo, "other@test.com").await;
+
+        let mut tx = db_conn.get_pool().begin().await.unwrap();
+        let folder = ObjectRepository::new(&db_conn)
+            .insert_object(
+                &mut tx,
+                ObjectCreateModel {
+                    id: Id::new_v4(),
+                    owner_id: owner,
+                    creator_id: owner,
+                    name: "shared".to_string(),
+                    size: Some(0),
+                    type_: ObjectType::Dir,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let (editor_email, other_email) = ("editor@test.com", "other@test.com");
+        service
+            .give_access_by_email(owner, folder.id, access(true, true, false, editor_email))
+            .await
+            .unwrap();
+        service
+            .give_access_by_email(owner, folder.id, access(true, true, true, other_email))
+            .await
+            .unwrap();
+
+        // Доступ владельца не выдается и не снимается
+        let res = service
+            .give_access_by_email(
+                editor,
+                folder.id,
+                access(true, false, false, "owner@test.com"),
+            )
+            .await;
+        assert_eq!(status(res), Some(400));
+        let res = service
+            .remove_access_by_user_id(
+                editor,
+                folder.id,
+                DeleteAccessDtoIn {
+                    recipient_id: owner,
+                },
+            )
+            .await;
+        assert_eq!(status(res), Some(400));
+
+        // Запись с правом удаления, которого нет у выдающего, не перезаписывается и не снимается
+        let res = service
+            .give_access_by_email(editor, folder.id, access(true, false, false, other_email))
+            .await;
+        assert_eq!(status(res), Some(403));
+        let res = service
+            .remove_access_by_user_id(
+                editor,
+                folder.id,
+                DeleteAccessDtoIn {
+                    recipient_id: other,
+                },
+            )
+            .await;
+        assert_eq!(status(res), Some(403));
+        let list = service.get_object_uxo_list(owner, folder.id).await.unwrap();
+        let uxo = list
+            .items
+            .iter()
+            .find(|item| item.uxo.user_id == other)
+            .unwrap();
+        assert!(uxo.uxo.can_delete);
+
+        // В пределах своих прав можно и понизить, и снять
+        service
+            .give_access_by_email(owner, folder.id, access(true, true, false, other_email))
+            .await
+            .unwrap();
+        service
+            .give_access_by_email(editor, folder.id, access(true, false, false, other_email))
+            .await
+            .unwrap();
+        service
+            .remove_access_by_user_id(
+                editor,
+                folder.id,
+                DeleteAccessDtoIn {
+                    recipient_id: other,
+                },
+            )
+            .await
+            .unwrap();
+    }
+}
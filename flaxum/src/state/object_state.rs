@@ -1,15 +1,24 @@
 use crate::config::database::Database;
+use crate::config::env::EnvironmentVariables;
 use crate::config::parameter;
 
 use crate::repository::user_repository::{UserRepository, UserRepositoryTrait};
 
+use crate::service::key_rotation_service::KeyRotationService;
+use crate::service::object_job_service::ObjectJobService;
 use crate::service::object_service::ObjectService;
+use crate::service::scrub_service::ScrubService;
+use crate::service::share_link_service::ShareLinkService;
+use crate::service::storage_gc_service::StorageGcService;
+use crate::service::trash_service::TrashService;
+use crate::service::upload_dead_letter_service::UploadDeadLetterService;
 use crate::service::token_service::{TokenService, TokenServiceTrait};
+use crate::service::upload_session_service::UploadSessionService;
 use crate::service::user_service::UserService;
 use crate::service::uxo_service::UxoService;
 
-use amqprs::connection::Connection as RMQConn;
-use aws_sdk_s3::Client as S3Client;
+use file_worker::cipher::Keyring;
+use file_worker::storage::StorageBackend;
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -18,21 +27,43 @@ pub struct ObjectState {
     pub(crate) token_service: TokenService,
     pub(crate) user_service: UserService,
     pub(crate) object_service: ObjectService,
+    pub(crate) object_job_service: ObjectJobService,
     pub(crate) uxo_service: UxoService,
+    pub(crate) upload_session_service: UploadSessionService,
+    pub(crate) share_link_service: ShareLinkService,
+    pub(crate) trash_service: TrashService,
+    pub(crate) upload_dead_letter_service: UploadDeadLetterService,
+    pub(crate) key_rotation_service: KeyRotationService,
+    pub(crate) scrub_service: ScrubService,
+    pub(crate) storage_gc_service: StorageGcService,
 }
 
 impl ObjectState {
     pub fn new(
         db_conn: &Arc<Database>,
-        s3_client: &Arc<S3Client>,
-        rmq_conn: &Arc<RMQConn>,
+        storage: &Arc<dyn StorageBackend>,
+        keyring: &Arc<Keyring>,
+        env: &EnvironmentVariables,
     ) -> Self {
         Self {
             user_repo: UserRepository::new(db_conn),
             token_service: TokenService::new(parameter::get("JWT_SECRET")),
             user_service: UserService::new(db_conn),
-            object_service: ObjectService::new(db_conn, s3_client, rmq_conn),
+            object_service: ObjectService::new(db_conn, storage, keyring),
+            object_job_service: ObjectJobService::new(db_conn, storage, keyring),
             uxo_service: UxoService::new(db_conn),
+            upload_session_service: UploadSessionService::new(db_conn, storage, keyring),
+            share_link_service: ShareLinkService::new(db_conn, storage, keyring),
+            trash_service: TrashService::new(db_conn, storage, env.trash_retention_days),
+            upload_dead_letter_service: UploadDeadLetterService::new(db_conn),
+            key_rotation_service: KeyRotationService::new(db_conn, keyring),
+            scrub_service: ScrubService::new(
+                db_conn,
+                storage,
+                keyring,
+                env.scrub_rate_bytes_per_sec,
+            ),
+            storage_gc_service: StorageGcService::new(db_conn, storage),
         }
     }
 }
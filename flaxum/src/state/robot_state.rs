@@ -8,7 +8,7 @@ use crate::service::robot_service::{RobotService, RobotServiceTrait};
 use crate::service::token_service::TokenServiceTrait;
 
 use amqprs::connection::Connection as RMQConn;
-use aws_sdk_s3::Client as S3Client;
+use file_worker::storage::StorageBackend;
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -20,12 +20,12 @@ pub struct RobotState {
 impl RobotState {
     pub fn new(
         db_conn: &Arc<Database>,
-        s3_client: &Arc<S3Client>,
+        storage: &Arc<dyn StorageBackend>,
         rmq_conn: &Arc<RMQConn>,
     ) -> Self {
         Self {
             robot_service: RobotService::new(db_conn),
-            robot_object_service: RobotObjectService::new(db_conn, s3_client, rmq_conn),
+            robot_object_service: RobotObjectService::new(db_conn, storage, rmq_conn),
         }
     }
 }
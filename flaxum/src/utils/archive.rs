@@ -0,0 +1,226 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+
+use flate2::read::GzDecoder;
+use tar::EntryType;
+use zip::ZipArchive;
+
+/// Длина имени объекта в БД
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Поддерживаемые форматы загружаемых архивов
+#[derive(Clone, Copy, Debug)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    Dir,
+    File,
+    /// Ссылки, устройства и прочее, что не переносится в дерево объектов
+    Unsupported,
+}
+
+/// Запись архива. `path` - компоненты пути, `None` для путей, выходящих за корень архива
+#[derive(Debug)]
+pub struct ArchiveEntry {
+    pub path: Option<Vec<String>>,
+    pub kind: EntryKind,
+    pub size: u64,
+}
+
+impl ArchiveFormat {
+    /// Формат по сигнатуре в начале файла; файл возвращается к началу
+    pub fn detect(file: &mut File) -> io::Result<Option<Self>> {
+        let mut head = Vec::with_capacity(512);
+        file.by_ref().take(512).read_to_end(&mut head)?;
+        file.seek(SeekFrom::Start(0))?;
+        let format = match head.as_slice() {
+            [0x50, 0x4b, 0x03, 0x04, ..] | [0x50, 0x4b, 0x05, 0x06, ..] => Some(Self::Zip),
+            [0x1f, 0x8b, ..] => Some(Self::TarGz),
+            head if head.get(257..262) == Some(b"ustar") => Some(Self::Tar),
+            _ => None,
+        };
+        Ok(format)
+    }
+}
+
+/// Список записей архива без чтения содержимого (у `.tar.gz` архив распаковывается целиком).
+/// `None`, если записей больше `limit`
+pub fn list(
+    file: File,
+    format: ArchiveFormat,
+    limit: usize,
+) -> io::Result<Option<Vec<ArchiveEntry>>> {
+    let mut entries = Vec::new();
+    let res = walk(file, format, false, |entry, _| {
+        if entries.len() == limit {
+            return Err(io::Error::other("too many entries"));
+        }
+        entries.push(entry);
+        Ok(())
+    });
+    match res {
+        Ok(()) => Ok(Some(entries)),
+        Err(_) if entries.len() == limit => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Обход записей в том же порядке, что и `list`, с содержимым каждой записи.
+/// Содержимого нет у папок и у записей, которые нельзя прочитать (шифрование, неизвестное сжатие)
+pub fn extract<F>(file: File, format: ArchiveFormat, f: F) -> io::Result<()>
+where
+    F: FnMut(ArchiveEntry, Option<&mut dyn Read>) -> io::Result<()>,
+{
+    walk(file, format, true, f)
+}
+
+fn walk<F>(file: File, format: ArchiveFormat, read_data: bool, mut f: F) -> io::Result<()>
+where
+    F: FnMut(ArchiveEntry, Option<&mut dyn Read>) -> io::Result<()>,
+{
+    let reader = BufReader::new(file);
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = ZipArchive::new(reader)?;
+            for i in 0..archive.len() {
+                let entry = {
+                    let file = archive.by_index_raw(i)?;
+                    let kind = match (file.is_dir(), file.is_symlink()) {
+                        (true, _) => EntryKind::Dir,
+                        (false, true) => EntryKind::Unsupported,
+                        (false, false) => EntryKind::File,
+                    };
+                    ArchiveEntry {
+                        path: entry_path(file.name()),
+                        kind,
+                        size: file.size(),
+                    }
+                };
+                if !read_data || entry.kind != EntryKind::File {
+                    f(entry, None)?;
+                    continue;
+                }
+                match archive.by_index(i) {
+                    Ok(mut data) => f(entry, Some(&mut data))?,
+                    Err(err) => {
+                        tracing::warn!("Archive entry {} cannot be read: {}", i, err);
+                        f(entry, None)?
+                    }
+                }
+            }
+            Ok(())
+        }
+        ArchiveFormat::Tar => walk_tar(tar::Archive::new(reader), read_data, f),
+        ArchiveFormat::TarGz => walk_tar(tar::Archive::new(GzDecoder::new(reader)), read_data, f),
+    }
+}
+
+fn walk_tar<R, F>(mut archive: tar::Archive<R>, read_data: bool, mut f: F) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(ArchiveEntry, Option<&mut dyn Read>) -> io::Result<()>,
+{
+    for entry in archive.entries()? {
+        let mut data = entry?;
+        let kind = match data.header().entry_type() {
+            EntryType::Directory => EntryKind::Dir,
+            EntryType::Regular | EntryType::Continuous => EntryKind::File,
+            _ => EntryKind::Unsupported,
+        };
+        let entry = ArchiveEntry {
+            path: entry_path(&String::from_utf8_lossy(&data.path_bytes())),
+            kind,
+            size: data.size(),
+        };
+        match read_data && kind == EntryKind::File {
+            true => f(entry, Some(&mut data))?,
+            false => f(entry, None)?,
+        }
+    }
+    Ok(())
+}
+
+/// Компоненты пути записи без `.` и пустых. Абсолютные пути, буквы дисков, `..`,
+/// управляющие символы и имена длиннее допустимого для объекта не допускаются
+pub fn entry_path(raw: &str) -> Option<Vec<String>> {
+    if raw.starts_with(['/', '\\']) {
+        return None;
+    }
+    let mut parts = Vec::new();
+    for part in raw.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            part if part.chars().any(char::is_control) => return None,
+            part if part.chars().count() > MAX_NAME_LENGTH => return None,
+            part if parts.is_empty() && part.len() == 2 && part.ends_with(':') => return None,
+            part => parts.push(part.to_string()),
+        }
+    }
+    match parts.is_empty() {
+        true => None,
+        false => Some(parts),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(parts: &[&str]) -> Option<Vec<String>> {
+        Some(parts.iter().map(|part| part.to_string()).collect())
+    }
+
+    #[test]
+    fn relative_paths_are_split() {
+        assert_eq!(entry_path("a/b/c.txt"), path(&["a", "b", "c.txt"]));
+        assert_eq!(entry_path("a\\b\\c.txt"), path(&["a", "b", "c.txt"]));
+        assert_eq!(entry_path("dir/"), path(&["dir"]));
+        assert_eq!(entry_path("C:x"), path(&["C:x"]));
+    }
+
+    #[test]
+    fn parent_segments_are_rejected() {
+        assert_eq!(entry_path("../x"), None);
+        assert_eq!(entry_path("a/../../x"), None);
+        assert_eq!(entry_path("a/../x"), None);
+        assert_eq!(entry_path("a\\..\\x"), None);
+    }
+
+    #[test]
+    fn absolute_paths_are_rejected() {
+        assert_eq!(entry_path("/etc/passwd"), None);
+        assert_eq!(entry_path("C:\\x"), None);
+        assert_eq!(entry_path("C:/x"), None);
+        assert_eq!(entry_path("\\\\server\\x"), None);
+        assert_eq!(entry_path("//server/x"), None);
+    }
+
+    #[test]
+    fn control_characters_are_rejected() {
+        assert_eq!(entry_path("a/b\0c"), None);
+        assert_eq!(entry_path("a\nb"), None);
+        assert_eq!(entry_path("a/\u{7f}"), None);
+    }
+
+    #[test]
+    fn long_names_are_rejected() {
+        let name = "я".repeat(MAX_NAME_LENGTH);
+        assert_eq!(entry_path(&name), path(&[&name]));
+        assert_eq!(entry_path(&format!("{name}я")), None);
+    }
+
+    #[test]
+    fn empty_segments_are_skipped() {
+        assert_eq!(entry_path("a//b"), path(&["a", "b"]));
+        assert_eq!(entry_path("./a/./b/"), path(&["a", "b"]));
+        assert_eq!(entry_path(""), None);
+        assert_eq!(entry_path("."), None);
+        assert_eq!(entry_path("./"), None);
+    }
+}
@@ -0,0 +1,438 @@
+use std::io;
+
+use bytes::Bytes;
+use ctr::cipher::StreamCipher;
+use file_worker::cipher::{
+    self, Aes256Ctr, BlobCipher, BlobHeader, CipherError, HEADER_LENGTH, KEY_LENGTH,
+};
+use file_worker::storage::StorageStream;
+use futures::{stream, Stream, StreamExt};
+
+use crate::entity::blob::BlobFormatVersion;
+use crate::scalar::Id;
+use crate::utils::range::ByteRange;
+
+/// Расшифровка тела блоба по мере чтения из хранилища
+pub enum BlobDecryptor {
+    /// Блоб старого формата: CTR, уже перемотанный на начало выдаваемого диапазона
+    Ctr(Aes256Ctr),
+    Segmented(SegmentReader),
+}
+
+impl BlobDecryptor {
+    fn update(&mut self, mut chunk: Vec<u8>) -> Result<Vec<u8>, CipherError> {
+        match self {
+            BlobDecryptor::Ctr(cipher) => {
+                cipher.apply_keystream(&mut chunk);
+                Ok(chunk)
+            }
+            BlobDecryptor::Segmented(reader) => reader.push(&chunk),
+        }
+    }
+
+    fn finish(&self) -> Result<(), CipherError> {
+        match self {
+            BlobDecryptor::Ctr(_) => Ok(()),
+            BlobDecryptor::Segmented(reader) => reader.finish(),
+        }
+    }
+}
+
+/// Сборка сегментов из кусков тела и их проверка. Читать начинает с сегмента,
+/// в который попадает `start`, и отдает ровно `length` открытых байт
+pub struct SegmentReader {
+    cipher: BlobCipher,
+    index: u64,
+    skip: usize,
+    remaining: u64,
+    /// У пустого файла единственный сегмент пуст, но его тег тоже проверяется
+    verify_empty: bool,
+    buffer: Vec<u8>,
+}
+
+impl SegmentReader {
+    pub fn new(cipher: BlobCipher, start: u64, length: u64) -> Self {
+        let index = cipher.header().segment_index(start);
+        let skip = (start - index * cipher.header().segment_size as u64) as usize;
+        Self {
+            cipher,
+            index,
+            skip,
+            remaining: length,
+            verify_empty: length == 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, CipherError> {
+        self.buffer.extend_from_slice(chunk);
+        let mut plaintext = Vec::new();
+        while self.remaining > 0 || self.verify_empty {
+            let segment_len = self.cipher.segment_encrypted_len(self.index);
+            if self.buffer.len() < segment_len {
+                break;
+            }
+            let segment = self
+                .cipher
+                .decrypt_segment(self.index, &self.buffer[..segment_len])?;
+            self.buffer.drain(..segment_len);
+            let take = (segment.len() - self.skip).min(self.remaining as usize);
+            plaintext.extend_from_slice(&segment[self.skip..self.skip + take]);
+            self.remaining -= take as u64;
+            self.skip = 0;
+            self.index += 1;
+            self.verify_empty = false;
+        }
+        Ok(plaintext)
+    }
+
+    fn finish(&self) -> Result<(), CipherError> {
+        match self.remaining > 0 || self.verify_empty {
+            true => Err(CipherError::InvalidSegment(self.index)),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Заголовок блоба по формату, сохраненному в БД. У старого формата заголовка нет;
+/// у сегментного он обязателен, и подмена начала блоба не переводит чтение на CTR
+pub fn parse_header(
+    head: &[u8],
+    format_version: BlobFormatVersion,
+) -> Result<Option<BlobHeader>, CipherError> {
+    match format_version {
+        BlobFormatVersion::Legacy => Ok(None),
+        BlobFormatVersion::Segmented => {
+            BlobHeader::parse(head, cipher::BLOB_VERSION_SEGMENTED).map(Some)
+        }
+    }
+}
+
+/// Зашифрованные байты блоба с сегментами, в которые попадает диапазон открытых байт `range`
+pub fn encrypted_range(cipher: &BlobCipher, range: ByteRange) -> ByteRange {
+    let header = cipher.header();
+    let first = header.segment_index(range.start);
+    let last = header.segment_index(range.end);
+    ByteRange {
+        start: header.segment_offset(first),
+        end: header.segment_offset(last) + cipher.segment_encrypted_len(last) as u64 - 1,
+    }
+}
+
+/// Начало тела блоба, достаточное для разбора заголовка; у коротких блобов старого формата - все тело
+pub async fn read_head(body: &mut StorageStream) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(HEADER_LENGTH);
+    while head.len() < HEADER_LENGTH {
+        match body.next().await {
+            Some(chunk) => head.extend_from_slice(&chunk?),
+            None => break,
+        }
+    }
+    Ok(head)
+}
+
+/// Расшифровщик всего блоба по его началу `head` и остаток `head` после заголовка
+pub fn open_full(
+    head: Vec<u8>,
+    data_key: &[u8; KEY_LENGTH],
+    size: u64,
+    format_version: BlobFormatVersion,
+) -> Result<(Vec<u8>, BlobDecryptor), CipherError> {
+    match parse_header(&head, format_version)? {
+        Some(header) => {
+            let reader = SegmentReader::new(BlobCipher::new(data_key, header, size), 0, size);
+            Ok((
+                head[HEADER_LENGTH..].to_vec(),
+                BlobDecryptor::Segmented(reader),
+            ))
+        }
+        None => Ok((head, BlobDecryptor::Ctr(cipher::init_cipher(data_key)))),
+    }
+}
+
+/// Поток открытых байт для ответа. `prefix` - уже прочитанное из `body` после заголовка.
+/// Ошибка проверки сегмента обрывает ответ, а не отдает клиенту поврежденные данные
+pub fn decrypt_stream(
+    object_id: Id,
+    body: StorageStream,
+    prefix: Vec<u8>,
+    decryptor: BlobDecryptor,
+) -> impl Stream<Item = io::Result<Bytes>> {
+    stream::unfold(
+        Some((body, decryptor, Some(prefix))),
+        move |state| async move {
+            let (mut body, mut decryptor, prefix) = state?;
+            let chunk = match prefix {
+                Some(prefix) => Some(Ok(prefix)),
+                None => body
+                    .next()
+                    .await
+                    .map(|chunk| chunk.map(|chunk| chunk.to_vec())),
+            };
+            match chunk {
+                Some(Ok(chunk)) => match decryptor.update(chunk) {
+                    Ok(plaintext) => {
+                        Some((Ok(Bytes::from(plaintext)), Some((body, decryptor, None))))
+                    }
+                    Err(err) => {
+                        tracing::error!("Object {} failed integrity check: {}", object_id, err);
+                        Some((Err(io::Error::new(io::ErrorKind::InvalidData, err)), None))
+                    }
+                },
+                Some(Err(err)) => {
+                    tracing::error!("Failed to read object body: {}", err);
+                    Some((Err(err), None))
+                }
+                None => match decryptor.finish() {
+                    Ok(()) => None,
+                    Err(err) => {
+                        tracing::error!("Object {} is truncated: {}", object_id, err);
+                        Some((Err(io::Error::new(io::ErrorKind::InvalidData, err)), None))
+                    }
+                },
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use file_worker::cipher::{generate_data_key, SEGMENT_SIZE, TAG_LENGTH};
+
+    const S: u64 = SEGMENT_SIZE as u64;
+    const TAG: u64 = TAG_LENGTH as u64;
+    const HEADER: u64 = HEADER_LENGTH as u64;
+
+    fn plaintext(len: u64) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Блоб сегментного формата целиком: заголовок и сегменты
+    fn encrypt(cipher: &BlobCipher, plaintext: &[u8]) -> Vec<u8> {
+        let mut blob = cipher.header().to_bytes().to_vec();
+        for index in 0..cipher.segment_count() {
+            let start = (index * S) as usize;
+            let end = (start + S as usize).min(plaintext.len());
+            blob.extend(
+                cipher
+                    .encrypt_segment(index, &plaintext[start..end])
+                    .unwrap(),
+            );
+        }
+        blob
+    }
+
+    /// Диапазон так же, как при скачивании: нужные сегменты блоба приходят кусками по `chunk` байт
+    fn read_range(
+        cipher: &BlobCipher,
+        blob: &[u8],
+        range: ByteRange,
+        chunk: usize,
+    ) -> Result<Vec<u8>, CipherError> {
+        let encrypted = encrypted_range(cipher, range);
+        let mut reader = SegmentReader::new(cipher.clone(), range.start, range.length());
+        let mut plaintext = Vec::new();
+        for part in blob[encrypted.start as usize..=encrypted.end as usize].chunks(chunk) {
+            plaintext.extend(reader.push(part)?);
+        }
+        reader.finish()?;
+        Ok(plaintext)
+    }
+
+    /// Все тело через `open_full`, первые `head_len` байт - как прочитанные `read_head`
+    fn read_full(
+        blob: &[u8],
+        head_len: usize,
+        key: &[u8; KEY_LENGTH],
+        size: u64,
+        format_version: BlobFormatVersion,
+    ) -> Result<Vec<u8>, CipherError> {
+        let (prefix, mut decryptor) =
+            open_full(blob[..head_len].to_vec(), key, size, format_version)?;
+        let mut plaintext = decryptor.update(prefix)?;
+        plaintext.extend(decryptor.update(blob[head_len..].to_vec())?);
+        decryptor.finish()?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn encrypted_range_covers_whole_segments() {
+        let key = generate_data_key();
+        let size = 2 * S + 100;
+        let cipher = BlobCipher::new(&key, BlobHeader::generate(), size);
+        let range = |start, end| encrypted_range(&cipher, ByteRange { start, end });
+
+        assert_eq!(
+            range(0, 0),
+            ByteRange {
+                start: HEADER,
+                end: HEADER + S + TAG - 1
+            }
+        );
+        assert_eq!(
+            range(S - 1, S),
+            ByteRange {
+                start: HEADER,
+                end: HEADER + 2 * (S + TAG) - 1
+            }
+        );
+        assert_eq!(
+            range(S, 2 * S - 1),
+            ByteRange {
+                start: HEADER + S + TAG,
+                end: HEADER + 2 * (S + TAG) - 1
+            }
+        );
+        // Последний сегмент короче остальных
+        let tail = range(2 * S + 10, size - 1);
+        assert_eq!(tail.start, HEADER + 2 * (S + TAG));
+        assert_eq!(tail.end, cipher.header().encrypted_len(size) - 1);
+        assert_eq!(tail.length(), 100 + TAG);
+    }
+
+    #[test]
+    fn ranges_decrypt_across_segments() {
+        let key = generate_data_key();
+        let size = 2 * S + 100;
+        let data = plaintext(size);
+        let cipher = BlobCipher::new(&key, BlobHeader::generate(), size);
+        let blob = encrypt(&cipher, &data);
+
+        let ranges = [
+            (0, 0),
+            (5, S - 1),
+            (S - 10, S + 10),
+            (S, S),
+            (10, 2 * S + 50),
+            (2 * S, size - 1),
+            (size - 1, size - 1),
+            (0, size - 1),
+        ];
+        for (start, end) in ranges {
+            for chunk in [7, 4096, 1 << 20] {
+                let range = ByteRange { start, end };
+                assert_eq!(
+                    read_range(&cipher, &blob, range, chunk).unwrap(),
+                    data[start as usize..=end as usize],
+                    "range {start}-{end}, chunk {chunk}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_or_damaged_range_fails() {
+        let key = generate_data_key();
+        let size = S + 100;
+        let cipher = BlobCipher::new(&key, BlobHeader::generate(), size);
+        let mut blob = encrypt(&cipher, &plaintext(size));
+        let tail = ByteRange {
+            start: S,
+            end: size - 1,
+        };
+
+        let truncated = &blob[..blob.len() - 1];
+        let encrypted = encrypted_range(&cipher, tail);
+        let mut reader = SegmentReader::new(cipher.clone(), tail.start, tail.length());
+        assert!(reader
+            .push(&truncated[encrypted.start as usize..])
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            reader.finish(),
+            Err(CipherError::InvalidSegment(1))
+        ));
+
+        let last = blob.len() - 1;
+        blob[last] ^= 1;
+        assert!(matches!(
+            read_range(&cipher, &blob, tail, 4096),
+            Err(CipherError::Authentication(1))
+        ));
+    }
+
+    #[test]
+    fn full_read_uses_stored_format() {
+        let key = generate_data_key();
+        let size = S + 100;
+        let data = plaintext(size);
+        let cipher = BlobCipher::new(&key, BlobHeader::generate(), size);
+        let blob = encrypt(&cipher, &data);
+        for head_len in [HEADER_LENGTH, HEADER_LENGTH + 3] {
+            let read = read_full(&blob, head_len, &key, size, BlobFormatVersion::Segmented);
+            assert_eq!(read.unwrap(), data);
+        }
+
+        let mut legacy = data.clone();
+        cipher::init_cipher(&key).apply_keystream(&mut legacy);
+        let read = read_full(
+            &legacy,
+            HEADER_LENGTH,
+            &key,
+            size,
+            BlobFormatVersion::Legacy,
+        );
+        assert_eq!(read.unwrap(), data);
+
+        // Блоб без заголовка вместо сегментного не читается как CTR
+        assert!(matches!(
+            read_full(
+                &legacy,
+                HEADER_LENGTH,
+                &key,
+                size,
+                BlobFormatVersion::Segmented
+            ),
+            Err(CipherError::MissingHeader)
+        ));
+        let mut damaged = blob.clone();
+        damaged[1] ^= 1;
+        assert!(matches!(
+            read_full(
+                &damaged,
+                HEADER_LENGTH,
+                &key,
+                size,
+                BlobFormatVersion::Segmented
+            ),
+            Err(CipherError::MissingHeader)
+        ));
+        let mut other_version = blob.clone();
+        other_version[4] = 2;
+        assert!(matches!(
+            read_full(
+                &other_version,
+                HEADER_LENGTH,
+                &key,
+                size,
+                BlobFormatVersion::Segmented
+            ),
+            Err(CipherError::VersionMismatch {
+                expected: 1,
+                found: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn empty_file_checks_its_only_segment() {
+        let key = generate_data_key();
+        let cipher = BlobCipher::new(&key, BlobHeader::generate(), 0);
+        let blob = encrypt(&cipher, &[]);
+        assert_eq!(blob.len() as u64, HEADER + TAG);
+
+        let read = read_full(&blob, HEADER_LENGTH, &key, 0, BlobFormatVersion::Segmented);
+        assert!(read.unwrap().is_empty());
+        assert!(matches!(
+            read_full(
+                &blob[..HEADER_LENGTH],
+                HEADER_LENGTH,
+                &key,
+                0,
+                BlobFormatVersion::Segmented
+            ),
+            Err(CipherError::InvalidSegment(0))
+        ));
+    }
+}
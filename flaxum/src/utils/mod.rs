@@ -1 +1,5 @@
+pub mod archive;
+pub mod blob_stream;
 pub mod crypto;
+pub mod range;
+pub mod zip_stream;
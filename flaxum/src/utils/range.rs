@@ -0,0 +1,163 @@
+use std::ops::{Bound, RangeInclusive};
+
+use axum::http::HeaderValue;
+use axum_extra::headers::{Header, Range};
+
+/// Диапазон байт `start..=end` внутри файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Диапазон для чтения из хранилища
+    pub fn to_inclusive(&self) -> RangeInclusive<u64> {
+        self.start..=self.end
+    }
+}
+
+/// Что отдать клиенту в ответ на `Range`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestedRange {
+    Full,
+    Partial(ByteRange),
+    NotSatisfiable,
+}
+
+impl RequestedRange {
+    /// Поддерживается один диапазон; несколько диапазонов и заголовок с некорректным
+    /// диапазоном игнорируются, файл отдается целиком (200).
+    /// `bytes=-N` длиннее файла отдает весь файл
+    pub fn resolve(range: Option<&Range>, size: u64) -> Self {
+        let range = match range {
+            Some(range) => range,
+            None => return RequestedRange::Full,
+        };
+        // При длине `u64::MAX` пропускаются только некорректные диапазоны
+        if range.satisfiable_ranges(u64::MAX).count() != Self::spec_count(range) {
+            return RequestedRange::Full;
+        }
+        let mut ranges = range.satisfiable_ranges(size);
+        let (start, end) = match (ranges.next(), ranges.next()) {
+            (Some(single), None) => single,
+            (Some(_), Some(_)) => return RequestedRange::Full,
+            // Единственный корректный диапазон пропускается, только если это суффикс длиннее файла
+            (None, _) => (Bound::Included(0), Bound::Unbounded),
+        };
+
+        let last = size.saturating_sub(1);
+        let start = match start {
+            Bound::Included(start) => start,
+            Bound::Excluded(start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match end {
+            Bound::Included(end) => end.min(last),
+            Bound::Excluded(end) => end.saturating_sub(1).min(last),
+            Bound::Unbounded => last,
+        };
+        if size == 0 || start >= size || start > end {
+            return RequestedRange::NotSatisfiable;
+        }
+        RequestedRange::Partial(ByteRange { start, end })
+    }
+
+    /// Число диапазонов в заголовке, включая некорректные
+    fn spec_count(range: &Range) -> usize {
+        let mut values: Vec<HeaderValue> = Vec::new();
+        range.encode(&mut values);
+        values
+            .first()
+            .and_then(|value| value.to_str().ok())
+            .map_or(0, |value| value.split(',').count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(header: &str, size: u64) -> RequestedRange {
+        let value = HeaderValue::from_str(header).unwrap();
+        let range = Range::decode(&mut std::iter::once(&value)).unwrap();
+        RequestedRange::resolve(Some(&range), size)
+    }
+
+    fn partial(start: u64, end: u64) -> RequestedRange {
+        RequestedRange::Partial(ByteRange { start, end })
+    }
+
+    #[test]
+    fn no_header_is_full() {
+        assert_eq!(RequestedRange::resolve(None, 100), RequestedRange::Full);
+    }
+
+    #[test]
+    fn bounded_range() {
+        assert_eq!(resolve("bytes=10-19", 100), partial(10, 19));
+        assert_eq!(resolve("bytes=0-0", 100), partial(0, 0));
+        assert_eq!(resolve("bytes=90-200", 100), partial(90, 99));
+        assert_eq!(ByteRange { start: 10, end: 19 }.length(), 10);
+    }
+
+    #[test]
+    fn open_ended_range() {
+        assert_eq!(resolve("bytes=0-", 100), partial(0, 99));
+        assert_eq!(resolve("bytes=99-", 100), partial(99, 99));
+    }
+
+    #[test]
+    fn suffix_range() {
+        assert_eq!(resolve("bytes=-10", 100), partial(90, 99));
+        assert_eq!(resolve("bytes=-100", 100), partial(0, 99));
+        assert_eq!(resolve("bytes=-0", 100), RequestedRange::NotSatisfiable);
+    }
+
+    #[test]
+    fn suffix_longer_than_file_is_whole_file() {
+        assert_eq!(resolve("bytes=-500", 100), partial(0, 99));
+    }
+
+    #[test]
+    fn start_after_end_is_not_satisfiable() {
+        assert_eq!(resolve("bytes=20-10", 100), RequestedRange::NotSatisfiable);
+    }
+
+    #[test]
+    fn start_past_size_is_not_satisfiable() {
+        assert_eq!(resolve("bytes=100-", 100), RequestedRange::NotSatisfiable);
+        assert_eq!(
+            resolve("bytes=100-150", 100),
+            RequestedRange::NotSatisfiable
+        );
+        assert_eq!(
+            resolve("bytes=500-600", 100),
+            RequestedRange::NotSatisfiable
+        );
+    }
+
+    #[test]
+    fn multiple_ranges_are_full() {
+        assert_eq!(resolve("bytes=0-9,20-29", 100), RequestedRange::Full);
+    }
+
+    #[test]
+    fn malformed_header_is_full() {
+        assert_eq!(resolve("bytes=0-9,x-1", 100), RequestedRange::Full);
+        assert_eq!(resolve("bytes=x-1", 100), RequestedRange::Full);
+        assert_eq!(resolve("bytes=", 100), RequestedRange::Full);
+        assert_eq!(resolve("bytes=1-2-3", 100), RequestedRange::Full);
+    }
+
+    #[test]
+    fn zero_length_file() {
+        assert_eq!(resolve("bytes=0-", 0), RequestedRange::NotSatisfiable);
+        assert_eq!(resolve("bytes=-10", 0), RequestedRange::NotSatisfiable);
+        assert_eq!(resolve("bytes=0-0", 0), RequestedRange::NotSatisfiable);
+    }
+}
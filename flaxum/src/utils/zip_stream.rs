@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const ZIP64_END_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const END_SIGNATURE: u32 = 0x0605_4b50;
+
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+/// Размеры и CRC записаны в дескрипторе после данных
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+/// Имена в UTF-8
+const FLAG_UTF8: u16 = 0x0800;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+const DIRECTORY_ATTRIBUTE: u32 = 0x10;
+
+const MAX_U16: u64 = u16::MAX as u64;
+const MAX_U32: u64 = u32::MAX as u64;
+
+struct Entry {
+    name: Vec<u8>,
+    time: u16,
+    date: u16,
+    is_dir: bool,
+    zip64: bool,
+    offset: u64,
+    crc: u32,
+    size: u64,
+}
+
+/// Архив ZIP без сжатия, собираемый по мере передачи: заголовки отдаются байтами,
+/// данные записей передает вызывающий. Для записей от 4 ГиБ, смещений за 4 ГиБ
+/// и больше 65535 записей используется ZIP64
+#[derive(Default)]
+pub struct ZipWriter {
+    offset: u64,
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Заголовок папки, `name` без завершающего `/`
+    pub fn add_dir(&mut self, name: &str, modified: NaiveDateTime) -> Bytes {
+        self.start(format!("{name}/"), modified, true, 0)
+    }
+
+    /// Заголовок файла размером `size`; дальше идут данные и `finish_file`
+    pub fn start_file(&mut self, name: &str, modified: NaiveDateTime, size: u64) -> Bytes {
+        self.start(name.to_string(), modified, false, size)
+    }
+
+    /// Дескриптор данных текущего файла
+    pub fn finish_file(&mut self, crc: u32, size: u64) -> Bytes {
+        let Some(entry) = self.entries.last_mut() else {
+            return Bytes::new();
+        };
+        entry.crc = crc;
+        entry.size = size;
+
+        let mut buf = BytesMut::with_capacity(24);
+        buf.put_u32_le(DATA_DESCRIPTOR_SIGNATURE);
+        buf.put_u32_le(crc);
+        match entry.zip64 {
+            true => {
+                buf.put_u64_le(size);
+                buf.put_u64_le(size);
+            }
+            false => {
+                buf.put_u32_le(size as u32);
+                buf.put_u32_le(size as u32);
+            }
+        }
+        self.offset += size + buf.len() as u64;
+        buf.freeze()
+    }
+
+    /// Центральный каталог и концевые записи
+    pub fn finish(self) -> Bytes {
+        let mut buf = BytesMut::new();
+        let central_offset = self.offset;
+        for entry in &self.entries {
+            let size_overflow = entry.zip64 || entry.size >= MAX_U32;
+            let offset_overflow = entry.offset >= MAX_U32;
+            let mut extra = BytesMut::new();
+            if size_overflow {
+                extra.put_u64_le(entry.size);
+                extra.put_u64_le(entry.size);
+            }
+            if offset_overflow {
+                extra.put_u64_le(entry.offset);
+            }
+            let version = match size_overflow || offset_overflow {
+                true => VERSION_ZIP64,
+                false => VERSION_DEFAULT,
+            };
+
+            buf.put_u32_le(CENTRAL_HEADER_SIGNATURE);
+            buf.put_u16_le(version);
+            buf.put_u16_le(version);
+            buf.put_u16_le(Self::flags(entry.is_dir));
+            buf.put_u16_le(0);
+            buf.put_u16_le(entry.time);
+            buf.put_u16_le(entry.date);
+            buf.put_u32_le(entry.crc);
+            for _ in 0..2 {
+                buf.put_u32_le(match size_overflow {
+                    true => u32::MAX,
+                    false => entry.size as u32,
+                });
+            }
+            buf.put_u16_le(entry.name.len() as u16);
+            buf.put_u16_le(match extra.is_empty() {
+                true => 0,
+                false => extra.len() as u16 + 4,
+            });
+            buf.put_u16_le(0);
+            buf.put_u16_le(0);
+            buf.put_u16_le(0);
+            buf.put_u32_le(match entry.is_dir {
+                true => DIRECTORY_ATTRIBUTE,
+                false => 0,
+            });
+            buf.put_u32_le(match offset_overflow {
+                true => u32::MAX,
+                false => entry.offset as u32,
+            });
+            buf.put_slice(&entry.name);
+            if !extra.is_empty() {
+                buf.put_u16_le(ZIP64_EXTRA_ID);
+                buf.put_u16_le(extra.len() as u16);
+                buf.put_slice(&extra);
+            }
+        }
+
+        let central_size = buf.len() as u64;
+        let count = self.entries.len() as u64;
+        let zip64 = count >= MAX_U16 || central_offset >= MAX_U32 || central_size >= MAX_U32;
+        if zip64 {
+            let zip64_end_offset = central_offset + central_size;
+            buf.put_u32_le(ZIP64_END_SIGNATURE);
+            buf.put_u64_le(44);
+            buf.put_u16_le(VERSION_ZIP64);
+            buf.put_u16_le(VERSION_ZIP64);
+            buf.put_u32_le(0);
+            buf.put_u32_le(0);
+            buf.put_u64_le(count);
+            buf.put_u64_le(count);
+            buf.put_u64_le(central_size);
+            buf.put_u64_le(central_offset);
+
+            buf.put_u32_le(ZIP64_LOCATOR_SIGNATURE);
+            buf.put_u32_le(0);
+            buf.put_u64_le(zip64_end_offset);
+            buf.put_u32_le(1);
+        }
+        buf.put_u32_le(END_SIGNATURE);
+        buf.put_u16_le(0);
+        buf.put_u16_le(0);
+        for _ in 0..2 {
+            buf.put_u16_le(count.min(MAX_U16) as u16);
+        }
+        buf.put_u32_le(central_size.min(MAX_U32) as u32);
+        buf.put_u32_le(central_offset.min(MAX_U32) as u32);
+        buf.put_u16_le(0);
+        buf.freeze()
+    }
+
+    fn start(&mut self, name: String, modified: NaiveDateTime, is_dir: bool, size: u64) -> Bytes {
+        let (time, date) = dos_datetime(modified);
+        let zip64 = !is_dir && size >= MAX_U32;
+        let name = name.into_bytes();
+
+        let mut buf = BytesMut::with_capacity(30 + name.len() + 20);
+        buf.put_u32_le(LOCAL_HEADER_SIGNATURE);
+        buf.put_u16_le(match zip64 {
+            true => VERSION_ZIP64,
+            false => VERSION_DEFAULT,
+        });
+        buf.put_u16_le(Self::flags(is_dir));
+        buf.put_u16_le(0);
+        buf.put_u16_le(time);
+        buf.put_u16_le(date);
+        buf.put_u32_le(0);
+        for _ in 0..2 {
+            buf.put_u32_le(match zip64 {
+                true => u32::MAX,
+                false => 0,
+            });
+        }
+        buf.put_u16_le(name.len() as u16);
+        buf.put_u16_le(match zip64 {
+            true => 20,
+            false => 0,
+        });
+        buf.put_slice(&name);
+        if zip64 {
+            buf.put_u16_le(ZIP64_EXTRA_ID);
+            buf.put_u16_le(16);
+            buf.put_u64_le(0);
+            buf.put_u64_le(0);
+        }
+
+        self.entries.push(Entry {
+            name,
+            time,
+            date,
+            is_dir,
+            zip64,
+            offset: self.offset,
+            crc: 0,
+            size: 0,
+        });
+        self.offset += buf.len() as u64;
+        buf.freeze()
+    }
+
+    fn flags(is_dir: bool) -> u16 {
+        match is_dir {
+            true => FLAG_UTF8,
+            false => FLAG_UTF8 | FLAG_DATA_DESCRIPTOR,
+        }
+    }
+}
+
+/// Пути записей архива без совпадений: имена очищаются от разделителей и управляющих
+/// символов, при совпадении (без учета регистра) добавляется ` (N)`
+#[derive(Default)]
+pub struct ArchivePaths {
+    used: HashSet<String>,
+}
+
+impl ArchivePaths {
+    /// Свободный путь для `name` внутри папки `parent`; у файлов номер ставится перед расширением
+    pub fn add(&mut self, parent: Option<&str>, name: &str, is_file: bool) -> String {
+        let name = sanitize_name(name);
+        let (stem, ext) = match name.rsplit_once('.') {
+            Some((stem, ext)) if is_file && !stem.is_empty() => (stem, format!(".{ext}")),
+            _ => (name.as_str(), String::new()),
+        };
+        let mut n = 0;
+        loop {
+            let candidate = match n {
+                0 => name.clone(),
+                n => format!("{stem} ({n}){ext}"),
+            };
+            let path = match parent {
+                Some(parent) => format!("{parent}/{candidate}"),
+                None => candidate,
+            };
+            if self.used.insert(path.to_lowercase()) {
+                return path;
+            }
+            n += 1;
+        }
+    }
+}
+
+/// Имя объекта как один сегмент пути внутри архива
+pub fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    match name.trim() {
+        "" | "." | ".." => "_".to_string(),
+        _ => name,
+    }
+}
+
+/// Время и дата в формате MS-DOS; все, что раньше 1980 года, - 1 января 1980
+fn dos_datetime(time: NaiveDateTime) -> (u16, u16) {
+    if time.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let dos_time = (time.hour() << 11) | (time.minute() << 5) | (time.second() / 2);
+    let dos_date = (((time.year() - 1980) as u32).min(127) << 9) | (time.month() << 5) | time.day();
+    (dos_time as u16, dos_date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+    fn modified() -> NaiveDateTime {
+        chrono::DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .naive_utc()
+    }
+
+    /// Архив из папки и файлов `(путь, содержимое)`, записанный как при скачивании
+    fn write_archive(zip: &mut ZipWriter, dir: &str, files: &[(String, &[u8])]) -> Vec<u8> {
+        let mut out = zip.add_dir(dir, modified()).to_vec();
+        for (name, content) in files {
+            out.extend(zip.start_file(name, modified(), content.len() as u64));
+            out.extend_from_slice(content);
+            out.extend(zip.finish_file(crc32fast::hash(content), content.len() as u64));
+        }
+        out
+    }
+
+    /// Записи архива: имя, размер, CRC и содержимое
+    fn read_archive<R: Read + Seek>(reader: R) -> Vec<(String, u64, u32, Vec<u8>)> {
+        let mut archive = zip::ZipArchive::new(reader).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut content = Vec::new();
+                file.read_to_end(&mut content).unwrap();
+                (file.name().to_string(), file.size(), file.crc32(), content)
+            })
+            .collect()
+    }
+
+    fn expected(dir: &str, files: &[(String, &[u8])]) -> Vec<(String, u64, u32, Vec<u8>)> {
+        let mut entries = vec![(format!("{dir}/"), 0, 0, Vec::new())];
+        for (name, content) in files {
+            let size = content.len() as u64;
+            entries.push((
+                name.clone(),
+                size,
+                crc32fast::hash(content),
+                content.to_vec(),
+            ));
+        }
+        entries
+    }
+
+    #[test]
+    fn archive_reads_back() {
+        let mut paths = ArchivePaths::default();
+        let dir = paths.add(None, "docs", false);
+        let files: Vec<(String, &[u8])> = vec![
+            (paths.add(Some(&dir), "report.txt", true), b"first"),
+            (paths.add(Some(&dir), "Report.TXT", true), b"second"),
+            (paths.add(Some(&dir), "empty", true), b""),
+        ];
+        assert_eq!(files[1].0, "docs/Report (1).TXT");
+
+        let mut zip = ZipWriter::new();
+        let mut out = write_archive(&mut zip, &dir, &files);
+        out.extend(zip.finish());
+        assert_eq!(read_archive(Cursor::new(out)), expected(&dir, &files));
+    }
+
+    #[test]
+    fn archive_past_4gib_uses_zip64() {
+        // Записи начинаются за 4 ГиБ: архив пишется в разреженный файл с этого смещения
+        let start = MAX_U32 + 1024;
+        let files: Vec<(String, &[u8])> = vec![
+            ("big/a.txt".to_string(), b"zip64 offset"),
+            ("big/A.txt".to_string(), b"same name, other case"),
+        ];
+        let mut zip = ZipWriter {
+            offset: start,
+            ..Default::default()
+        };
+        let mut out = write_archive(&mut zip, "big", &files);
+        out.extend(zip.finish());
+
+        let mut file = tempfile().unwrap();
+        file.seek(SeekFrom::Start(start)).unwrap();
+        file.write_all(&out).unwrap();
+        assert_eq!(read_archive(file), expected("big", &files));
+    }
+
+    fn tempfile() -> std::io::Result<std::fs::File> {
+        let path = std::env::temp_dir().join(format!("flaxum-zip-{}", crate::scalar::Id::new_v4()));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        std::fs::remove_file(&path)?;
+        Ok(file)
+    }
+}